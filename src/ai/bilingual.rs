@@ -0,0 +1,170 @@
+//! Bilingual commit messages (`language = "en+zh"`).
+//!
+//! The model is asked to emit one section per language, each introduced by a `[code]`
+//! marker line. The sections are then validated and re-assembled in a fixed order
+//! (first language, blank line, second language), whatever order the model used.
+
+use anyhow::{Result, bail};
+
+use super::language;
+use crate::config::BilingualStyle;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bilingual {
+    pub primary: String,
+    pub secondary: String,
+    pub style: BilingualStyle,
+}
+
+impl Bilingual {
+    /// Split a `primary+secondary` language code. Returns `None` for a single language, and fails
+    /// for anything `parse_language_code` rejects (`en+en`, `en+`, `en+zh+ja`).
+    pub fn parse(language: &str, style: BilingualStyle) -> Result<Option<Self>> {
+        let code = language::parse_language_code(language).map_err(anyhow::Error::msg)?;
+        Ok(code.split_once('+').map(|(primary, secondary)| Self {
+            primary: primary.to_string(),
+            secondary: secondary.to_string(),
+            style,
+        }))
+    }
+
+    /// Output contract appended to the user prompt; `assemble` relies on these markers.
+    pub fn format_instructions(&self) -> String {
+        let (p, s) = (&self.primary, &self.secondary);
+        let (p_name, s_name) = (language::display_name(p), language::display_name(s));
+        match self.style {
+            BilingualStyle::Stacked => format!(
+                "OUTPUT FORMAT (follow exactly):\n\
+                 Write the complete commit message twice: first in {p_name}, then the same message in {s_name}.\n\
+                 Start each version with a marker line containing only its language code in square brackets:\n\
+                 [{p}]\n<commit message in {p_name}>\n[{s}]\n<the same commit message in {s_name}>\n\
+                 Use the same Conventional Commits type and scope in both versions. \
+                 Output nothing outside these two sections."
+            ),
+            BilingualStyle::SubjectBody => format!(
+                "OUTPUT FORMAT (follow exactly):\n\
+                 Write the subject line in {p_name} and the body in {s_name}.\n\
+                 Start each part with a marker line containing only its language code in square brackets:\n\
+                 [{p}]\n<single subject line in {p_name}, type(scope): description, under 72 characters>\n\
+                 [{s}]\n<body in {s_name}: a few lines explaining what changed and why>\n\
+                 Output nothing outside these two sections."
+            ),
+        }
+    }
+
+    /// Parse the model output into its two sections and join them in a fixed order.
+    ///
+    /// Fails when either section is missing or empty, or when the subject-body layout
+    /// got a multi-line subject. Returns the message and any language-check warnings.
+    pub fn assemble(&self, raw: &str) -> Result<(String, Vec<String>)> {
+        let mut primary: Option<Vec<&str>> = None;
+        let mut secondary: Option<Vec<&str>> = None;
+        let mut current: Option<&mut Vec<&str>> = None;
+
+        for line in raw.lines() {
+            if let Some(code) = marker_code(line) {
+                current = if code == self.primary {
+                    Some(primary.get_or_insert_with(Vec::new))
+                } else if code == self.secondary {
+                    Some(secondary.get_or_insert_with(Vec::new))
+                } else {
+                    None
+                };
+                continue;
+            }
+            if let Some(section) = current.as_mut() {
+                section.push(line);
+            }
+        }
+
+        let primary = section_text(primary);
+        let secondary = section_text(secondary);
+        for (text, code) in [(&primary, &self.primary), (&secondary, &self.secondary)] {
+            if text.is_empty() {
+                bail!(
+                    "Bilingual message is missing the {} part. Model output was:\n{raw}",
+                    language::display_name(code)
+                );
+            }
+        }
+        if self.style == BilingualStyle::SubjectBody && primary.lines().count() > 1 {
+            bail!("Bilingual subject must be a single line, got:\n{primary}");
+        }
+
+        let warnings = [(&primary, &self.primary), (&secondary, &self.secondary)]
+            .into_iter()
+            .filter_map(|(text, code)| language::check_language(text, code))
+            .collect();
+
+        Ok((format!("{primary}\n\n{secondary}"), warnings))
+    }
+}
+
+/// `[en]`, `[ZH]` or `[pt_BR]` on a line of its own.
+fn marker_code(line: &str) -> Option<String> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let code = language::normalize_language_code(inner);
+    (!code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')).then_some(code)
+}
+
+fn section_text(lines: Option<Vec<&str>>) -> String {
+    let text = lines.unwrap_or_default().join("\n");
+    let text = text.trim();
+    // Some models wrap each section in a code fence.
+    let text = text.strip_prefix("```").and_then(|t| t.strip_suffix("```")).unwrap_or(text);
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn en_zh(style: BilingualStyle) -> Bilingual {
+        Bilingual::parse("en+zh", style).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse_single_language_is_none() {
+        assert!(Bilingual::parse("en", BilingualStyle::Stacked).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_pairs() {
+        for code in ["en+en", "EN+en", "en+", "+zh", "en+zh+ja"] {
+            assert!(Bilingual::parse(code, BilingualStyle::Stacked).is_err(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_assemble_stacked_reorders() {
+        let raw = "[zh]\nfeat: 添加多语言支持\n\n[en]\nfeat: add multi-language support\n";
+        let (message, warnings) = en_zh(BilingualStyle::Stacked).assemble(raw).unwrap();
+        assert_eq!(message, "feat: add multi-language support\n\nfeat: 添加多语言支持");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_assemble_missing_part_fails() {
+        let raw = "[en]\nfeat: add multi-language support\n";
+        let err = en_zh(BilingualStyle::Stacked).assemble(raw).unwrap_err();
+        assert!(err.to_string().contains("Simplified Chinese"));
+    }
+
+    #[test]
+    fn test_assemble_subject_body() {
+        let bilingual = en_zh(BilingualStyle::SubjectBody);
+        let raw = "[en]\nfix(auth): handle token refresh race\n[zh]\n刷新令牌时加锁，避免并发请求重复刷新。\n";
+        let (message, _) = bilingual.assemble(raw).unwrap();
+        assert_eq!(message, "fix(auth): handle token refresh race\n\n刷新令牌时加锁，避免并发请求重复刷新。");
+
+        let raw = "[en]\nfix: one\nsecond line\n[zh]\n正文内容\n";
+        assert!(bilingual.assemble(raw).is_err());
+    }
+
+    #[test]
+    fn test_assemble_reports_wrong_language() {
+        let raw = "[en]\nfeat: add support\n[zh]\nfeat: add support for languages\n";
+        let (_, warnings) = en_zh(BilingualStyle::Stacked).assemble(raw).unwrap();
+        assert_eq!(warnings.len(), 1);
+    }
+}
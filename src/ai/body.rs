@@ -0,0 +1,261 @@
+//! `--body` mode: a subject line followed by a wrapped body explaining what changed and why.
+//!
+//! The prompt asks the model for the layout, but models do not reliably respect widths
+//! or counts, so `enforce` re-wraps the body and trims bullets after generation.
+
+use crate::config::BodyConfig;
+
+/// Output contract appended to the user prompt.
+pub fn format_instructions(config: &BodyConfig) -> String {
+    let footers = if config.footers {
+        "Optionally end with footers after a blank line, one per line as `Token: value` \
+         (e.g. `BREAKING CHANGE: ...`, `Refs: #123`). Only add footers that the diff justifies."
+    } else {
+        "Do not add footers."
+    };
+    format!(
+        "OUTPUT FORMAT (follow exactly):\n\
+         Line 1: the subject, type(scope): description, under 72 characters.\n\
+         Line 2: blank.\n\
+         Then a body explaining WHAT changed and WHY, as bullet points (`- `) grouped by file or concern. \
+         When there are several groups, put a short heading line ending with `:` before each group. \
+         Use at most {} bullets in total and keep lines under {} characters.\n\
+         {footers}",
+        config.max_bullets, config.wrap_width
+    )
+}
+
+enum Item {
+    Heading(String),
+    Bullet(String),
+    Paragraph(String),
+    Break,
+}
+
+/// Re-wrap and trim a generated message to the configured body format.
+///
+/// Returns the formatted message and warnings about anything that had to be dropped or
+/// could not be fixed (missing body, over-long subject).
+pub fn enforce(raw: &str, config: &BodyConfig) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+    let text = raw.trim();
+    let text = text.strip_prefix("```").and_then(|t| t.strip_suffix("```")).unwrap_or(text).trim();
+
+    let mut lines = text.lines();
+    let subject = lines.next().unwrap_or_default().trim().to_string();
+    if subject.chars().count() > 72 {
+        warnings.push(format!("subject is {} characters (limit 72)", subject.chars().count()));
+    }
+
+    let rest: Vec<&str> = lines.collect();
+    let (body_lines, footer_lines) = split_footers(&rest);
+
+    let mut items = parse_items(body_lines);
+    let bullet_count = items.iter().filter(|i| matches!(i, Item::Bullet(_))).count();
+    if bullet_count > config.max_bullets {
+        let mut seen = 0;
+        items.retain(|item| match item {
+            Item::Bullet(_) => {
+                seen += 1;
+                seen <= config.max_bullets
+            }
+            _ => seen < config.max_bullets,
+        });
+        warnings.push(format!(
+            "dropped {} bullets beyond max_bullets = {}",
+            bullet_count - config.max_bullets,
+            config.max_bullets
+        ));
+    }
+
+    let body = render_items(&items, config.wrap_width.max(20));
+    if body.is_empty() {
+        warnings.push("the model did not produce a body".to_string());
+    }
+
+    let footers = if footer_lines.is_empty() || config.footers {
+        footer_lines.iter().map(|l| l.trim()).collect::<Vec<_>>().join("\n")
+    } else {
+        warnings.push("dropped footers (body.footers = false)".to_string());
+        String::new()
+    };
+
+    let parts: Vec<String> = [subject, body, footers].into_iter().filter(|part| !part.is_empty()).collect();
+    let message = parts.join("\n\n");
+    (message, warnings)
+}
+
+/// Footers are the last paragraph when every line in it looks like `Token: value`.
+fn split_footers<'a>(lines: &'a [&'a str]) -> (&'a [&'a str], &'a [&'a str]) {
+    let end = lines.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |i| i + 1);
+    let lines = &lines[..end];
+    let start = lines.iter().rposition(|l| l.trim().is_empty()).map_or(0, |i| i + 1);
+    let last = &lines[start..];
+    if start > 0 && !last.is_empty() && last.iter().all(|l| is_footer_line(l)) {
+        (&lines[..start], last)
+    } else {
+        (lines, &[])
+    }
+}
+
+pub(crate) fn is_footer_line(line: &str) -> bool {
+    let line = line.trim();
+    if line.starts_with("BREAKING CHANGE: ") || line.starts_with("BREAKING-CHANGE: ") {
+        return true;
+    }
+    let Some(idx) = line.find(": ").or_else(|| line.find(" #")) else {
+        return false;
+    };
+    let token = &line[..idx];
+    !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn parse_items(lines: &[&str]) -> Vec<Item> {
+    let mut items = Vec::new();
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if !matches!(items.last(), None | Some(Item::Break)) {
+                items.push(Item::Break);
+            }
+            continue;
+        }
+        if let Some(text) = ["- ", "* ", "• "].iter().find_map(|m| trimmed.strip_prefix(m)) {
+            items.push(Item::Bullet(text.trim().to_string()));
+        } else if line.starts_with(char::is_whitespace)
+            && let Some(Item::Bullet(text)) = items.last_mut()
+        {
+            text.push(' ');
+            text.push_str(trimmed);
+        } else if trimmed.ends_with(':') {
+            items.push(Item::Heading(trimmed.to_string()));
+        } else if let Some(Item::Paragraph(text)) = items.last_mut() {
+            text.push(' ');
+            text.push_str(trimmed);
+        } else {
+            items.push(Item::Paragraph(trimmed.to_string()));
+        }
+    }
+    while matches!(items.last(), Some(Item::Break)) {
+        items.pop();
+    }
+    items
+}
+
+fn render_items(items: &[Item], width: usize) -> String {
+    let mut out: Vec<String> = Vec::new();
+    for item in items {
+        match item {
+            Item::Heading(text) => {
+                if !matches!(out.last().map(String::as_str), None | Some("")) {
+                    out.push(String::new());
+                }
+                out.push(text.clone());
+            }
+            Item::Bullet(text) => {
+                for (i, line) in wrap(text, width - 2).into_iter().enumerate() {
+                    out.push(if i == 0 { format!("- {line}") } else { format!("  {line}") });
+                }
+            }
+            Item::Paragraph(text) => out.extend(wrap(text, width)),
+            Item::Break => out.push(String::new()),
+        }
+    }
+    let mut body = out.join("\n");
+    while body.contains("\n\n\n") {
+        body = body.replace("\n\n\n", "\n\n");
+    }
+    body.trim().to_string()
+}
+
+/// East Asian wide characters take two columns and may be broken between any two of them.
+pub(crate) fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6)
+}
+
+pub(crate) fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+/// Greedy word wrap by display width. Words longer than `width` are kept whole
+/// unless they contain wide characters, which can be split.
+pub(crate) fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut pieces: Vec<String> = Vec::new();
+        for c in word.chars() {
+            match pieces.last_mut() {
+                Some(last) if !is_wide(c) && !last.chars().last().is_some_and(is_wide) => last.push(c),
+                _ => pieces.push(c.to_string()),
+            }
+        }
+        for (i, piece) in pieces.iter().enumerate() {
+            let sep = if i == 0 && !line.is_empty() { " " } else { "" };
+            if !line.is_empty() && display_width(&line) + sep.len() + display_width(piece) > width {
+                lines.push(std::mem::take(&mut line));
+            } else {
+                line.push_str(sep);
+            }
+            line.push_str(piece);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_bullets: usize, footers: bool) -> BodyConfig {
+        BodyConfig { enabled: true, wrap_width: 40, max_bullets, footers }
+    }
+
+    #[test]
+    fn test_wrap_respects_width() {
+        let lines = wrap("the quick brown fox jumps over the lazy dog again and again", 20);
+        assert!(lines.iter().all(|l| l.len() <= 20));
+        assert_eq!(lines.join(" "), "the quick brown fox jumps over the lazy dog again and again");
+    }
+
+    #[test]
+    fn test_wrap_cjk() {
+        let lines = wrap("刷新令牌时加锁避免并发请求重复刷新", 10);
+        assert!(lines.iter().all(|l| display_width(l) <= 10));
+        assert_eq!(lines.concat(), "刷新令牌时加锁避免并发请求重复刷新");
+    }
+
+    #[test]
+    fn test_enforce_wraps_bullets_and_keeps_footers() {
+        let raw = "fix(client): retry on timeout\n\n\
+                   - Retry idempotent requests up to three times when the connection times out\n\n\
+                   Refs: #42";
+        let (message, warnings) = enforce(raw, &config(8, true));
+        assert_eq!(
+            message,
+            "fix(client): retry on timeout\n\n\
+             - Retry idempotent requests up to three\n  times when the connection times out\n\n\
+             Refs: #42"
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_enforce_limits_bullets_and_drops_footers() {
+        let raw = "feat: add things\n\nsrc/a.rs:\n- one\n- two\nsrc/b.rs:\n- three\n\nBREAKING CHANGE: removed x";
+        let (message, warnings) = enforce(raw, &config(2, false));
+        assert_eq!(message, "feat: add things\n\nsrc/a.rs:\n- one\n- two");
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_enforce_warns_without_body() {
+        let (message, warnings) = enforce("chore: bump version", &config(8, true));
+        assert_eq!(message, "chore: bump version");
+        assert_eq!(warnings, vec!["the model did not produce a body".to_string()]);
+    }
+}
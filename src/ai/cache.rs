@@ -0,0 +1,205 @@
+//! Local cache of model responses. Generating again for the same staged tree with the same
+//! prompts, model and options reuses the earlier response instead of paying for it twice.
+
+use anyhow::Result;
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::config::CacheConfig;
+
+/// Whether one invocation reads and writes the response cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Reuse a cached response when there is one; cache new ones.
+    #[default]
+    Use,
+    /// `--regenerate`: always ask the model, and cache the new response.
+    Refresh,
+    /// `--no-cache`: neither read nor write the cache.
+    Off,
+}
+
+/// Builds the hash that identifies a request. Every part is length-prefixed so adjacent
+/// values cannot run together.
+#[derive(Debug, Default)]
+pub struct CacheKey {
+    data: Vec<u8>,
+}
+
+impl CacheKey {
+    pub fn part(mut self, name: &str, value: impl Display) -> Self {
+        let value = value.to_string();
+        self.data.extend_from_slice(format!("{name} {}\n", value.len()).as_bytes());
+        self.data.extend_from_slice(value.as_bytes());
+        self.data.push(b'\n');
+        self
+    }
+
+    pub fn finish(&self) -> Result<String> {
+        Ok(Oid::hash_object(ObjectType::Blob, &self.data)?.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    model: String,
+    response: String,
+}
+
+/// Responses stored one file per key, expired by age and evicted oldest first.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_entries: usize,
+    policy: CachePolicy,
+}
+
+impl ResponseCache {
+    /// The cache under `$XDG_CACHE_HOME/ai-commit/responses`, or `None` when caching is
+    /// disabled by the config or `policy`.
+    pub fn open(config: &CacheConfig, policy: CachePolicy) -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("ai-commit").join("responses");
+        Self::in_dir(dir, config, policy)
+    }
+
+    pub fn in_dir(dir: PathBuf, config: &CacheConfig, policy: CachePolicy) -> Option<Self> {
+        if !config.enabled || policy == CachePolicy::Off {
+            return None;
+        }
+        Some(Self { dir, ttl: Duration::from_secs(config.ttl_hours * 3600), max_entries: config.max_entries, policy })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// The cached response for `key`, unless it has expired or the policy asks for a new one.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.get_at(key, SystemTime::now())
+    }
+
+    pub fn put(&self, key: &str, model: &str, response: &str) -> Result<()> {
+        self.put_at(key, model, response, SystemTime::now())
+    }
+
+    fn get_at(&self, key: &str, now: SystemTime) -> Option<String> {
+        if self.policy == CachePolicy::Refresh {
+            return None;
+        }
+        let path = self.path(key);
+        if self.is_expired(&path, now) {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let entry: Entry = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+        Some(entry.response)
+    }
+
+    /// Store `response` as written at `now`; the file's modification time is its age.
+    fn put_at(&self, key: &str, model: &str, response: &str, now: SystemTime) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = Entry { model: model.to_string(), response: response.to_string() };
+        // Write then rename, so a concurrent reader never sees half an entry
+        let tmp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        let file = fs::File::create(&tmp)?;
+        serde_json::to_writer(&file, &entry)?;
+        file.set_modified(now)?;
+        drop(file);
+        fs::rename(&tmp, self.path(key))?;
+        self.prune(now)
+    }
+
+    fn is_expired(&self, path: &Path, now: SystemTime) -> bool {
+        age(path, now).is_none_or(|age| age > self.ttl)
+    }
+
+    /// Remove expired entries, then the oldest ones beyond `max_entries`.
+    fn prune(&self, now: SystemTime) -> Result<()> {
+        let mut entries = Vec::new();
+        for item in fs::read_dir(&self.dir)? {
+            let path = item?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match age(&path, now) {
+                Some(age) if age <= self.ttl => entries.push((age, path)),
+                _ => fs::remove_file(&path)?,
+            }
+        }
+        entries.sort();
+        for (_, path) in entries.iter().skip(self.max_entries) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn age(path: &Path, now: SystemTime) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(now.duration_since(modified).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn temp_cache(dir: &TempDir, name: &str, config: &CacheConfig, policy: CachePolicy) -> ResponseCache {
+        ResponseCache::in_dir(dir.join(name), config, policy).unwrap()
+    }
+
+    #[test]
+    fn test_key_depends_on_every_part() {
+        let key =
+            |model: &str, diff: &str| CacheKey::default().part("model", model).part("diff", diff).finish().unwrap();
+        assert_eq!(key("m1", "abc"), key("m1", "abc"));
+        assert_ne!(key("m1", "abc"), key("m2", "abc"));
+        assert_ne!(key("m1", "abc"), key("m1", "abd"));
+        // Moving text across a part boundary changes the key
+        assert_ne!(key("m1", "abc"), key("m1a", "bc"));
+    }
+
+    #[test]
+    fn test_get_put_and_policies() {
+        let config = CacheConfig::default();
+        let dir = TempDir::new("cache-policy");
+        let cache = temp_cache(&dir, "policy", &config, CachePolicy::Use);
+        assert_eq!(cache.get("k1"), None);
+        cache.put("k1", "m1", "feat: add x").unwrap();
+        assert_eq!(cache.get("k1").as_deref(), Some("feat: add x"));
+
+        let refresh = ResponseCache::in_dir(cache.dir.clone(), &config, CachePolicy::Refresh).unwrap();
+        assert_eq!(refresh.get("k1"), None);
+        assert!(ResponseCache::in_dir(cache.dir.clone(), &config, CachePolicy::Off).is_none());
+        let disabled = CacheConfig { enabled: false, ..CacheConfig::default() };
+        assert!(ResponseCache::in_dir(cache.dir.clone(), &disabled, CachePolicy::Use).is_none());
+    }
+
+    #[test]
+    fn test_limits() {
+        let dir = TempDir::new("cache-limits");
+        let start = SystemTime::now();
+        let hours = |n: u64| start + Duration::from_secs(n * 3600);
+
+        let expiring =
+            temp_cache(&dir, "ttl", &CacheConfig { ttl_hours: 2, ..CacheConfig::default() }, CachePolicy::Use);
+        expiring.put_at("k1", "m1", "feat: add x", start).unwrap();
+        assert_eq!(expiring.get_at("k1", hours(2)).as_deref(), Some("feat: add x"));
+        assert_eq!(expiring.get_at("k1", hours(3)), None);
+        assert!(!expiring.path("k1").exists());
+
+        let small =
+            temp_cache(&dir, "size", &CacheConfig { max_entries: 2, ..CacheConfig::default() }, CachePolicy::Use);
+        for (n, key) in ["k1", "k2", "k3"].into_iter().enumerate() {
+            small.put_at(key, "m1", key, hours(n as u64)).unwrap();
+        }
+        assert_eq!(small.get_at("k1", hours(2)), None);
+        assert_eq!(small.get_at("k3", hours(2)).as_deref(), Some("k3"));
+        assert_eq!(fs::read_dir(&small.dir).unwrap().count(), 2);
+    }
+}
@@ -2,8 +2,12 @@ use anyhow::Context;
 use log::{debug, error};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
 use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
 
+use super::cache::CacheKey;
+use super::ledger::UsageLedger;
 use crate::config::ApiConfig;
 
 #[derive(Serialize, Debug)]
@@ -27,12 +31,21 @@ struct ChatRequest {
     /// DeepSeek V4 defaults to thinking mode which wastes tokens on reasoning.
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize, Debug)]
+struct StreamOptions {
+    include_usage: bool,
 }
 
 /// API 响应中的 message 对象。
 /// - `content` uses `#[serde(default)]` for `null` compatibility.
 /// - `reasoning_content` is populated by DeepSeek V4 thinking mode (separate from content).
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 struct ChatMessage {
     #[serde(default)]
     content: String,
@@ -49,6 +62,93 @@ struct Choice {
 #[derive(Deserialize)]
 struct ChatResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// One `data:` event of a streamed response.
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct StreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+}
+
+/// Assembles a streamed (server-sent events) response into the message a non-streamed
+/// request would have returned.
+#[derive(Default)]
+struct StreamState {
+    message: ChatMessage,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+    done: bool,
+}
+
+impl StreamState {
+    /// Take one line of the event stream; returns the content it adds, if any.
+    fn push_line(&mut self, line: &str) -> anyhow::Result<Option<String>> {
+        let Some(data) = line.trim_end_matches('\r').strip_prefix("data:").map(str::trim) else {
+            return Ok(None);
+        };
+        if data == "[DONE]" {
+            self.done = true;
+            return Ok(None);
+        }
+        let chunk: StreamChunk = serde_json::from_str(data)
+            .map_err(|e| anyhow::anyhow!("Failed to parse streamed API response: {e}. Event: {data}"))?;
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return Ok(None);
+        };
+        if choice.finish_reason.is_some() {
+            self.finish_reason = choice.finish_reason;
+        }
+        if let Some(reasoning) = choice.delta.reasoning_content {
+            self.message.reasoning_content.get_or_insert_default().push_str(&reasoning);
+        }
+        let content = choice.delta.content.filter(|c| !c.is_empty());
+        if let Some(content) = &content {
+            self.message.content.push_str(content);
+        }
+        Ok(content)
+    }
+}
+
+/// Token counts reported by the API, summed over the requests a client has sent.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
 }
 
 /// 从推理模型响应中剥离 `<think` 思考内容。
@@ -80,11 +180,57 @@ fn strip_thinking_content(content: &str) -> String {
     trimmed.to_string()
 }
 
+/// Replace each `{name}` in `template` with its value in one pass, so a value that itself
+/// contains `{diff}` or another placeholder (a commit subject, a diff line) is left alone.
+/// Unknown placeholders are kept as written.
+fn fill_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            vars.iter().find(|(name, _)| *name == &after[..end]).map(|(name, value)| (name.len(), value))
+        });
+        match value {
+            Some((len, value)) => {
+                out.push_str(value);
+                rest = &after[len + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parse a JSON object out of a model response, tolerating surrounding prose or code fences.
+pub fn parse_json_response<T: serde::de::DeserializeOwned>(raw: &str) -> anyhow::Result<T> {
+    let json = match (raw.find('{'), raw.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &raw[start..=end],
+        _ => anyhow::bail!("AI response did not contain a JSON object:\n{raw}"),
+    };
+    serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Failed to parse AI response as JSON: {e}\n{raw}"))
+}
+
 pub struct AiClient {
     client: Client,
     config: ApiConfig,
     system_prompt: String,
     user_prompt_template: String,
+    /// Further input for the model appended to every user message, after the filled template.
+    context: Vec<String>,
+    /// Extra output-format instructions appended to every user message (e.g. bilingual section markers).
+    output_format: Vec<String>,
+    /// Tokens used so far; `None` until the API reports usage.
+    usage: Mutex<Option<Usage>>,
+    /// Receives the response text as it is generated; requests are streamed when set.
+    stream: Option<UnboundedSender<String>>,
+    /// Where the usage is recorded when the client is dropped, and budgets are checked.
+    ledger: Option<UsageLedger>,
 }
 
 impl AiClient {
@@ -94,7 +240,79 @@ impl AiClient {
             .timeout(Duration::from_secs(120))
             .build()
             .context("Failed to build HTTP client — TLS backend initialization error")?;
-        Ok(AiClient { client, config, system_prompt, user_prompt_template })
+        Ok(AiClient {
+            client,
+            config,
+            system_prompt,
+            user_prompt_template,
+            context: Vec::new(),
+            output_format: Vec::new(),
+            usage: Mutex::new(None),
+            stream: None,
+            ledger: None,
+        })
+    }
+
+    /// Add input the template has no placeholder for, such as the message being amended.
+    /// It is sent before any output-format instructions, which stay last.
+    pub fn with_context(mut self, context: String) -> Self {
+        self.context.push(context);
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: String) -> Self {
+        self.output_format.push(output_format);
+        self
+    }
+
+    /// Stream responses, sending each piece of generated text to `sink` as it arrives. The
+    /// pieces are raw model output; the returned message is still the one to use.
+    pub fn with_stream(mut self, sink: UnboundedSender<String>) -> Self {
+        self.stream = Some(sink);
+        self
+    }
+
+    /// Check `ledger`'s budgets before each request, and record the tokens used in it.
+    pub fn with_ledger(mut self, ledger: Option<UsageLedger>) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// `key` extended with everything that shapes this client's requests: the prompts,
+    /// context, output-format instructions, model and sampling options.
+    pub fn cache_key(&self, key: CacheKey) -> CacheKey {
+        let mut key = key
+            .part("endpoint", &self.config.endpoint)
+            .part("model", &self.config.model)
+            .part("max_tokens", format!("{:?}", self.config.max_tokens))
+            .part("temperature", format!("{:?}", self.config.temperature))
+            .part("system", &self.system_prompt)
+            .part("template", &self.user_prompt_template);
+        for context in &self.context {
+            key = key.part("context", context);
+        }
+        for format in &self.output_format {
+            key = key.part("format", format);
+        }
+        key
+    }
+
+    /// Tokens used by the requests sent so far, if the API reported them.
+    pub fn usage(&self) -> Option<Usage> {
+        *self.usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `user_content` followed by the context, then the output-format instructions.
+    fn append_context_and_format(&self, mut user_content: String) -> String {
+        for part in self.context.iter().chain(&self.output_format) {
+            user_content.push_str("\n\n");
+            user_content.push_str(part);
+        }
+        user_content
     }
 
     pub async fn send_chat_request(&self, messages: Vec<Message>) -> anyhow::Result<String> {
@@ -107,8 +325,13 @@ impl AiClient {
             max_tokens: self.config.max_tokens,
             temperature: self.config.temperature,
             thinking: Some(ThinkingConfig { r#type: "disabled".to_string() }),
+            stream: self.stream.is_some(),
+            stream_options: self.stream.as_ref().map(|_| StreamOptions { include_usage: true }),
         };
 
+        if let Some(ledger) = &self.ledger {
+            ledger.check_budget()?;
+        }
         debug!("Sending chat request to {}", self.config.endpoint);
 
         let response = self
@@ -127,23 +350,13 @@ impl AiClient {
             anyhow::bail!("API request failed ({status}): {error_text}");
         }
 
-        // 先获取原始响应文本，记录日志后再解析
-        let response_text = response.text().await?;
-        debug!("API raw response: {response_text}");
-
-        let chat_response: ChatResponse = match serde_json::from_str(&response_text) {
-            Ok(r) => r,
-            Err(e) => {
-                error!("Failed to parse API response: {e}. Raw response: {response_text}");
-                anyhow::bail!("Failed to parse API response: {e}. Run with RUST_LOG=debug to see the raw response.");
-            }
+        let (choice, usage) = match &self.stream {
+            Some(sink) => Self::read_stream(response, sink).await?,
+            None => Self::read_response(response).await?,
         };
-
-        let choice = chat_response
-            .choices
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("API returned empty choices — the request may have been filtered"))?;
+        if let Some(usage) = usage {
+            self.usage.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_default().add(usage);
+        }
 
         if choice.finish_reason.as_deref() == Some("length") {
             anyhow::bail!(
@@ -176,9 +389,67 @@ impl AiClient {
         Ok(message)
     }
 
+    async fn read_response(response: reqwest::Response) -> anyhow::Result<(Choice, Option<Usage>)> {
+        // 先获取原始响应文本，记录日志后再解析
+        let response_text = response.text().await?;
+        debug!("API raw response: {response_text}");
+
+        let chat_response: ChatResponse = match serde_json::from_str(&response_text) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to parse API response: {e}. Raw response: {response_text}");
+                anyhow::bail!("Failed to parse API response: {e}. Run with RUST_LOG=debug to see the raw response.");
+            }
+        };
+
+        let choice = chat_response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("API returned empty choices — the request may have been filtered"))?;
+        Ok((choice, chat_response.usage))
+    }
+
+    async fn read_stream(
+        mut response: reqwest::Response,
+        sink: &UnboundedSender<String>,
+    ) -> anyhow::Result<(Choice, Option<Usage>)> {
+        let mut state = StreamState::default();
+        let mut buffer: Vec<u8> = Vec::new();
+        while !state.done
+            && let Some(chunk) = response.chunk().await?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line[..end]);
+                debug!("API stream event: {line}");
+                if let Some(content) = state.push_line(&line)? {
+                    // A closed sink only means nobody is watching; the message is still returned.
+                    let _ = sink.send(content);
+                }
+            }
+        }
+        if !buffer.is_empty() {
+            state.push_line(&String::from_utf8_lossy(&buffer))?;
+        }
+        Ok((Choice { message: state.message, finish_reason: state.finish_reason }, state.usage))
+    }
+
+    /// Fill `{name}` placeholders in the user prompt template and send it.
+    /// Used by commands whose templates take other inputs than a single diff.
+    pub async fn generate_with_vars(&self, vars: &[(&str, &str)]) -> anyhow::Result<String> {
+        let system_message = Message { role: "system".to_string(), content: self.system_prompt.clone() };
+        let user_content = fill_template(&self.user_prompt_template, vars);
+        let user_message = Message { role: "user".to_string(), content: self.append_context_and_format(user_content) };
+        let messages = vec![system_message, user_message];
+        debug!("Sending messages: {messages:?}");
+        self.send_chat_request(messages).await
+    }
+
     pub async fn generate_commit_message(&self, diff: &str) -> anyhow::Result<String> {
         let system_message = Message { role: "system".to_string(), content: self.system_prompt.clone() };
-        let user_content = self.user_prompt_template.replace("{diff}", diff);
+        let user_content = self.append_context_and_format(self.user_prompt_template.replace("{diff}", diff));
         let user_message = Message { role: "user".to_string(), content: user_content };
         let messages = vec![system_message, user_message];
         debug!("Sending messages: {messages:?}");
@@ -197,6 +468,7 @@ impl AiClient {
              Please focus on the user's keywords/context when generating the commit message. \
              Provide only the commit message, no explanations or additional text."
         );
+        let user_content = self.append_context_and_format(user_content);
         let user_message = Message { role: "user".to_string(), content: user_content };
         let messages = vec![system_message, user_message];
         debug!("Sending messages: {messages:?}");
@@ -204,10 +476,28 @@ impl AiClient {
     }
 }
 
+/// One usage record per client, so an invocation is logged once however many requests it made.
+impl Drop for AiClient {
+    fn drop(&mut self) {
+        if let (Some(ledger), Some(usage)) = (&self.ledger, self.usage())
+            && let Err(e) = ledger.record(&self.config.model, usage)
+        {
+            debug!("Could not record token usage: {e:#}");
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fill_template_is_single_pass() {
+        let template = "Commits:\n{commits}\n\nDiff:\n{diff}\n{other}";
+        let filled = fill_template(template, &[("commits", "fix: escape {diff} in prompts"), ("diff", "+x")]);
+        assert_eq!(filled, "Commits:\nfix: escape {diff} in prompts\n\nDiff:\n+x\n{other}");
+    }
+
     #[test]
     fn test_strip_thinking_with_tag() {
         let content = "<think >Some thinking\nMultiple lines\n</think >feat: add new feature";
@@ -244,6 +534,76 @@ mod tests {
         assert_eq!(strip_thinking_content(content), "feat: add feature");
     }
 
+    #[test]
+    fn test_parse_json_response_with_fences() {
+        #[derive(Deserialize)]
+        struct Plan {
+            commits: Vec<String>,
+        }
+        let raw = "Here you go:\n```json\n{\"commits\": [\"fix: a\"]}\n```";
+        let plan: Plan = parse_json_response(raw).unwrap();
+        assert_eq!(plan.commits, vec!["fix: a"]);
+        assert!(parse_json_response::<Plan>("no json here").is_err());
+    }
+
+    #[test]
+    fn test_usage_is_optional_and_summed() {
+        let response: ChatResponse = serde_json::from_str(r#"{"choices": []}"#).unwrap();
+        assert!(response.usage.is_none());
+        let response: ChatResponse = serde_json::from_str(
+            r#"{"choices": [], "usage": {"prompt_tokens": 120, "completion_tokens": 8, "total_tokens": 128}}"#,
+        )
+        .unwrap();
+        let mut total = Usage::default();
+        total.add(response.usage.unwrap());
+        total.add(response.usage.unwrap());
+        assert_eq!(total, Usage { prompt_tokens: 240, completion_tokens: 16, total_tokens: 256 });
+    }
+
+    fn client() -> AiClient {
+        let config = ApiConfig {
+            endpoint: "http://localhost".to_string(),
+            api_key: "key".to_string(),
+            model: "m1".to_string(),
+            max_tokens: None,
+            temperature: None,
+        };
+        AiClient::new(config, "system".to_string(), "{diff}".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_context_comes_before_output_format() {
+        let amend = client().with_output_format("format".to_string()).with_context("previous".to_string());
+        assert_eq!(amend.append_context_and_format("diff".to_string()), "diff\n\nprevious\n\nformat");
+
+        // Context is keyed apart from format instructions with the same text
+        let as_format = client().with_output_format("previous".to_string());
+        let as_context = client().with_context("previous".to_string());
+        let key = |c: &AiClient| c.cache_key(CacheKey::default()).finish().unwrap();
+        assert_ne!(key(&as_format), key(&as_context));
+    }
+
+    #[test]
+    fn test_stream_state_assembles_events() {
+        let mut state = StreamState::default();
+        let events = [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"feat: add"}}]}"#,
+            ": keep-alive",
+            r#"data: {"choices":[{"delta":{"content":" greeting"},"finish_reason":"stop"}]}"#,
+            r#"data: {"choices":[],"usage":{"prompt_tokens":5,"completion_tokens":3,"total_tokens":8}}"#,
+            "data: [DONE]",
+        ];
+        let deltas: Vec<String> = events.iter().filter_map(|e| state.push_line(e).unwrap()).collect();
+        assert_eq!(deltas, vec!["feat: add", " greeting"]);
+        assert_eq!(state.message.content, "feat: add greeting");
+        assert_eq!(state.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(state.usage.map(|u| u.total_tokens), Some(8));
+        assert!(state.done);
+        assert!(state.push_line("data: {not json").is_err());
+    }
+
     #[test]
     fn test_strip_thinking_multiline() {
         let content = "<think >\nLine 1\nLine 2\n</think >\n\nfeat: add feature\n";
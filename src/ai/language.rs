@@ -0,0 +1,134 @@
+//! A script-based check that a generated message is in the requested language.
+//!
+//! The check only looks at writing systems, so it can tell Chinese from English or Russian,
+//! but not German from Spanish. It is meant to catch the common failure where the model
+//! answers in the language of the diff instead of the configured one.
+
+// The codes themselves are parsed by the config; re-exported here for the commands
+pub use crate::config::language::{display_name, normalize_language_code, parse_language_code, primary_language};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Thai,
+    Devanagari,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    let script = match c as u32 {
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => Script::Latin,
+        0x370..=0x3FF => Script::Greek,
+        0x400..=0x4FF => Script::Cyrillic,
+        0x590..=0x5FF => Script::Hebrew,
+        0x600..=0x6FF => Script::Arabic,
+        0x900..=0x97F => Script::Devanagari,
+        0xE00..=0xE7F => Script::Thai,
+        0x3040..=0x30FF => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Script::Hangul,
+        _ => return None,
+    };
+    Some(script)
+}
+
+fn expected_scripts(code: &str) -> &'static [Script] {
+    let primary = code.split('-').next().unwrap_or(code);
+    match primary {
+        "zh" => &[Script::Han],
+        "ja" => &[Script::Kana, Script::Han],
+        "ko" => &[Script::Hangul],
+        "ru" | "uk" | "bg" | "sr" | "be" | "kk" => &[Script::Cyrillic],
+        "el" => &[Script::Greek],
+        "ar" | "fa" | "ur" => &[Script::Arabic],
+        "he" | "yi" => &[Script::Hebrew],
+        "th" => &[Script::Thai],
+        "hi" | "mr" | "ne" => &[Script::Devanagari],
+        _ => &[Script::Latin],
+    }
+}
+
+/// Drop the parts of a message that are expected to be English regardless of language:
+/// the Conventional Commits `type(scope):` prefix and anything in backticks.
+fn prose(message: &str) -> String {
+    let mut out = String::new();
+    for line in message.lines() {
+        let line = line.trim_start_matches(|c: char| c == '-' || c == '*' || c.is_whitespace());
+        let line = match line.split_once(':') {
+            Some((prefix, rest))
+                if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphanumeric() || "()!-_/.".contains(c)) =>
+            {
+                rest
+            }
+            _ => line,
+        };
+        let mut in_code = false;
+        for c in line.chars() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if !in_code {
+                out.push(c);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Heuristically check that `message` is written in `language`.
+///
+/// Returns a short explanation when the message looks like it is in a different script,
+/// or `None` when it matches (or when there is too little text to tell).
+pub fn check_language(message: &str, language: &str) -> Option<String> {
+    let code = normalize_language_code(language);
+    let expected = expected_scripts(&code);
+    let text = prose(message);
+
+    let mut matching = 0usize;
+    let mut total = 0usize;
+    for script in text.chars().filter_map(script_of) {
+        total += 1;
+        if expected.contains(&script) {
+            matching += 1;
+        }
+    }
+    if total < 4 {
+        return None;
+    }
+
+    // Non-Latin languages routinely mix in Latin identifiers, so only require a share of the
+    // letters there; Latin-script languages should be almost entirely Latin.
+    let ok = if expected == [Script::Latin] { matching * 10 >= total * 8 } else { matching * 10 >= total * 3 };
+    if ok { None } else { Some(format!("the generated message does not look like {}", display_name(&code))) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_language_matches() {
+        assert!(check_language("feat: 添加多语言支持", "zh").is_none());
+        assert!(check_language("fix(auth): ログインのタイムアウトを修正", "ja").is_none());
+        assert!(check_language("fix: Zeitüberschreitung beim Login behoben", "de").is_none());
+        assert!(check_language("feat: add `AiClient` retry", "en").is_none());
+    }
+
+    #[test]
+    fn test_check_language_mismatch() {
+        assert!(check_language("feat: add multi-language support", "zh").is_some());
+        assert!(check_language("feat: 添加多语言支持和配置", "en").is_some());
+        assert!(check_language("fix: исправить тайм-аут входа", "es").is_some());
+    }
+
+    #[test]
+    fn test_check_language_ignores_type_and_code() {
+        assert!(check_language("refactor(config): 重构 `PromptConfig` 结构", "zh").is_none());
+    }
+}
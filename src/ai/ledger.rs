@@ -0,0 +1,199 @@
+//! Token usage and cost of every invocation, appended to a JSONL log, and the budget checks
+//! made before a request is sent.
+
+use anyhow::Result;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::client::Usage;
+use crate::config::{BudgetAction, UsageConfig};
+
+impl UsageConfig {
+    /// What `usage` cost with `model`, or `None` when the model has no price.
+    pub fn cost(&self, model: &str, usage: Usage) -> Option<f64> {
+        let price = self.prices.get(model)?;
+        Some((usage.prompt_tokens as f64 * price.input + usage.completion_tokens as f64 * price.output) / 1_000_000.0)
+    }
+}
+
+/// One line of the usage log: what one client used over its lifetime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Working directory of the repository, when run inside one.
+    pub repo: Option<String>,
+    pub command: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+    /// Priced with the `[usage.prices]` entry in effect at the time; `None` when unpriced.
+    pub cost: Option<f64>,
+}
+
+/// The usage log of one command, used by `AiClient` to check budgets and record usage.
+#[derive(Debug)]
+pub struct UsageLedger {
+    path: PathBuf,
+    config: UsageConfig,
+    repo: Option<String>,
+    command: String,
+    /// Budget warnings are shown once per client, not once per request.
+    warned: AtomicBool,
+}
+
+impl UsageLedger {
+    /// The log for `command`, or `None` when recording is disabled.
+    pub fn open(config: &UsageConfig, command: &str) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            path: Self::log_path()?,
+            config: config.clone(),
+            repo: crate::git::current_workdir(),
+            command: command.to_string(),
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    /// `$XDG_DATA_HOME/ai-commit/usage.jsonl`.
+    pub fn log_path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ai-commit").join("usage.jsonl"))
+    }
+
+    pub fn record(&self, model: &str, usage: Usage) -> Result<()> {
+        let record = UsageRecord {
+            timestamp: now(),
+            repo: self.repo.clone(),
+            command: self.command.clone(),
+            model: model.to_string(),
+            usage,
+            cost: self.config.cost(model, usage),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+
+    /// Warn about, or refuse, a request once spending has reached a configured budget.
+    pub fn check_budget(&self) -> Result<()> {
+        if self.config.daily_budget.is_none() && self.config.monthly_budget.is_none() {
+            return Ok(());
+        }
+        let records = read_records(&self.path)?;
+        let today = today();
+        let budgets = [
+            ("daily", self.config.daily_budget, spent(&records, &today)),
+            ("monthly", self.config.monthly_budget, spent(&records, &today[..7])),
+        ];
+
+        for (label, budget, spent) in budgets {
+            let Some(budget) = budget.filter(|&budget| spent >= budget) else { continue };
+            let message = format!("{spent:.4} spent, which reaches the {label} budget of {budget}");
+            match self.config.on_budget {
+                BudgetAction::Refuse => anyhow::bail!(
+                    "Not sending the request: {message}. Raise [usage] {label}_budget or set on_budget = \"warn\""
+                ),
+                BudgetAction::Warn if !self.warned.swap(true, Ordering::Relaxed) => {
+                    eprintln!("{}", format!("Warning: {message}.").yellow());
+                }
+                BudgetAction::Warn => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every record in the log at `path`; a missing log has none. Unreadable lines are skipped.
+pub fn read_records(path: &std::path::Path) -> Result<Vec<UsageRecord>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// Cost of the records from UTC dates starting with `prefix`: a day (`YYYY-MM-DD`) or a
+/// month (`YYYY-MM`).
+pub fn spent(records: &[UsageRecord], prefix: &str) -> f64 {
+    records.iter().filter(|r| day_of(r.timestamp).starts_with(prefix)).filter_map(|r| r.cost).sum()
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+pub fn today() -> String {
+    day_of(now())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// The UTC date of a Unix timestamp as `YYYY-MM-DD`.
+pub fn day_of(timestamp: u64) -> String {
+    // Days to civil date, after Howard Hinnant's `civil_from_days`
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelPrice;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_day_of() {
+        assert_eq!(day_of(0), "1970-01-01");
+        assert_eq!(day_of(951_782_400), "2000-02-29");
+        assert_eq!(day_of(1_792_368_000 + 86_399), "2026-10-19");
+    }
+
+    #[test]
+    fn test_record_and_budget() {
+        let dir = TempDir::new("ledger");
+        let mut config = UsageConfig::default();
+        config.prices.insert("m1".to_string(), ModelPrice { input: 1.0, output: 2.0 });
+        config.daily_budget = Some(3.0);
+        config.on_budget = BudgetAction::Refuse;
+        let ledger = UsageLedger {
+            path: dir.join("usage.jsonl"),
+            config,
+            repo: None,
+            command: "commit".to_string(),
+            warned: AtomicBool::new(false),
+        };
+
+        let usage = Usage { prompt_tokens: 1_000_000, completion_tokens: 500_000, total_tokens: 1_500_000 };
+        ledger.check_budget().unwrap();
+        ledger.record("m1", usage).unwrap();
+        ledger.record("unpriced", usage).unwrap();
+
+        let records = read_records(&ledger.path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].cost, Some(2.0));
+        assert_eq!(records[1].cost, None);
+        ledger.check_budget().unwrap();
+
+        ledger.record("m1", usage).unwrap();
+        let error = ledger.check_budget().unwrap_err().to_string();
+        assert!(error.contains("daily budget of 3"), "{error}");
+    }
+}
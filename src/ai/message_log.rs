@@ -0,0 +1,270 @@
+//! Every generated message, kept in a JSONL log so a cancelled commit or a failed hook does
+//! not lose it. `ai-commit history` lists the log and `commit --reuse` commits from it.
+//!
+//! The log is only appended to: a line per message, and an `{"accepted": ID}` line once a
+//! commit is made with one. Writers hold a lock on `history.jsonl.lock`, since `serve` and
+//! several terminals may generate at once; the log is compacted to `max_entries` messages
+//! when it has grown to twice that.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::HistoryConfig;
+use crate::git::{current_branch, current_workdir, open_repo};
+
+/// One generated message and where it was generated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedMessage {
+    /// Sequential over the whole log; `commit --reuse <ID>` takes it.
+    pub id: u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Working directory of the repository.
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    /// The index tree the message was generated for.
+    pub tree: Option<String>,
+    pub command: String,
+    pub model: String,
+    pub message: String,
+    /// Whether a commit was made with the message.
+    pub accepted: bool,
+}
+
+/// A line of the log file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum LogLine {
+    Message(GeneratedMessage),
+    Accepted { accepted: u64 },
+}
+
+/// The message log shared by every repository.
+#[derive(Debug)]
+pub struct MessageLog {
+    path: PathBuf,
+    max_entries: usize,
+}
+
+impl MessageLog {
+    /// The log, or `None` when history is disabled.
+    pub fn open(config: &HistoryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self { path: Self::log_path()?, max_entries: config.max_entries })
+    }
+
+    /// `$XDG_DATA_HOME/ai-commit/history.jsonl`.
+    pub fn log_path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ai-commit").join("history.jsonl"))
+    }
+
+    /// The newest `max_entries` messages, oldest first.
+    pub fn entries(&self) -> Result<Vec<GeneratedMessage>> {
+        read_entries(&self.path, self.max_entries)
+    }
+
+    /// Keep a message generated by `command` in the current repository, not yet committed.
+    /// Returns its id.
+    pub fn record(&self, command: &str, model: &str, message: &str) -> Result<u64> {
+        let repo = open_repo().ok();
+        let tree = repo.as_ref().and_then(|repo| repo.index().ok()?.write_tree().ok()).map(|oid| oid.to_string());
+        let branch = if repo.is_some() { current_branch().ok().flatten() } else { None };
+        self.append(GeneratedMessage {
+            id: 0,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            repo: current_workdir(),
+            branch,
+            tree,
+            command: command.to_string(),
+            model: model.to_string(),
+            message: message.to_string(),
+            accepted: false,
+        })
+    }
+
+    /// Add `entry` under the next id, compacting the log once it has grown too long.
+    fn append(&self, mut entry: GeneratedMessage) -> Result<u64> {
+        let _lock = self.lock()?;
+        let lines = read_lines(&self.path)?;
+        let last_id =
+            lines.iter().filter_map(|line| if let LogLine::Message(e) = line { Some(e.id) } else { None }).max();
+        entry.id = last_id.unwrap_or(0) + 1;
+        let id = entry.id;
+        self.append_line(&LogLine::Message(entry))?;
+        if lines.len() + 1 > self.max_entries.max(1) * 2 {
+            self.compact()?;
+        }
+        Ok(id)
+    }
+
+    /// Note that a commit was made with message `id`.
+    pub fn mark_accepted(&self, id: u64) -> Result<()> {
+        let _lock = self.lock()?;
+        match fold(read_lines(&self.path)?).iter().find(|e| e.id == id) {
+            Some(entry) if !entry.accepted => self.append_line(&LogLine::Accepted { accepted: id }),
+            _ => Ok(()),
+        }
+    }
+
+    /// `last` is the newest message from repository `repo`; a number is the message with
+    /// that id, from any repository.
+    pub fn find(&self, selector: &str, repo: Option<&str>) -> Result<GeneratedMessage> {
+        let entries = self.entries()?;
+        let found = if selector == "last" {
+            entries.into_iter().rev().find(|e| e.repo.as_deref() == repo)
+        } else {
+            let id: u64 =
+                selector.parse().with_context(|| format!("'{selector}' is neither 'last' nor a message id"))?;
+            entries.into_iter().find(|e| e.id == id)
+        };
+        found.with_context(|| match selector {
+            "last" => "No generated message kept for this repository".to_string(),
+            _ => format!("No generated message #{selector}; see `ai-commit history`"),
+        })
+    }
+
+    /// Held until the returned file is dropped.
+    fn lock(&self) -> Result<File> {
+        let dir = self.path.parent().context("The history log has no parent directory")?;
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join("history.jsonl.lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    fn append_line(&self, line: &LogLine) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(format!("{}\n", serde_json::to_string(line)?).as_bytes())?;
+        Ok(())
+    }
+
+    /// Rewrite the log as its newest `max_entries` messages, acceptance folded in.
+    fn compact(&self) -> Result<()> {
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let dir = self.path.parent().context("The history log has no parent directory")?;
+        let mut content = String::new();
+        for entry in self.entries()? {
+            content.push_str(&serde_json::to_string(&entry)?);
+            content.push('\n');
+        }
+        // Write then rename, so a reader never sees half a log
+        let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = dir.join(format!("history.{}.{n}.tmp", std::process::id()));
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn read_lines(path: &Path) -> Result<Vec<LogLine>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    // A line still being appended by another process does not parse yet
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+fn fold(lines: Vec<LogLine>) -> Vec<GeneratedMessage> {
+    let mut entries = Vec::new();
+    for line in lines {
+        match line {
+            LogLine::Message(entry) => entries.push(entry),
+            LogLine::Accepted { accepted } => {
+                if let Some(entry) = entries.iter_mut().find(|e| e.id == accepted) {
+                    entry.accepted = true;
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// The newest `max_entries` messages in the log at `path`, oldest first; a missing log has
+/// none. Unreadable lines are skipped.
+pub fn read_entries(path: &Path, max_entries: usize) -> Result<Vec<GeneratedMessage>> {
+    let mut entries = fold(read_lines(path)?);
+    let excess = entries.len().saturating_sub(max_entries.max(1));
+    entries.drain(..excess);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn entry(repo: &str, message: &str) -> GeneratedMessage {
+        GeneratedMessage {
+            id: 0,
+            timestamp: 1_792_368_000,
+            repo: Some(repo.to_string()),
+            branch: Some("main".to_string()),
+            tree: None,
+            command: "commit".to_string(),
+            model: "m1".to_string(),
+            message: message.to_string(),
+            accepted: false,
+        }
+    }
+
+    #[test]
+    fn test_append_find_and_accept() {
+        let dir = TempDir::new("history");
+        let log = MessageLog { path: dir.join("history.jsonl"), max_entries: 2 };
+        assert!(log.find("last", Some("/a")).is_err());
+
+        assert_eq!(log.append(entry("/a", "feat: one")).unwrap(), 1);
+        assert_eq!(log.append(entry("/b", "feat: two")).unwrap(), 2);
+        assert_eq!(log.find("last", Some("/a")).unwrap().message, "feat: one");
+        assert_eq!(log.find("2", Some("/a")).unwrap().message, "feat: two");
+        assert!(log.find("first", Some("/a")).is_err());
+
+        // The oldest entry goes beyond max_entries; ids keep counting
+        assert_eq!(log.append(entry("/a", "feat: three")).unwrap(), 3);
+        let ids: Vec<u64> = log.entries().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(log.find("last", Some("/a")).unwrap().message, "feat: three");
+
+        log.mark_accepted(3).unwrap();
+        assert!(log.find("3", None).unwrap().accepted);
+        assert!(!log.find("2", None).unwrap().accepted);
+
+        // Compacted at twice max_entries, keeping acceptance
+        assert_eq!(log.append(entry("/a", "feat: four")).unwrap(), 4);
+        assert_eq!(fs::read_to_string(&log.path).unwrap().lines().count(), 2);
+        assert!(log.find("3", None).unwrap().accepted);
+    }
+
+    #[test]
+    fn test_concurrent_appends_get_distinct_ids() {
+        let dir = TempDir::new("history-threads");
+        let path = dir.join("history.jsonl");
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let log = MessageLog { path: path.clone(), max_entries: 500 };
+                std::thread::spawn(move || {
+                    let id = log.append(entry("/a", &format!("feat: {i}"))).unwrap();
+                    log.mark_accepted(id).unwrap();
+                    id
+                })
+            })
+            .collect();
+        let mut ids: Vec<u64> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+
+        let entries = read_entries(&path, 500).unwrap();
+        assert_eq!(entries.len(), 8);
+        assert!(entries.iter().all(|e| e.accepted));
+    }
+}
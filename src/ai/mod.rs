@@ -1,3 +1,10 @@
+mod bilingual;
+pub mod body;
+pub mod cache;
 mod client;
+pub mod language;
+pub mod ledger;
+pub mod message_log;
 
+pub use bilingual::*;
 pub use client::*;
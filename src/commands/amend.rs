@@ -2,49 +2,70 @@ use anyhow::Result;
 use colored::*;
 use std::io::{self, Write};
 
-use crate::ai::AiClient;
+use crate::ai::ledger::UsageLedger;
+use crate::ai::message_log::MessageLog;
+use crate::ai::{AiClient, Bilingual, body, language};
+use crate::commands::commit::{RunMode, keep_message};
+use crate::commands::output::{ExitStatus, WithStatus};
 use crate::config::{ApiConfig, AppConfig};
 use crate::git::{
-    execute_amend_with_cli, get_amend_diff, get_last_commit_message, get_staged_diff, get_truncated_diff,
+    CommitOptions, execute_commit, get_amend_diff, get_last_commit_message, get_staged_diff, get_truncated_diff,
 };
 
 pub async fn handle_amend(
     language: Option<&str>,
     keywords: Option<&str>,
-    dry_run: bool,
+    mode: RunMode,
     context_limit: Option<usize>,
+    body: bool,
+    sign: Option<bool>,
 ) -> Result<()> {
-    let app_config = AppConfig::load_or_create()?;
-    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let app_config = AppConfig::load_or_create().status(ExitStatus::ConfigError)?;
+    let api_config = ApiConfig::from_env(&app_config.env).status(ExitStatus::ConfigError)?;
 
     // Determine language: CLI arg > config file
     let language = language.unwrap_or(app_config.commit.language.as_str());
 
-    // Select prompts based on language
-    let (system_prompt, user_prompt_template) = if language == "zh" {
-        (app_config.prompts.system_prompt_zh.clone(), app_config.prompts.user_prompt_template_zh.clone())
-    } else {
-        (app_config.prompts.system_prompt.clone(), app_config.prompts.user_prompt_template.clone())
+    // Select prompts based on language; `a+b` codes produce a bilingual message
+    let bilingual = Bilingual::parse(language, app_config.commit.bilingual_style).status(ExitStatus::Usage)?;
+    let (system_prompt, user_prompt_template) = match &bilingual {
+        Some(b) => app_config.prompts.for_bilingual(&b.primary, &b.secondary),
+        None => app_config.prompts.for_language(language),
     };
 
-    let ai_client = AiClient::new(api_config, system_prompt, user_prompt_template)?;
+    let body_mode = body || app_config.body.enabled;
+    if body_mode && bilingual.is_some() {
+        return Err(ExitStatus::ConfigError
+            .error("--body cannot be combined with a bilingual language; use bilingual_style = \"subject-body\""));
+    }
 
-    let staged_diff = get_staged_diff(Some(&app_config.commit))?;
-    let amend_diff = get_amend_diff(Some(&app_config.commit))?;
     let last_commit_msg = get_last_commit_message()?;
 
-    let diff_content = if !staged_diff.is_empty() {
+    let mut ai_client = AiClient::new(api_config, system_prompt, user_prompt_template)
+        .status(ExitStatus::AiError)?
+        .with_ledger(UsageLedger::open(&app_config.usage, "amend"))
+        .with_context(previous_message_context(&last_commit_msg));
+    if let Some(b) = &bilingual {
+        ai_client = ai_client.with_output_format(b.format_instructions());
+    }
+    if body_mode {
+        ai_client = ai_client.with_output_format(body::format_instructions(&app_config.body));
+    }
+
+    // The amended commit is HEAD's parent plus the index; unstaged changes are not part of it
+    let staged_diff = get_staged_diff(Some(&app_config.commit))?;
+    let diff_content = get_amend_diff(Some(&app_config.commit))?;
+
+    if !staged_diff.is_empty() {
         println!("{}", "Found staged changes to amend.".green());
-        amend_diff
     } else {
         println!("{}", "No staged changes found.".yellow());
         println!("{}", "Will generate new message for existing commit content.".yellow());
-        amend_diff
-    };
+    }
 
     if diff_content.is_empty() {
         println!("{}", "No changes found to amend.".red());
-        return Ok(());
+        return Err(ExitStatus::NoChanges.error("No changes found to amend"));
     }
 
     let limit = context_limit.unwrap_or(app_config.commit.context_limit);
@@ -66,19 +87,47 @@ pub async fn handle_amend(
         ai_client.generate_commit_message(&diff_content).await
     };
 
-    match result {
-        Ok(message) => {
+    let result = match (&bilingual, result) {
+        (Some(b), Ok(raw)) => b.assemble(&raw),
+        (None, Ok(message)) => {
+            let (message, mut warnings) =
+                if body_mode { body::enforce(&message, &app_config.body) } else { (message, Vec::new()) };
+            warnings.extend(language::check_language(&message, language));
+            Ok((message, warnings))
+        }
+        (_, Err(e)) => Err(e),
+    };
+
+    match result.status(ExitStatus::AiError) {
+        Ok((message, warnings)) => {
+            let kept = keep_message(&app_config, "amend", ai_client.model(), &message);
             println!("{}", "Generated new commit message:".bright_cyan().bold());
             println!("{}", "─────────────────────".bright_blue());
             println!("{}", message.bright_green().bold());
             println!("{}", "─────────────────────".bright_blue());
 
-            if dry_run {
+            for warning in warnings {
+                println!("{}", format!("Warning: {warning}.").yellow());
+            }
+
+            if mode.is_dry_run(&app_config.commit) {
                 println!("{}", "(Dry run mode - no actual amend made)".yellow());
-            } else if app_config.commit.auto_confirm || confirm_amend()? {
-                execute_amend_with_cli(&message)?;
+                if mode.dry_run.is_none() {
+                    println!("{}", "dry_run_by_default is set; pass --commit to amend.".dimmed());
+                }
+            } else if !mode.needs_confirmation(&app_config.commit) || confirm_amend()? {
+                let options = CommitOptions::new(&app_config.commit)
+                    .amend()
+                    .sign(sign)
+                    .trailers_from_config(&app_config.trailers)?;
+                execute_commit(&message, &options).inspect_err(|_| kept_hint(&kept))?;
+                if let Some((log, id)) = &kept {
+                    log.mark_accepted(*id)?;
+                }
             } else {
                 println!("{}", "Amend cancelled.".red());
+                kept_hint(&kept);
+                return Err(ExitStatus::Cancelled.error("Amend cancelled"));
             }
         }
         Err(e) => {
@@ -90,6 +139,25 @@ pub async fn handle_amend(
     Ok(())
 }
 
+/// Where to find the generated message after the amend was cancelled or failed.
+fn kept_hint(kept: &Option<(MessageLog, u64)>) {
+    if let Some((_, id)) = kept {
+        eprintln!("{}", format!("The message is kept as #{id}; see `ai-commit history {id}`.").yellow());
+    }
+}
+
+/// Lets the model update the existing message instead of replacing it outright.
+fn previous_message_context(message: &str) -> String {
+    format!(
+        "The commit being amended currently has this message:\n\
+         \"\"\"\n{}\n\"\"\"\n\
+         The diff above is the complete content of the amended commit. Update that message so it \
+         describes all of it: keep what is still accurate and in the same style, add what is new, \
+         and drop anything that is no longer part of the diff.",
+        message.trim()
+    )
+}
+
 fn confirm_amend() -> Result<bool> {
     print!("Do you want to amend the commit with this message? (y/N): ");
     io::stdout().flush()?;
@@ -0,0 +1,333 @@
+use anyhow::{Result, bail};
+use colored::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language};
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::conventional;
+use crate::git::{CommitInfo, get_commits_in_range, get_truncated_diff};
+
+/// Keep a Changelog sections, in the order they are rendered.
+const SECTIONS: &[&str] = &["Added", "Changed", "Deprecated", "Removed", "Fixed", "Security"];
+
+const CHANGELOG_HEADER: &str = "# Changelog\n\n\
+    All notable changes to this project will be documented in this file.\n\n\
+    The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),\n\
+    and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n";
+
+pub struct ChangelogOptions<'a> {
+    /// `A..B`, `A`, or `None` for the latest tag up to HEAD.
+    pub range: Option<&'a str>,
+    /// Version for the heading; `None` renders an `[Unreleased]` section.
+    pub version: Option<&'a str>,
+    /// Rewrite the entries as user-facing release notes with the AI service.
+    pub ai: bool,
+    /// Update `file` in place instead of only printing.
+    pub write: bool,
+    pub file: PathBuf,
+    /// Include docs/test/chore/ci/build/style commits.
+    pub all: bool,
+    pub language: Option<&'a str>,
+    pub context_limit: Option<usize>,
+}
+
+pub async fn handle_changelog(opts: ChangelogOptions<'_>) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let range = get_commits_in_range(opts.range)?;
+    let from = range.from_name.as_deref().unwrap_or("the first commit");
+
+    let entries = group_commits(&range.commits, opts.all);
+    if entries.iter().all(|(_, items)| items.is_empty()) {
+        println!("{}", format!("No changelog entries between {from} and {}.", range.to_name).red());
+        return Ok(());
+    }
+    println!("{}", format!("Found {} commits between {from} and {}.", range.commits.len(), range.to_name).green());
+
+    let mut notes = render_sections(&entries);
+    if opts.ai {
+        notes = rewrite_with_ai(&app_config, &notes, opts.language, opts.context_limit).await?;
+    }
+
+    let heading = match opts.version {
+        Some(version) => format!("## [{}] - {}", version.trim_start_matches('v'), today()),
+        None => "## [Unreleased]".to_string(),
+    };
+    let section = format!("{heading}\n\n{}\n", notes.trim_end());
+
+    println!("{}", "Generated changelog:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    print!("{section}");
+    println!("{}", "─────────────────────".bright_blue());
+
+    if opts.write {
+        let existing = match fs::read_to_string(&opts.file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CHANGELOG_HEADER.to_string(),
+            Err(e) => return Err(e.into()),
+        };
+        let updated = insert_section(&existing, &heading, &section)?;
+        fs::write(&opts.file, updated)?;
+        println!("{}", format!("Updated {}", opts.file.display()).green());
+    }
+
+    Ok(())
+}
+
+/// Group commits into Keep a Changelog sections. Non-conventional commits go to Changed;
+/// housekeeping types are dropped unless `all` is set or the commit is breaking.
+fn group_commits(commits: &[CommitInfo], all: bool) -> Vec<(&'static str, Vec<String>)> {
+    let mut grouped: Vec<(&'static str, Vec<String>)> = SECTIONS.iter().map(|s| (*s, Vec::new())).collect();
+    for commit in commits {
+        if commit.summary.starts_with("Merge ") {
+            continue;
+        }
+        let (section, entry) = match conventional::parse(&commit.message) {
+            Some(c) => {
+                let section = match c.kind.as_str() {
+                    "feat" => Some("Added"),
+                    "fix" => Some("Fixed"),
+                    "perf" | "refactor" | "revert" => Some("Changed"),
+                    "deprecate" => Some("Deprecated"),
+                    "remove" => Some("Removed"),
+                    "security" => Some("Security"),
+                    _ if all || c.breaking => Some("Changed"),
+                    _ => None,
+                };
+                let Some(section) = section else { continue };
+                let mut entry = String::new();
+                if c.breaking {
+                    entry.push_str("**BREAKING:** ");
+                }
+                if let Some(scope) = &c.scope {
+                    let _ = write!(entry, "**{scope}:** ");
+                }
+                entry.push_str(&c.description);
+                (section, entry)
+            }
+            None => ("Changed", commit.summary.trim().to_string()),
+        };
+        if let Some((_, items)) = grouped.iter_mut().find(|(s, _)| *s == section) {
+            items.push(format!("{entry} ({})", commit.short_id));
+        }
+    }
+    grouped
+}
+
+fn render_sections(entries: &[(&str, Vec<String>)]) -> String {
+    let mut out = String::new();
+    for (section, items) in entries.iter().filter(|(_, items)| !items.is_empty()) {
+        let _ = writeln!(out, "### {section}\n");
+        for item in items {
+            let _ = writeln!(out, "- {item}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+async fn rewrite_with_ai(
+    app_config: &AppConfig,
+    notes: &str,
+    language: Option<&str>,
+    context_limit: Option<usize>,
+) -> Result<String> {
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+
+    // Release notes are single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let (system_prompt, _) = app_config.prompts.for_language(&language);
+    let template =
+        app_config.prompts.changelog_prompt_template.replace("{language}", &language::display_name(&language));
+    let ai_client = AiClient::new(api_config, system_prompt, template)?
+        .with_ledger(UsageLedger::open(&app_config.usage, "changelog"));
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let changes = get_truncated_diff(notes, limit);
+
+    println!("{}", "Rewriting changelog as release notes using AI service...".cyan());
+    let raw = match ai_client.generate_with_vars(&[("changes", &changes)]).await {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to generate release notes:".red());
+            return Err(e);
+        }
+    };
+
+    match clean_ai_notes(&raw) {
+        Some(rewritten) => Ok(rewritten),
+        None => {
+            println!("{}", "Warning: AI response had no changelog sections; using the commit list.".yellow());
+            Ok(notes.to_string())
+        }
+    }
+}
+
+/// Keep everything from the first `### ` heading on, dropping code fences and any
+/// version heading the model added. `None` if there are no sections at all.
+fn clean_ai_notes(raw: &str) -> Option<String> {
+    let lines: Vec<&str> = raw
+        .lines()
+        .filter(|l| !l.trim_start().starts_with("```"))
+        .filter(|l| !(l.starts_with("## ") || l.starts_with("# ")))
+        .collect();
+    let start = lines.iter().position(|l| l.starts_with("### "))?;
+    Some(format!("{}\n", lines[start..].join("\n").trim_end()))
+}
+
+/// Insert `section` into an existing changelog. An existing `[Unreleased]` section is
+/// replaced; a version that is already present is an error. New sections go after
+/// `[Unreleased]` (for versions) and before the newest release.
+fn insert_section(existing: &str, heading: &str, section: &str) -> Result<String> {
+    let key = heading_key(heading);
+    let lines: Vec<&str> = existing.lines().collect();
+    let headings: Vec<usize> = lines.iter().enumerate().filter(|(_, l)| l.starts_with("## ")).map(|(i, _)| i).collect();
+
+    if let Some(&at) = headings.iter().find(|&&i| heading_key(lines[i]) == key) {
+        if key != "[Unreleased]" {
+            bail!("Changelog already contains a section for {key}");
+        }
+        let end = headings.iter().find(|&&i| i > at).copied().unwrap_or_else(|| release_end(&lines));
+        return Ok(splice(&lines, at, end, section));
+    }
+
+    let at = headings
+        .iter()
+        .find(|&&i| key == "[Unreleased]" || heading_key(lines[i]) != "[Unreleased]")
+        .copied()
+        .unwrap_or_else(|| release_end(&lines));
+    Ok(splice(&lines, at, at, section))
+}
+
+/// `## [1.2.0] - 2024-01-01` → `[1.2.0]`.
+fn heading_key(heading: &str) -> &str {
+    heading.trim_start_matches('#').trim().split(" - ").next().unwrap_or_default().trim()
+}
+
+/// End of the release sections: before trailing link references such as `[1.0.0]: https://…`.
+fn release_end(lines: &[&str]) -> usize {
+    let mut end = lines.len();
+    while end > 0 && (lines[end - 1].trim().is_empty() || is_link_reference(lines[end - 1])) {
+        end -= 1;
+    }
+    if lines[end..].iter().any(|l| is_link_reference(l)) { end } else { lines.len() }
+}
+
+fn is_link_reference(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]: ")
+}
+
+fn splice(lines: &[&str], start: usize, end: usize, section: &str) -> String {
+    let before = lines[..start].join("\n");
+    let after = lines[end..].join("\n");
+    let mut out = before.trim_end().to_string();
+    out.push_str("\n\n");
+    out.push_str(section.trim_end());
+    out.push('\n');
+    if !after.trim().is_empty() {
+        out.push('\n');
+        out.push_str(after.trim_start_matches('\n'));
+        out.push('\n');
+    }
+    out
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let (y, m, d) = civil_from_days((secs / 86_400) as i64);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Days since 1970-01-01 to a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    fn commit(message: &str) -> CommitInfo {
+        CommitInfo {
+            id: Oid::ZERO_SHA1,
+            short_id: "abc1234".to_string(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            message: message.to_string(),
+            author: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_group_and_render() {
+        let commits = vec![
+            commit("feat(cli): add changelog command"),
+            commit("fix: handle empty diff"),
+            commit("chore: bump deps"),
+            commit("refactor!: rename config keys"),
+            commit("Update README"),
+            commit("Merge branch 'topic'"),
+        ];
+        let rendered = render_sections(&group_commits(&commits, false));
+        assert_eq!(
+            rendered,
+            "### Added\n\n- **cli:** add changelog command (abc1234)\n\n\
+             ### Changed\n\n- **BREAKING:** rename config keys (abc1234)\n- Update README (abc1234)\n\n\
+             ### Fixed\n\n- handle empty diff (abc1234)\n\n"
+        );
+        let all = group_commits(&commits, true);
+        assert_eq!(all.iter().find(|(s, _)| *s == "Changed").unwrap().1.len(), 3);
+    }
+
+    #[test]
+    fn test_insert_section_new_version() {
+        let existing = format!("{CHANGELOG_HEADER}\n## [Unreleased]\n\n- wip\n\n## [0.1.0] - 2024-01-01\n\n- first\n");
+        let out = insert_section(&existing, "## [0.2.0] - 2024-02-01", "## [0.2.0] - 2024-02-01\n\n- new\n").unwrap();
+        let unreleased = out.find("## [Unreleased]").unwrap();
+        let new = out.find("## [0.2.0]").unwrap();
+        let old = out.find("## [0.1.0]").unwrap();
+        assert!(unreleased < new && new < old);
+        assert!(out.contains("- wip"));
+        assert!(insert_section(&out, "## [0.2.0] - 2024-03-01", "## [0.2.0]\n").is_err());
+    }
+
+    #[test]
+    fn test_insert_section_replaces_unreleased() {
+        let existing = "# Changelog\n\n## [Unreleased]\n\n- old\n\n## [0.1.0] - 2024-01-01\n\n- first\n\n\
+                        [0.1.0]: https://example.com/v0.1.0\n";
+        let out = insert_section(existing, "## [Unreleased]", "## [Unreleased]\n\n- fresh\n").unwrap();
+        assert!(!out.contains("- old"));
+        assert!(out.contains("## [Unreleased]\n\n- fresh\n\n## [0.1.0]"));
+        assert!(out.ends_with("[0.1.0]: https://example.com/v0.1.0\n"));
+
+        let empty = insert_section(CHANGELOG_HEADER, "## [Unreleased]", "## [Unreleased]\n\n- x\n").unwrap();
+        assert!(empty.starts_with("# Changelog") && empty.ends_with("## [Unreleased]\n\n- x\n"));
+    }
+
+    #[test]
+    fn test_clean_ai_notes() {
+        assert_eq!(clean_ai_notes("```markdown\n## [1.0.0]\n### Added\n- x\n```").unwrap(), "### Added\n- x\n");
+        assert!(clean_ai_notes("Sorry, no changes.").is_none());
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
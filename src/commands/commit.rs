@@ -1,87 +1,490 @@
 use anyhow::Result;
 use colored::*;
+use log::debug;
 use std::io::{self, Write};
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::ai::AiClient;
-use crate::config::{ApiConfig, AppConfig};
-use crate::git::{execute_commit_with_cli, get_staged_diff, get_truncated_diff, get_unstaged_diff};
+use crate::ai::cache::{CacheKey, CachePolicy, ResponseCache};
+use crate::ai::ledger::UsageLedger;
+use crate::ai::message_log::MessageLog;
+use crate::ai::{AiClient, Bilingual, body, language};
+use crate::commands::merge_message::generate_merge_message;
+use crate::commands::output::{CommitReport, ExitStatus, OutputFormat, WithStatus};
+use crate::config::{ApiConfig, AppConfig, CommitConfig};
+use crate::git::trailers::{self, Author, Trailer};
+use crate::git::{
+    CommitOptions, DiffSource, current_workdir, execute_commit, get_diff_stats, get_merge_state, get_staged_diff,
+    get_tracked_diff, get_truncated_diff, get_unstaged_diff, open_repo,
+};
 
-pub async fn handle_commit(
-    language: Option<&str>,
-    keywords: Option<&str>,
-    dry_run: bool,
-    context_limit: Option<usize>,
+/// Per-invocation overrides of `dry_run_by_default` and `auto_confirm`; `None` leaves
+/// the decision to the config.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunMode {
+    /// `Some(true)` for `--dry-run`, `Some(false)` for `--commit`.
+    pub dry_run: Option<bool>,
+    /// `Some(true)` for `--confirm`, `Some(false)` for `--yes` / `--no-confirm`.
+    pub confirm: Option<bool>,
+}
+
+impl RunMode {
+    /// Only show the message: `--dry-run` / `--commit`, else `dry_run_by_default`.
+    pub(crate) fn is_dry_run(&self, config: &CommitConfig) -> bool {
+        self.dry_run.unwrap_or(config.dry_run_by_default)
+    }
+
+    /// Ask before committing: `--confirm` / `--yes`, else unless `auto_confirm`.
+    pub(crate) fn needs_confirmation(&self, config: &CommitConfig) -> bool {
+        self.confirm.unwrap_or(!config.auto_confirm)
+    }
+}
+
+/// `git commit` options accepted by `ai-commit commit` and forwarded to the commit.
+#[derive(Debug, Default)]
+pub struct GitCommitArgs {
+    /// `-a`: commit all modified and deleted tracked files; the model sees them too.
+    pub all: bool,
+    pub signoff: bool,
+    pub no_verify: bool,
+    /// `-S` / `--no-gpg-sign`; `None` follows the config.
+    pub sign: Option<bool>,
+    /// `--trailer` values.
+    pub trailers: Vec<Trailer>,
+    /// `--co-author` values; an empty one means "choose from recent authors".
+    pub co_authors: Vec<String>,
+    /// Everything after `--`, passed to `git commit` unchanged.
+    pub passthrough: Vec<String>,
+}
+
+impl GitCommitArgs {
+    /// `interactive` is false when nobody can answer a prompt for ambiguous co-authors.
+    fn commit_options(&self, app_config: &AppConfig, interactive: bool) -> Result<CommitOptions> {
+        let mut options =
+            CommitOptions::new(&app_config.commit).sign(self.sign).trailers_from_config(&app_config.trailers)?;
+        options.all = self.all;
+        options.signoff |= self.signoff;
+        options.no_verify = self.no_verify;
+        options.trailers.extend(self.trailers.iter().cloned());
+        options.trailers.extend(co_author_trailers(app_config, &self.co_authors, interactive)?);
+        options.git_args = self.passthrough.clone();
+        Ok(options)
+    }
+}
+
+/// The options of one `commit` invocation.
+#[derive(Debug, Default)]
+pub struct CommitArgs {
+    pub language: Option<String>,
+    pub keywords: Option<String>,
+    /// Replaces the model from the environment for this invocation.
+    pub model: Option<String>,
+    pub mode: RunMode,
+    pub context_limit: Option<usize>,
+    pub body: bool,
+    pub git: GitCommitArgs,
+    pub format: OutputFormat,
+    /// `--no-cache` / `--regenerate`.
+    pub cache: CachePolicy,
+    /// `--reuse last|ID`: commit with a message from the history instead of generating one.
+    pub reuse: Option<String>,
+}
+
+pub async fn handle_commit(args: &CommitArgs) -> Result<()> {
+    let mut report = CommitReport::default();
+    let result = generate_commit(args, &mut report, None).await;
+    if args.format.is_json() {
+        if let Err(e) = &result {
+            report.set_error(e);
+        }
+        report.print()?;
+    }
+    result
+}
+
+/// Generate the message and, unless previewing, commit with it. What was generated is
+/// recorded in `report`; `stream` receives the message text as the model writes it.
+pub(crate) async fn generate_commit(
+    args: &CommitArgs,
+    report: &mut CommitReport,
+    stream: Option<UnboundedSender<String>>,
 ) -> Result<()> {
-    let app_config = AppConfig::load_or_create()?;
-    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let (mode, context_limit, format, git_args) = (args.mode, args.context_limit, args.format, &args.git);
+    let keywords = args.keywords.as_deref();
+    let json = format.is_json();
+    // Progress messages are for people; JSON output is the document alone
+    let say = |line: ColoredString| {
+        if !json {
+            println!("{line}");
+        }
+    };
+
+    let app_config = AppConfig::load_or_create().status(ExitStatus::ConfigError)?;
     // Determine language: CLI arg > config file
-    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = args.language.as_deref().unwrap_or(app_config.commit.language.as_str());
+    let dry_run = mode.is_dry_run(&app_config.commit);
+    if json && !dry_run {
+        return Err(ExitStatus::Usage.error("--format json only previews; add --dry-run"));
+    }
+    // Co-authors are chosen up front so the prompt does not interrupt the generated message
+    let options = git_args.commit_options(&app_config, !json)?;
 
-    // Select prompts based on language
-    let (system_prompt, user_prompt_template) = if language == "zh" {
-        (app_config.prompts.system_prompt_zh.clone(), app_config.prompts.user_prompt_template_zh.clone())
-    } else {
-        (app_config.prompts.system_prompt.clone(), app_config.prompts.user_prompt_template.clone())
+    if let Some(selector) = &args.reuse {
+        return reuse_message(&app_config, selector, report, dry_run, mode, &options, format);
+    }
+
+    let mut api_config = ApiConfig::from_env(&app_config.env).status(ExitStatus::ConfigError)?;
+    if let Some(model) = &args.model {
+        api_config.model = model.clone();
+    }
+
+    // Concluding a merge: keep git's merge header and describe the merge instead of the diff
+    if let Some(merge) = get_merge_state(Some(&app_config.commit))? {
+        report.model = Some(api_config.model.clone());
+        let message = generate_merge_message(&app_config, &merge, language, context_limit, format)
+            .await
+            .status(ExitStatus::AiError)?;
+        report.set_message(&message);
+        let kept = keep_message(&app_config, "commit", &api_config.model, &message);
+        report.history_id = kept.as_ref().map(|(_, id)| *id);
+        let committed = finish_commit(&app_config, &message, Vec::new(), dry_run, mode, &options, format);
+        return settle_kept(kept, committed, json);
+    }
+
+    // Select prompts based on language; `a+b` codes produce a bilingual message
+    let bilingual = Bilingual::parse(language, app_config.commit.bilingual_style).status(ExitStatus::Usage)?;
+    let (system_prompt, user_prompt_template) = match &bilingual {
+        Some(b) => app_config.prompts.for_bilingual(&b.primary, &b.secondary),
+        None => app_config.prompts.for_language(language),
     };
 
-    let ai_client = AiClient::new(api_config, system_prompt, user_prompt_template)?;
+    let body_mode = args.body || app_config.body.enabled;
+    if body_mode && bilingual.is_some() {
+        return Err(ExitStatus::ConfigError
+            .error("--body cannot be combined with a bilingual language; use bilingual_style = \"subject-body\""));
+    }
+
+    let mut ai_client = AiClient::new(api_config, system_prompt, user_prompt_template)
+        .status(ExitStatus::AiError)?
+        .with_ledger(UsageLedger::open(&app_config.usage, "commit"));
+    if let Some(b) = &bilingual {
+        ai_client = ai_client.with_output_format(b.format_instructions());
+    }
+    if body_mode {
+        ai_client = ai_client.with_output_format(body::format_instructions(&app_config.body));
+    }
+    if let Some(sink) = stream {
+        ai_client = ai_client.with_stream(sink);
+    }
+    report.model = Some(ai_client.model().to_string());
 
-    let staged_diff = get_staged_diff(Some(&app_config.commit))?;
+    // With -a the commit takes every tracked change, so that is what the model describes
+    let staged_source = if git_args.all { DiffSource::Tracked } else { DiffSource::Staged };
+    let staged_diff = if git_args.all {
+        get_tracked_diff(Some(&app_config.commit))?
+    } else {
+        get_staged_diff(Some(&app_config.commit))?
+    };
     let unstaged_diff = get_unstaged_diff(Some(&app_config.commit))?;
 
     let limit = context_limit.unwrap_or(app_config.commit.context_limit);
 
-    let (diff_content, auto_dry_run) = if !staged_diff.is_empty() {
-        println!("{}", "Staged changes found. Generating commit message...".green());
-        (staged_diff, false)
+    let (diff_content, source) = if !staged_diff.is_empty() {
+        say("Staged changes found. Generating commit message...".green());
+        (staged_diff, staged_source)
     } else if !unstaged_diff.is_empty() {
-        println!("{}", "No staged changes found, but found unstaged changes.".yellow());
-        println!("{}", "Running in dry-run mode (no actual commit will be made).".yellow());
-        println!("{}", "To commit these changes, please stage them first with 'git add'.".yellow());
-        (unstaged_diff, true)
+        say("No staged changes found, but found unstaged changes.".yellow());
+        say("Running in dry-run mode (no actual commit will be made).".yellow());
+        say("To commit these changes, please stage them first with 'git add'.".yellow());
+        report.warnings.push("nothing is staged; the message describes unstaged changes".to_string());
+        (unstaged_diff, DiffSource::Unstaged)
     } else {
-        println!("{}", "No changes found to commit.".red());
-        return Ok(());
+        say("No changes found to commit.".red());
+        return Err(ExitStatus::NoChanges.error("No changes found to commit"));
     };
 
-    let is_dry_run = dry_run || auto_dry_run;
+    let is_dry_run = dry_run || source == DiffSource::Unstaged;
+    report.diff_source = Some(source);
+    report.truncated = diff_content.len() > limit;
+    if json {
+        report.diff_stats = Some(get_diff_stats(source, Some(&app_config.commit))?);
+    }
     let diff_content = get_truncated_diff(&diff_content, limit);
 
     if let Some(kw) = keywords {
-        println!("{}", format!("Using keywords: {kw}").cyan());
+        say(format!("Using keywords: {kw}").cyan());
     }
-    println!("{}", "Generating commit message using AI service...".cyan());
 
-    let result = if let Some(kw) = keywords {
-        ai_client.generate_commit_message_with_keywords(&diff_content, kw).await
+    let cache = ResponseCache::open(&app_config.cache, args.cache);
+    let cache_key = match &cache {
+        Some(_) => Some(cache_key(&ai_client, &app_config.commit, source, &diff_content, keywords, limit)?),
+        None => None,
+    };
+    let cached = cache.as_ref().zip(cache_key.as_deref()).and_then(|(cache, key)| cache.get(key));
+
+    let result = if let Some(raw) = cached {
+        say("Reusing the message generated earlier for these changes (--regenerate for a new one).".dimmed());
+        report.cached = true;
+        Ok(raw)
     } else {
-        ai_client.generate_commit_message(&diff_content).await
+        say("Generating commit message using AI service...".cyan());
+        let result = if let Some(kw) = keywords {
+            ai_client.generate_commit_message_with_keywords(&diff_content, kw).await
+        } else {
+            ai_client.generate_commit_message(&diff_content).await
+        };
+        report.usage = ai_client.usage();
+        if let (Ok(raw), Some(cache), Some(key)) = (&result, &cache, &cache_key)
+            && let Err(e) = cache.put(key, ai_client.model(), raw)
+        {
+            debug!("Could not cache the generated message: {e:#}");
+        }
+        result
     };
 
-    match result {
-        Ok(message) => {
-            println!("{}", "Generated commit message:".bright_cyan().bold());
-            println!("{}", "─────────────────────".bright_blue());
-            println!("{}", message.bright_green().bold());
-            println!("{}", "─────────────────────".bright_blue());
-
-            if is_dry_run {
-                println!("{}", "(Dry run mode - no actual commit made)".yellow());
-            } else if app_config.commit.auto_confirm || confirm_commit()? {
-                execute_commit_with_cli(&message)?;
-            } else {
-                println!("{}", "Commit cancelled.".red());
-            }
+    let result = match (&bilingual, result) {
+        (Some(b), Ok(raw)) => b.assemble(&raw),
+        (None, Ok(message)) => {
+            let (message, mut warnings) =
+                if body_mode { body::enforce(&message, &app_config.body) } else { (message, Vec::new()) };
+            warnings.extend(language::check_language(&message, language));
+            Ok((message, warnings))
+        }
+        (_, Err(e)) => Err(e),
+    };
+
+    match result.status(ExitStatus::AiError) {
+        Ok((message, warnings)) => {
+            report.set_message(&message);
+            report.warnings.extend(warnings.iter().cloned());
+            let kept = keep_message(&app_config, "commit", ai_client.model(), &message);
+            report.history_id = kept.as_ref().map(|(_, id)| *id);
+            let committed = finish_commit(&app_config, &message, warnings, is_dry_run, mode, &options, format);
+            settle_kept(kept, committed, json)
         }
         Err(e) => {
-            eprintln!("{} {e}", "Failed to generate commit message:".red());
-            return Err(e);
+            if !json {
+                eprintln!("{} {e}", "Failed to generate commit message:".red());
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Identifies a generation: the same staged tree on the same HEAD tree (or, for other sources, the same diff),
+/// prompts, model and options give the same key.
+fn cache_key(
+    ai_client: &AiClient,
+    commit_config: &CommitConfig,
+    source: DiffSource,
+    diff: &str,
+    keywords: Option<&str>,
+    limit: usize,
+) -> Result<String> {
+    // The staged tree alone is not the change: the same index on another HEAD is a different diff
+    let (base, changes) = match source {
+        DiffSource::Staged => {
+            let repo = open_repo()?;
+            let base = repo.head().and_then(|head| head.peel_to_tree()).map(|tree| tree.id().to_string());
+            (base.unwrap_or_default(), repo.index()?.write_tree()?.to_string())
         }
+        DiffSource::Unstaged | DiffSource::Tracked => (String::new(), diff.to_string()),
     };
+    ai_client
+        .cache_key(CacheKey::default())
+        .part("source", format!("{source:?}"))
+        .part("base", base)
+        .part("changes", changes)
+        .part("keywords", keywords.unwrap_or_default())
+        .part("context_limit", limit)
+        .part("ignore_lock_files", commit_config.ignore_lock_files)
+        .part("ignore_patterns", commit_config.custom_ignore_patterns.join("\n"))
+        .finish()
+}
 
+/// Commit with a message kept in the history instead of asking the model for one.
+fn reuse_message(
+    app_config: &AppConfig,
+    selector: &str,
+    report: &mut CommitReport,
+    dry_run: bool,
+    mode: RunMode,
+    options: &CommitOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    let Some(log) = MessageLog::open(&app_config.history) else {
+        return Err(ExitStatus::ConfigError.error("--reuse needs the message history; set [history] enabled = true"));
+    };
+    let entry = log.find(selector, current_workdir().as_deref()).status(ExitStatus::Usage)?;
+    let mut warnings = Vec::new();
+    let tree = open_repo()?.index()?.write_tree()?.to_string();
+    if entry.tree.as_deref() != Some(tree.as_str()) {
+        warnings.push(format!("message #{} was generated for different staged changes", entry.id));
+    }
+    if !format.is_json() {
+        println!("{}", format!("Reusing message #{} from the history.", entry.id).dimmed());
+    }
+    report.model = Some(entry.model.clone());
+    report.history_id = Some(entry.id);
+    report.set_message(&entry.message);
+    report.warnings.extend(warnings.iter().cloned());
+    if finish_commit(app_config, &entry.message, warnings, dry_run, mode, options, format)? {
+        log.mark_accepted(entry.id)?;
+    }
     Ok(())
 }
 
+/// Keep a generated message in the history, so it survives a cancelled or failed commit.
+/// `None` when history is off or could not be written.
+pub(crate) fn keep_message(
+    app_config: &AppConfig,
+    command: &str,
+    model: &str,
+    message: &str,
+) -> Option<(MessageLog, u64)> {
+    let log = MessageLog::open(&app_config.history)?;
+    match log.record(command, model, message) {
+        Ok(id) => Some((log, id)),
+        Err(e) => {
+            debug!("Could not keep the generated message: {e:#}");
+            None
+        }
+    }
+}
+
+/// Mark the kept message accepted once committed; when the commit was cancelled or failed,
+/// say how to commit with it later.
+fn settle_kept(kept: Option<(MessageLog, u64)>, committed: Result<bool>, json: bool) -> Result<()> {
+    match (committed, kept) {
+        (Ok(true), Some((log, id))) => {
+            if let Err(e) = log.mark_accepted(id) {
+                debug!("Could not mark message #{id} as committed: {e:#}");
+            }
+            Ok(())
+        }
+        (Ok(_), _) => Ok(()),
+        (Err(e), Some((_, id))) => {
+            if !json {
+                eprintln!(
+                    "{}",
+                    format!("The message is kept as #{id}; commit with it using `ai-commit commit --reuse last`.")
+                        .yellow()
+                );
+            }
+            Err(e)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// Show the generated message and commit with it after confirmation; `true` once committed.
+/// JSON output only previews, so there is nothing to show or commit here.
+fn finish_commit(
+    app_config: &AppConfig,
+    message: &str,
+    warnings: Vec<String>,
+    is_dry_run: bool,
+    mode: RunMode,
+    options: &CommitOptions,
+    format: OutputFormat,
+) -> Result<bool> {
+    if format.is_json() {
+        return Ok(false);
+    }
+    println!("{}", "Generated commit message:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    println!("{}", message.bright_green().bold());
+    println!("{}", "─────────────────────".bright_blue());
+
+    if !options.trailers.is_empty() || options.signoff {
+        println!("{}", "Trailers to add:".cyan());
+        for trailer in &options.trailers {
+            println!("  {trailer}");
+        }
+        if options.signoff {
+            println!("  Signed-off-by: <committer>");
+        }
+    }
+
+    for warning in warnings {
+        println!("{}", format!("Warning: {warning}.").yellow());
+    }
+
+    if is_dry_run {
+        println!("{}", "(Dry run mode - no actual commit made)".yellow());
+        if mode.dry_run.is_none() && app_config.commit.dry_run_by_default {
+            println!("{}", "dry_run_by_default is set; pass --commit to commit.".dimmed());
+        }
+    } else if !mode.needs_confirmation(&app_config.commit) || confirm_commit()? {
+        execute_commit(message, options)?;
+        return Ok(true);
+    } else {
+        println!("{}", "Commit cancelled.".red());
+        return Err(ExitStatus::Cancelled.error("Commit cancelled"));
+    }
+    Ok(false)
+}
+
+/// `Co-authored-by` trailers for the `--co-author` values. A name or email picks matching
+/// recent authors; an empty value lists recent authors to choose from. Without
+/// `interactive`, anything that would need that choice is a usage error instead.
+fn co_author_trailers(app_config: &AppConfig, queries: &[String], interactive: bool) -> Result<Vec<Trailer>> {
+    if queries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let authors = trailers::recent_authors(&open_repo()?, app_config.trailers.recent_authors)?;
+
+    let mut selected: Vec<Author> = Vec::new();
+    for query in queries {
+        if !interactive && query.trim().is_empty() {
+            return Err(ExitStatus::Usage.error("--format json cannot ask for co-authors; name them"));
+        }
+        let candidates =
+            if query.trim().is_empty() { authors.clone() } else { trailers::match_authors(&authors, query)? };
+        match candidates.as_slice() {
+            [] => anyhow::bail!("No other authors found in recent history; use --co-author 'Name <email>'"),
+            [only] if !query.trim().is_empty() => selected.push(only.clone()),
+            _ if interactive => selected.extend(choose_authors(&candidates)?),
+            _ => {
+                let names: Vec<String> = candidates.iter().map(Author::to_string).collect();
+                return Err(ExitStatus::Usage.error(format!(
+                    "--co-author '{query}' matches {}; --format json cannot ask which",
+                    names.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(selected.iter().map(|a| Trailer::new("Co-authored-by", a.to_string())).collect())
+}
+
+fn choose_authors(candidates: &[Author]) -> Result<Vec<Author>> {
+    println!("{}", "Recent authors:".bright_cyan().bold());
+    for (i, author) in candidates.iter().enumerate() {
+        println!("  {:>2}) {author}", i + 1);
+    }
+    print!("Co-authors (numbers separated by spaces or commas, empty for none): ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    parse_selection(&input, candidates.len())?.into_iter().map(|i| Ok(candidates[i].clone())).collect()
+}
+
+/// 0-based indices from a list like `1, 3 4`.
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for part in input.split([',', ' ']).map(str::trim).filter(|p| !p.is_empty()) {
+        match part.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => {
+                if !indices.contains(&(n - 1)) {
+                    indices.push(n - 1);
+                }
+            }
+            _ => anyhow::bail!("'{part}' is not a number between 1 and {count}"),
+        }
+    }
+    Ok(indices)
+}
+
 fn confirm_commit() -> Result<bool> {
     print!("Do you want to commit with this message? (y/N): ");
     io::stdout().flush()?;
@@ -91,3 +494,44 @@ fn confirm_commit() -> Result<bool> {
 
     Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_mode_precedence() {
+        let config = |dry_run_by_default, auto_confirm| CommitConfig {
+            dry_run_by_default,
+            auto_confirm,
+            ..CommitConfig::default()
+        };
+        let mode = |dry_run, confirm| RunMode { dry_run, confirm };
+
+        // Without flags the config decides.
+        assert!(!mode(None, None).is_dry_run(&config(false, false)));
+        assert!(mode(None, None).is_dry_run(&config(true, false)));
+        assert!(mode(None, None).needs_confirmation(&config(false, false)));
+        assert!(!mode(None, None).needs_confirmation(&config(false, true)));
+
+        // --dry-run and --commit override dry_run_by_default either way.
+        assert!(mode(Some(true), None).is_dry_run(&config(false, false)));
+        assert!(!mode(Some(false), None).is_dry_run(&config(true, false)));
+
+        // --confirm and --yes override auto_confirm either way.
+        assert!(mode(None, Some(true)).needs_confirmation(&config(false, true)));
+        assert!(!mode(None, Some(false)).needs_confirmation(&config(false, false)));
+
+        // The two settings are independent.
+        let both = mode(Some(false), Some(false));
+        assert!(!both.is_dry_run(&config(true, false)) && !both.needs_confirmation(&config(true, false)));
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("1, 3 3", 3).unwrap(), vec![0, 2]);
+        assert!(parse_selection("  ", 3).unwrap().is_empty());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("x", 3).is_err());
+    }
+}
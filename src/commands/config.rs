@@ -67,12 +67,57 @@ pub fn show_config() -> Result<()> {
     println!("  ignore_lock_files = {}", config.commit.ignore_lock_files);
     println!("  context_limit = {}", config.commit.context_limit);
     println!("  custom_ignore_patterns = {:?}", config.commit.custom_ignore_patterns);
+    println!("  language = \"{}\"", config.commit.language.bright_green());
+    println!("  bilingual_style = \"{}\"", config.commit.bilingual_style);
+    println!("  gpg_sign = {:?}", config.commit.gpg_sign);
+    println!("  backend = \"{}\"", config.commit.backend);
+    println!("  run_hooks = {}", config.commit.run_hooks);
+    println!();
+    println!("[body]");
+    println!("  enabled = {}", config.body.enabled);
+    println!("  wrap_width = {}", config.body.wrap_width);
+    println!("  max_bullets = {}", config.body.max_bullets);
+    println!("  footers = {}", config.body.footers);
+    println!();
+    println!("[pr]");
+    println!("  base_branch = {:?}", config.pr.base_branch);
+    println!();
+    println!("[reword]");
+    println!("  protected_branches = {:?}", config.reword.protected_branches);
+    println!();
+    println!("[trailers]");
+    println!("  signoff = {}", config.trailers.signoff);
+    println!("  custom = {:?}", config.trailers.custom);
+    println!("  recent_authors = {}", config.trailers.recent_authors);
+    println!();
+    println!("[cache]");
+    println!("  enabled = {}", config.cache.enabled);
+    println!("  ttl_hours = {}", config.cache.ttl_hours);
+    println!("  max_entries = {}", config.cache.max_entries);
+    println!();
+    println!("[usage]");
+    println!("  enabled = {}", config.usage.enabled);
+    println!("  daily_budget = {:?}", config.usage.daily_budget);
+    println!("  monthly_budget = {:?}", config.usage.monthly_budget);
+    println!("  on_budget = {}", config.usage.on_budget);
+    for (model, price) in &config.usage.prices {
+        println!("  prices.{model} = {{ input = {}, output = {} }}", price.input, price.output);
+    }
+    println!();
+    println!("[history]");
+    println!("  enabled = {}", config.history.enabled);
+    println!("  max_entries = {}", config.history.max_entries);
+    println!();
+    println!("[review]");
+    println!("  fail_on = {}", config.review.fail_on);
     println!();
     println!("[prompts]");
     let sys_len = config.prompts.system_prompt.len().min(50);
     println!("  system_prompt = \"{}...\"", &config.prompts.system_prompt[..sys_len]);
     let user_len = config.prompts.user_prompt_template.len().min(50);
     println!("  user_prompt_template = \"{}...\"", &config.prompts.user_prompt_template[..user_len]);
+    let languages: Vec<&str> = config.prompts.languages.keys().map(|k| k.as_str()).collect();
+    println!("  languages = {languages:?} (others use generic_system_prompt)");
 
     Ok(())
 }
@@ -97,6 +142,14 @@ pub fn edit_prompts_help() -> Result<()> {
     println!("  • Use {{diff}} as placeholder for the git diff content");
     println!("  • Controls how AI analyzes changes");
     println!();
+    println!("{}", "[prompts.languages.<code>]".yellow());
+    println!("  • Per-language system_prompt and user_prompt_template, e.g. [prompts.languages.ja]");
+    println!("  • Select with [commit] language = \"ja\" or ai-commit -l ja");
+    println!();
+    println!("{}", "[prompts.generic_system_prompt / generic_user_prompt_template]".yellow());
+    println!("  • Fallback for languages without their own entry");
+    println!("  • Use {{language}} as placeholder for the language name (e.g. German)");
+    println!();
     println!("{}", "[env]".yellow());
     println!("  • Customize environment variable names for API configuration");
     println!("  • endpoint_env, api_key_env, model_env");
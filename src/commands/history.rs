@@ -0,0 +1,94 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::ai::ledger;
+use crate::ai::message_log::{self, GeneratedMessage, MessageLog};
+use crate::config::AppConfig;
+use crate::git::current_workdir;
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+fn format_time(timestamp: u64) -> String {
+    let minutes = timestamp % 86_400 / 60;
+    format!("{} {:02}:{:02}", ledger::day_of(timestamp), minutes / 60, minutes % 60)
+}
+
+fn format_status(entry: &GeneratedMessage) -> ColoredString {
+    if entry.accepted { "committed".green() } else { "not committed".yellow() }
+}
+
+/// List the newest generated messages of this repository (or of every repository), or
+/// show message `id` in full.
+pub fn handle_history(id: Option<u64>, all: bool, limit: usize) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let Some(path) = MessageLog::log_path() else {
+        anyhow::bail!("Could not find the data directory for the message history");
+    };
+    let mut entries = message_log::read_entries(&path, app_config.history.max_entries)?;
+
+    if let Some(id) = id {
+        let Some(entry) = entries.into_iter().find(|e| e.id == id) else {
+            anyhow::bail!("No generated message #{id}");
+        };
+        println!("{}", format!("Message #{}", entry.id).bright_cyan().bold());
+        println!("Generated: {} UTC by `{}` with {}", format_time(entry.timestamp), entry.command, entry.model);
+        println!("Repository: {}", entry.repo.as_deref().unwrap_or("(no repository)"));
+        println!("Branch: {}", entry.branch.as_deref().unwrap_or("(detached)"));
+        println!("Tree: {}", entry.tree.as_deref().unwrap_or("-"));
+        println!("Status: {}", format_status(&entry));
+        println!("{}", "─────────────────────".bright_blue());
+        println!("{}", entry.message.trim_end());
+        println!("{}", "─────────────────────".bright_blue());
+        if !entry.accepted {
+            println!("{}", format!("Commit with it using `ai-commit commit --reuse {}`.", entry.id).dimmed());
+        }
+        return Ok(());
+    }
+
+    if !all {
+        let workdir = current_workdir();
+        entries.retain(|e| e.repo == workdir);
+    }
+    if entries.is_empty() {
+        println!("{}", "No generated messages kept yet.".yellow());
+        if !app_config.history.enabled {
+            println!("{}", "History is off; set [history] enabled = true to keep them.".dimmed());
+        } else if !all {
+            println!("{}", "Pass --all to include other repositories.".dimmed());
+        }
+        return Ok(());
+    }
+
+    println!("{}", "Generated messages, newest first (UTC)".bright_cyan().bold());
+    for entry in entries.iter().rev().take(limit) {
+        let subject = entry.message.lines().next().unwrap_or_default();
+        let repo =
+            if all { format!("{}  ", entry.repo.as_deref().unwrap_or("(no repository)")) } else { String::new() };
+        println!(
+            "{:>5}  {}  {repo}{}  {}  {:<13}  {subject}",
+            format!("#{}", entry.id).bold(),
+            format_time(entry.timestamp),
+            entry.branch.as_deref().unwrap_or("(detached)"),
+            entry.model.dimmed(),
+            format_status(entry)
+        );
+    }
+    if entries.len() > limit {
+        println!("{}", format!("{} older messages not shown; see -n.", entries.len() - limit).dimmed());
+    }
+    println!(
+        "{}",
+        "Show one with `ai-commit history <ID>`; commit with it using `ai-commit commit --reuse <ID>`.".dimmed()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(1_792_368_000 + 14 * 3600 + 3 * 60 + 59), "2026-10-19 14:03");
+        assert_eq!(format_time(0), "1970-01-01 00:00");
+    }
+}
@@ -2,12 +2,17 @@ use anyhow::Result;
 use std::fs;
 use std::path::Path;
 
-pub fn install_hook() -> Result<()> {
+pub fn install_hook(review: bool) -> Result<()> {
     let hook_path = Path::new(".git/hooks/prepare-commit-msg");
     let template_path = Path::new("templates/prepare-commit-msg");
 
     fs::copy(template_path, hook_path)?;
 
+    if review {
+        fs::copy(Path::new("templates/pre-commit"), Path::new(".git/hooks/pre-commit"))?;
+        println!("Pre-commit review hook installed.");
+    }
+
     println!("Git hook installed successfully.");
     Ok(())
 }
@@ -0,0 +1,131 @@
+use anyhow::{Result, bail};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language};
+use crate::commands::output::{ExitStatus, OutputFormat, WithStatus};
+use crate::commands::pr::format_commit_list;
+use crate::commands::squash_message::with_git_comments;
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::{MergeState, get_merge_state, get_truncated_diff, parse_merge_msg};
+
+/// Generate the message for the merge in progress. With `output` (the prepare-commit-msg
+/// hook passes git's message file), the subject and conflict list are taken from that file
+/// and the result is written back to it, keeping git's `#` comment lines.
+pub async fn handle_merge_message(
+    language: Option<&str>,
+    output: Option<&Path>,
+    context_limit: Option<usize>,
+) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let Some(mut merge) = get_merge_state(Some(&app_config.commit))? else {
+        bail!("No merge in progress");
+    };
+
+    let existing = output.and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+    let (header, conflicts) = parse_merge_msg(&existing);
+    if let Some(header) = header {
+        merge.header = header;
+    }
+    if !conflicts.is_empty() {
+        merge.conflicts = conflicts;
+    }
+
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let message = generate_merge_message(&app_config, &merge, language, context_limit, OutputFormat::Text).await?;
+
+    println!("{}", "Generated merge message:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    println!("{}", message.bright_green().bold());
+    println!("{}", "─────────────────────".bright_blue());
+
+    if let Some(path) = output {
+        fs::write(path, with_git_comments(&message, &existing))?;
+        println!("{}", format!("Message written to {}", path.display()).green());
+    }
+    Ok(())
+}
+
+/// Git's merge header followed by a generated body describing the merged commits and how
+/// any conflicts were resolved.
+pub(crate) async fn generate_merge_message(
+    app_config: &AppConfig,
+    merge: &MergeState,
+    language: &str,
+    context_limit: Option<usize>,
+    format: OutputFormat,
+) -> Result<String> {
+    let api_config = ApiConfig::from_env(&app_config.env).status(ExitStatus::ConfigError)?;
+
+    // Merge bodies are single-language; a bilingual setting uses its first language.
+    let language = language::primary_language(language);
+    let (system_prompt, _) = app_config.prompts.for_language(&language);
+    let ai_client = AiClient::new(api_config, system_prompt, app_config.prompts.merge_prompt_template.clone())?
+        .with_ledger(UsageLedger::open(&app_config.usage, "merge-message"));
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let conflicts = if merge.conflicts.is_empty() { "none".to_string() } else { merge.conflicts.join(", ") };
+    // Conflict resolutions matter most; they get up to half of the budget.
+    let resolution = get_truncated_diff(&merge.resolution, limit / 2);
+    let diff = get_truncated_diff(&merge.diff, limit.saturating_sub(resolution.len()));
+    let resolution = if resolution.is_empty() { "(no conflicts)".to_string() } else { resolution };
+
+    if !format.is_json() {
+        println!(
+            "{}",
+            format!(
+                "Merge in progress: {} ({} commits, {} conflicted files)",
+                merge.header,
+                merge.commits.len(),
+                merge.conflicts.len()
+            )
+            .green()
+        );
+        println!("{}", "Generating merge commit message using AI service...".cyan());
+    }
+    let body = match ai_client
+        .generate_with_vars(&[
+            ("header", &merge.header),
+            ("commits", &format_commit_list(&merge.commits)),
+            ("conflicts", &conflicts),
+            ("resolution", &resolution),
+            ("diff", &diff),
+        ])
+        .await
+    {
+        Ok(body) => body,
+        Err(e) => {
+            if !format.is_json() {
+                eprintln!("{} {e}", "Failed to generate merge message:".red());
+            }
+            return Err(e);
+        }
+    };
+
+    Ok(assemble(&merge.header, &body))
+}
+
+/// Keep git's header as the subject even if the model repeated it at the top of the body.
+fn assemble(header: &str, body: &str) -> String {
+    let body = body.trim();
+    let body = body.strip_prefix(header).unwrap_or(body).trim();
+    if body.is_empty() { header.to_string() } else { format!("{header}\n\n{body}") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_keeps_header() {
+        let header = "Merge branch 'feature' into main";
+        assert_eq!(assemble(header, "- add retry\n"), "Merge branch 'feature' into main\n\n- add retry");
+        assert_eq!(
+            assemble(header, "Merge branch 'feature' into main\n\n- add retry"),
+            "Merge branch 'feature' into main\n\n- add retry"
+        );
+        assert_eq!(assemble(header, "  "), header);
+    }
+}
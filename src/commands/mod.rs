@@ -1,5 +1,19 @@
 pub mod amend;
+pub mod changelog;
 pub mod commit;
 pub mod config;
+pub mod history;
 pub mod install;
+pub mod merge_message;
+pub mod output;
+pub mod pr;
+pub mod review;
+pub mod reword;
+pub mod serve;
+pub mod split;
+pub mod squash_message;
+pub mod stage;
+pub mod stats;
+pub mod tui;
 pub mod uninstall;
+pub mod version_bump;
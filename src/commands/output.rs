@@ -0,0 +1,236 @@
+//! Machine-readable output (`--format json`) and the exit codes scripts can rely on.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+
+use crate::ai::Usage;
+use crate::git::{DiffSource, DiffStats};
+
+/// Version of the `--format json` document; bumped only for incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How a command reports its result on stdout.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored progress messages and prompts for a terminal.
+    #[default]
+    Text,
+    /// A single JSON document and nothing else; never prompts.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == OutputFormat::Json
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("unknown format '{other}' (expected text or json)")),
+        }
+    }
+}
+
+/// Why a command stopped, as the process exit code tells it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    /// Any other failure, such as not being in a git repository.
+    Error,
+    /// Invalid combination of options; clap uses the same code for bad arguments.
+    Usage,
+    NoChanges,
+    /// Unreadable config file, missing API key or model, invalid prompt settings.
+    ConfigError,
+    /// The AI service failed or returned an unusable response.
+    AiError,
+    /// The user declined the confirmation prompt.
+    Cancelled,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> u8 {
+        match self {
+            ExitStatus::Error => 1,
+            ExitStatus::Usage => 2,
+            ExitStatus::NoChanges => 3,
+            ExitStatus::ConfigError => 4,
+            ExitStatus::AiError => 5,
+            ExitStatus::Cancelled => 6,
+        }
+    }
+
+    /// An error with `message` that exits with this status.
+    pub fn error<M>(self, message: M) -> anyhow::Error
+    where
+        M: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.wrap(anyhow::Error::msg(message))
+    }
+
+    fn wrap(self, error: anyhow::Error) -> anyhow::Error {
+        anyhow::Error::new(Failure { status: self, error })
+    }
+
+    /// The status `error` was tagged with, or [`ExitStatus::Error`].
+    pub fn of(error: &anyhow::Error) -> Self {
+        error.downcast_ref::<Failure>().map_or(ExitStatus::Error, |f| f.status)
+    }
+
+    /// Outcomes the command has already explained; nothing more needs printing.
+    pub fn is_quiet(self) -> bool {
+        matches!(self, ExitStatus::NoChanges | ExitStatus::Cancelled)
+    }
+}
+
+/// An error tagged with the exit status it should produce. Displays as the error it wraps.
+#[derive(Debug)]
+pub struct Failure {
+    pub status: ExitStatus,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Tag the error of a result with an exit status, unless it already has one.
+pub trait WithStatus<T> {
+    fn status(self, status: ExitStatus) -> Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> WithStatus<T> for std::result::Result<T, E> {
+    fn status(self, status: ExitStatus) -> Result<T> {
+        self.map_err(|e| {
+            let error = e.into();
+            if error.downcast_ref::<Failure>().is_some() { error } else { status.wrap(error) }
+        })
+    }
+}
+
+/// The `--format json` document for `commit`. Every key is always present so consumers can
+/// rely on the shape; values that do not apply are `null`, empty or zero.
+#[derive(Debug, Serialize)]
+pub struct CommitReport {
+    pub schema_version: u32,
+    /// `ok`, or the [`ExitStatus`] the process exits with.
+    pub status: String,
+    pub message: Option<String>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+    pub model: Option<String>,
+    pub usage: Option<Usage>,
+    /// Which change was described: `staged`, `unstaged` (nothing staged) or `tracked` (`-a`).
+    pub diff_source: Option<DiffSource>,
+    pub diff_stats: Option<DiffStats>,
+    /// Whether the diff was cut to `context_limit` before it was sent.
+    pub truncated: bool,
+    /// Whether the message was reused from the local cache instead of generated.
+    pub cached: bool,
+    /// Id of the message in `ai-commit history`, for `commit --reuse`; `None` when history is off.
+    pub history_id: Option<u64>,
+    pub warnings: Vec<String>,
+    pub dry_run: bool,
+    pub error: Option<String>,
+}
+
+impl Default for CommitReport {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            status: "ok".to_string(),
+            message: None,
+            subject: None,
+            body: None,
+            model: None,
+            usage: None,
+            diff_source: None,
+            diff_stats: None,
+            truncated: false,
+            cached: false,
+            history_id: None,
+            warnings: Vec::new(),
+            dry_run: true,
+            error: None,
+        }
+    }
+}
+
+impl CommitReport {
+    /// Fill `message`, `subject` and `body` from a generated message.
+    pub fn set_message(&mut self, message: &str) {
+        let message = message.trim();
+        let (subject, body) = match message.split_once('\n') {
+            Some((subject, body)) => (subject.trim(), Some(body.trim())),
+            None => (message, None),
+        };
+        self.message = Some(message.to_string());
+        self.subject = Some(subject.to_string());
+        self.body = body.filter(|b| !b.is_empty()).map(str::to_string);
+    }
+
+    /// Record `error` and its exit status.
+    pub fn set_error(&mut self, error: &anyhow::Error) {
+        let status = serde_json::to_value(ExitStatus::of(error)).ok();
+        self.status = status.and_then(|s| s.as_str().map(str::to_string)).unwrap_or_else(|| "error".to_string());
+        self.error = Some(format!("{error:#}"));
+    }
+
+    pub fn print(&self) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(self)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_is_kept_through_context() {
+        let error: Result<()> = Err(anyhow::anyhow!("API request failed (500)")).status(ExitStatus::AiError);
+        let error = error.status(ExitStatus::ConfigError).unwrap_err();
+        assert_eq!(ExitStatus::of(&error), ExitStatus::AiError);
+        assert_eq!(error.to_string(), "API request failed (500)");
+        assert_eq!(ExitStatus::of(&anyhow::anyhow!("other")), ExitStatus::Error);
+        assert_eq!(ExitStatus::of(&error.context("while committing")), ExitStatus::AiError);
+    }
+
+    #[test]
+    fn test_report_schema() {
+        let mut report = CommitReport::default();
+        report.set_message("feat: add x\n\nWhy it matters.\n");
+        assert_eq!(report.subject.as_deref(), Some("feat: add x"));
+        assert_eq!(report.body.as_deref(), Some("Why it matters."));
+
+        let json = serde_json::to_value(&report).unwrap();
+        let keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        for key in ["schema_version", "status", "message", "subject", "body", "model", "usage", "diff_source"] {
+            assert!(keys.contains(&key), "missing {key}");
+        }
+        for key in ["diff_stats", "truncated", "cached", "history_id", "warnings", "dry_run", "error"] {
+            assert!(keys.contains(&key), "missing {key}");
+        }
+        assert_eq!(json["body"], "Why it matters.");
+        assert!(json["usage"].is_null());
+
+        report.set_error(&ExitStatus::NoChanges.error("No changes found to commit"));
+        assert_eq!(report.status, "no_changes");
+        assert_eq!(ExitStatus::NoChanges.code(), 3);
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Result, bail};
+use colored::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language};
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::{CommitInfo, current_branch, get_branch_changes, get_truncated_diff};
+
+pub async fn handle_pr(
+    base: Option<&str>,
+    language: Option<&str>,
+    output: Option<&Path>,
+    context_limit: Option<usize>,
+) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+
+    let base = base.or(app_config.pr.base_branch.as_deref());
+    let changes = get_branch_changes(base, Some(&app_config.commit))?;
+    let branch = current_branch()?.unwrap_or_else(|| "HEAD".to_string());
+
+    if changes.commits.is_empty() {
+        println!("{}", format!("No commits on '{branch}' since '{}'.", changes.base_name).red());
+        return Ok(());
+    }
+    println!(
+        "{}",
+        format!("Found {} commits on '{branch}' since '{}'.", changes.commits.len(), changes.base_name).green()
+    );
+
+    // PR text is single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let language_name = language::display_name(&language);
+
+    let system_prompt = app_config.prompts.pr_system_prompt.replace("{language}", &language_name);
+    let ai_client = AiClient::new(api_config, system_prompt, app_config.prompts.pr_user_prompt_template.clone())?
+        .with_ledger(UsageLedger::open(&app_config.usage, "pr"));
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let diff_content = get_truncated_diff(&changes.diff, limit);
+
+    println!("{}", "Generating pull request description using AI service...".cyan());
+    let raw = match ai_client
+        .generate_with_vars(&[
+            ("language", &language_name),
+            ("branch", &branch),
+            ("base", &changes.base_name),
+            ("commits", &format_commit_list(&changes.commits)),
+            ("diff", &diff_content),
+        ])
+        .await
+    {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to generate pull request description:".red());
+            return Err(e);
+        }
+    };
+    let (title, body) = parse_pr_response(&raw)?;
+
+    println!("{}", "Generated pull request:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    println!("{}", title.bright_green().bold());
+    println!();
+    println!("{body}");
+    println!("{}", "─────────────────────".bright_blue());
+
+    if let Some(path) = output {
+        fs::write(path, format!("{body}\n"))?;
+        println!("{}", format!("Description written to {}", path.display()).green());
+        println!("Create the PR with:");
+        println!(
+            "  {}",
+            format!(
+                "gh pr create --base {} --title {} --body-file {}",
+                changes.base_name,
+                shell_quote(&title),
+                path.display()
+            )
+            .yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// One line per commit, with the rest of each message indented below it.
+pub(crate) fn format_commit_list(commits: &[CommitInfo]) -> String {
+    let mut out = String::new();
+    for commit in commits {
+        let _ = writeln!(out, "- {} {}", commit.short_id, commit.summary);
+        for line in commit.message.lines().skip(1).filter(|l| !l.trim().is_empty()) {
+            let _ = writeln!(out, "    {}", line.trim_end());
+        }
+    }
+    out
+}
+
+/// Split the response into title (first line) and Markdown body.
+fn parse_pr_response(raw: &str) -> Result<(String, String)> {
+    let mut lines = raw.trim().lines();
+    let title = lines.next().unwrap_or_default().trim();
+    let title = title.trim_start_matches('#').trim();
+    let title = title.strip_prefix("Title:").unwrap_or(title).trim().trim_matches('*').trim();
+    let body = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+    if title.is_empty() || body.is_empty() {
+        bail!("AI response did not contain both a title and a description:\n{raw}");
+    }
+    Ok((title.to_string(), body))
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pr_response() {
+        let raw = "# Title: Add retry to AI client\n\n## Summary\nRetries failed requests.\n";
+        let (title, body) = parse_pr_response(raw).unwrap();
+        assert_eq!(title, "Add retry to AI client");
+        assert_eq!(body, "## Summary\nRetries failed requests.");
+    }
+
+    #[test]
+    fn test_parse_pr_response_requires_body() {
+        assert!(parse_pr_response("Just a title").is_err());
+    }
+}
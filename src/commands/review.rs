@@ -0,0 +1,260 @@
+use anyhow::{Result, bail};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language, parse_json_response};
+use crate::commands::output::{ExitStatus, WithStatus};
+use crate::config::{ApiConfig, AppConfig, Severity};
+use crate::git::hunks::{self, FilePatch};
+use crate::git::{get_truncated_diff, open_repo};
+
+#[derive(Debug, Deserialize)]
+struct Review {
+    #[serde(default)]
+    findings: Vec<Finding>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Finding {
+    file: String,
+    /// Line in the new version of the file; `None` for file-level findings.
+    #[serde(default)]
+    line: Option<u32>,
+    severity: Severity,
+    message: String,
+}
+
+/// Where a finding points in the staged changes.
+#[derive(Debug, PartialEq, Eq)]
+enum Location {
+    /// Inside the given hunk (0-based).
+    Hunk(usize),
+    /// In a staged file, but not within any changed hunk.
+    OutsideHunks,
+    /// A file that is not part of the staged changes.
+    UnknownFile,
+}
+
+const OUTPUT_FORMAT: &str = "OUTPUT FORMAT (follow exactly):\n\
+    Respond with JSON only, no code fences and no explanations:\n\
+    {\"findings\": [{\"file\": \"src/client.rs\", \"line\": 42, \"severity\": \"high\", \"message\": \"unwrap on a network error panics\"}]}\n\
+    \"severity\" is one of \"low\", \"medium\", \"high\". Use {\"findings\": []} when there is nothing to report.";
+
+pub async fn handle_review(
+    language: Option<&str>,
+    fail_on: Option<Severity>,
+    hook: bool,
+    context_limit: Option<usize>,
+) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let patches = reviewable_patches(&app_config)?;
+    if patches.is_empty() {
+        if !hook {
+            println!("{}", "No staged changes to review.".red());
+        }
+        return Ok(());
+    }
+
+    // In the pre-commit hook an unreachable AI service must not block the commit.
+    println!("{}", "Reviewing staged changes using AI service...".cyan());
+    let findings = match review_patches(&app_config, &patches, language, context_limit).await {
+        Ok(findings) => findings,
+        Err(e) if hook => {
+            eprintln!("{} {e}", "Warning: AI review skipped:".yellow());
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to review staged changes:".red());
+            return Err(e);
+        }
+    };
+
+    print_findings(&findings, &patches);
+
+    let threshold = fail_on.or(hook.then_some(app_config.review.fail_on));
+    if let Some(threshold) = threshold {
+        let blocking = findings.iter().filter(|f| f.severity >= threshold).count();
+        if blocking > 0 {
+            if hook {
+                eprintln!("{}", "Use `git commit --no-verify` to commit anyway.".dimmed());
+            }
+            bail!("{blocking} finding(s) at or above {threshold} severity");
+        }
+    }
+    Ok(())
+}
+
+/// Findings for the staged changes, most severe first.
+pub(crate) async fn review_staged(
+    app_config: &AppConfig,
+    language: Option<&str>,
+    context_limit: Option<usize>,
+) -> Result<Vec<Finding>> {
+    let patches = reviewable_patches(app_config)?;
+    if patches.is_empty() {
+        return Err(ExitStatus::NoChanges.error("No staged changes to review"));
+    }
+    review_patches(app_config, &patches, language, context_limit).await
+}
+
+/// Staged files worth reviewing: not ignored and not binary.
+fn reviewable_patches(app_config: &AppConfig) -> Result<Vec<FilePatch>> {
+    let (_, patches) = hunks::staged_patches(&open_repo()?, Some(&app_config.commit))?;
+    Ok(patches.into_iter().filter(|p| !p.ignored && !p.binary).collect())
+}
+
+async fn review_patches(
+    app_config: &AppConfig,
+    patches: &[FilePatch],
+    language: Option<&str>,
+    context_limit: Option<usize>,
+) -> Result<Vec<Finding>> {
+    let mut findings = request_review(app_config, patches, language, context_limit).await?.findings;
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| (&a.file, a.line).cmp(&(&b.file, b.line))));
+    Ok(findings)
+}
+
+async fn request_review(
+    app_config: &AppConfig,
+    patches: &[FilePatch],
+    language: Option<&str>,
+    context_limit: Option<usize>,
+) -> Result<Review> {
+    let api_config = ApiConfig::from_env(&app_config.env).status(ExitStatus::ConfigError)?;
+
+    // Findings are single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let system_prompt =
+        app_config.prompts.review_system_prompt.replace("{language}", &language::display_name(&language));
+    let ai_client = AiClient::new(api_config, system_prompt, app_config.prompts.review_prompt_template.clone())?
+        .with_ledger(UsageLedger::open(&app_config.usage, "review"))
+        .with_output_format(OUTPUT_FORMAT.to_string());
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let listing = get_truncated_diff(&render_numbered(patches), limit);
+
+    let raw = ai_client.generate_commit_message(&listing).await.status(ExitStatus::AiError)?;
+    parse_json_response(&raw).status(ExitStatus::AiError)
+}
+
+/// Render hunks with new-file line numbers so findings can be mapped back to them.
+fn render_numbered(patches: &[FilePatch]) -> String {
+    let mut out = String::new();
+    for p in patches {
+        let _ = writeln!(out, "### {} ({})", p.path, p.status_label());
+        for hunk in &p.hunks {
+            let _ = writeln!(out, "{}", hunk.header.trim_end());
+            let mut line_no = hunk.new_start;
+            for line in &hunk.lines {
+                let content = String::from_utf8_lossy(&line.content);
+                let content = content.trim_end_matches(['\n', '\r']);
+                if line.origin == '-' {
+                    let _ = writeln!(out, "{:>6} - | {content}", "");
+                } else {
+                    let _ = writeln!(out, "{line_no:>6} {} | {content}", line.origin);
+                    line_no += 1;
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn locate(patches: &[FilePatch], file: &str, line: Option<u32>) -> Location {
+    let file = file.trim().trim_start_matches("./");
+    let Some(patch) = patches.iter().find(|p| p.path == file) else {
+        return Location::UnknownFile;
+    };
+    let Some(line) = line else {
+        return Location::OutsideHunks;
+    };
+    patch
+        .hunks
+        .iter()
+        .position(|h| line >= h.new_start && line < h.new_start + h.new_lines.max(1))
+        .map_or(Location::OutsideHunks, Location::Hunk)
+}
+
+fn print_findings(findings: &[Finding], patches: &[FilePatch]) {
+    if findings.is_empty() {
+        println!("{}", "No issues found.".green());
+        return;
+    }
+    println!("{}", "Review findings:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    for finding in findings {
+        let label = format!("{:<6}", finding.severity.to_string().to_uppercase());
+        let label = match finding.severity {
+            Severity::High => label.bright_red().bold(),
+            Severity::Medium => label.bright_yellow(),
+            Severity::Low => label.dimmed(),
+        };
+        let position = match finding.line {
+            Some(line) => format!("{}:{line}", finding.file.trim()),
+            None => finding.file.trim().to_string(),
+        };
+        let note = match locate(patches, &finding.file, finding.line) {
+            Location::Hunk(i) => format!("(hunk #{})", i + 1),
+            Location::OutsideHunks if finding.line.is_some() => "(outside changed lines)".to_string(),
+            Location::OutsideHunks => String::new(),
+            Location::UnknownFile => "(not in staged changes)".to_string(),
+        };
+        println!("{label} {} {}", position.bright_white(), note.dimmed());
+        println!("       {}", finding.message.trim());
+    }
+    println!("{}", "─────────────────────".bright_blue());
+
+    let count = |s: Severity| findings.iter().filter(|f| f.severity == s).count();
+    println!(
+        "{} findings: {} high, {} medium, {} low",
+        findings.len(),
+        count(Severity::High),
+        count(Severity::Medium),
+        count(Severity::Low)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::hunks::Hunk;
+
+    /// `src/lib.rs` with two hunks, each replacing `b` with `c` between `a` and `d`.
+    fn patch() -> FilePatch {
+        let hunk = |start| Hunk::from_lines(start, start, &[(' ', "a\n"), ('-', "b\n"), ('+', "c\n"), (' ', "d\n")]);
+        FilePatch::modified("src/lib.rs", vec![hunk(10), hunk(40)])
+    }
+
+    #[test]
+    fn test_render_numbered() {
+        let rendered = render_numbered(&[patch()]);
+        assert!(rendered.contains("    10   | a\n       - | b\n    11 + | c\n    12   | d\n"));
+        assert!(rendered.contains("    41 + | c\n"));
+    }
+
+    #[test]
+    fn test_locate() {
+        let patches = vec![patch()];
+        assert_eq!(locate(&patches, "src/lib.rs", Some(11)), Location::Hunk(0));
+        assert_eq!(locate(&patches, "./src/lib.rs", Some(42)), Location::Hunk(1));
+        assert_eq!(locate(&patches, "src/lib.rs", Some(20)), Location::OutsideHunks);
+        assert_eq!(locate(&patches, "src/main.rs", Some(1)), Location::UnknownFile);
+    }
+
+    #[test]
+    fn test_parse_review() {
+        let review: Review = parse_json_response(
+            r#"{"findings": [{"file": "src/lib.rs", "line": 11, "severity": "high", "message": "panics"},
+                {"file": "src/lib.rs", "severity": "low", "message": "naming"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(review.findings.len(), 2);
+        assert_eq!(review.findings[0].severity, Severity::High);
+        assert!(review.findings[1].line.is_none());
+        assert!(Severity::High > Severity::Medium && Severity::Medium > Severity::Low);
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::{Result, bail};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+
+use crate::ai::body::{display_width, is_wide};
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language};
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::{
+    edit_file, get_commit_diff, get_commits_in_range, get_truncated_diff, open_repo, protected_branch_containing,
+    reword_commits,
+};
+
+/// Old and new messages for each commit, oldest first; edited by the user as TOML.
+#[derive(Debug, Serialize, Deserialize)]
+struct RewordPlan {
+    commits: Vec<Reword>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Reword {
+    id: String,
+    #[serde(skip)]
+    old: String,
+    message: String,
+}
+
+pub async fn handle_reword(
+    range: &str,
+    language: Option<&str>,
+    dry_run: bool,
+    sign: Option<bool>,
+    context_limit: Option<usize>,
+) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let repo = open_repo()?;
+
+    if repo.state() != git2::RepositoryState::Clean {
+        bail!("Cannot reword while a merge, rebase or cherry-pick is in progress ({:?})", repo.state());
+    }
+
+    let range = get_commits_in_range(Some(range))?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    let Some(last) = range.commits.last() else {
+        println!("{}", "No commits in range.".red());
+        return Ok(());
+    };
+    if last.id != head {
+        bail!("Only commits up to HEAD can be reworded; '{}' is not HEAD", range.to_name);
+    }
+    for commit in &range.commits {
+        if repo.find_commit(commit.id)?.parent_count() > 1 {
+            bail!("Cannot reword merge commit {} {}", commit.short_id, commit.summary);
+        }
+        if let Some(branch) = protected_branch_containing(&repo, commit.id, &app_config.reword.protected_branches)? {
+            bail!("Commit {} {} is already on protected branch '{branch}'", commit.short_id, commit.summary);
+        }
+    }
+
+    // Reworded messages are single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let (system_prompt, user_prompt_template) = app_config.prompts.for_language(&language);
+    let ai_client = AiClient::new(api_config, system_prompt, user_prompt_template)?
+        .with_ledger(UsageLedger::open(&app_config.usage, "reword"));
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+
+    println!("{}", format!("Rewording {} commits...", range.commits.len()).green());
+    let mut plan = RewordPlan { commits: Vec::new() };
+    for (i, commit) in range.commits.iter().enumerate() {
+        println!("{}", format!("[{}/{}] {} {}", i + 1, range.commits.len(), commit.short_id, commit.summary).cyan());
+        let diff = get_commit_diff(&repo, commit.id, Some(&app_config.commit))?;
+        let message = if diff.is_empty() {
+            commit.message.trim().to_string()
+        } else {
+            match ai_client.generate_commit_message(&get_truncated_diff(&diff, limit)).await {
+                Ok(message) => message.trim().to_string(),
+                Err(e) => {
+                    eprintln!("{} {e}", "Failed to generate commit message:".red());
+                    return Err(e);
+                }
+            }
+        };
+        plan.commits.push(Reword { id: commit.short_id.clone(), old: commit.message.trim().to_string(), message });
+    }
+
+    loop {
+        print_preview(&plan);
+        if dry_run {
+            println!("{}", "(Dry run mode - history not rewritten)".yellow());
+            return Ok(());
+        }
+        match prompt_action()? {
+            Action::Accept => break,
+            Action::Edit => edit_plan(&repo, &mut plan)?,
+            Action::Cancel => {
+                println!("{}", "Reword cancelled.".red());
+                return Ok(());
+            }
+        }
+    }
+
+    if plan.commits.iter().any(|c| c.message.trim().is_empty()) {
+        bail!("Commit messages must not be empty");
+    }
+    let rewrites: Vec<(git2::Oid, String)> =
+        range.commits.iter().zip(&plan.commits).map(|(c, r)| (c.id, format!("{}\n", r.message.trim()))).collect();
+    let new_head = reword_commits(&repo, &rewrites, sign.or(app_config.commit.gpg_sign))?;
+
+    println!("{}", format!("Reworded {} commits; HEAD is now {}.", rewrites.len(), &new_head.to_string()[..7]).green());
+    println!("{}", format!("The previous history is still available as {}.", &head.to_string()[..7]).dimmed());
+    Ok(())
+}
+
+/// Old and new messages side by side, one block per commit.
+fn print_preview(plan: &RewordPlan) {
+    let width = std::env::var("COLUMNS").ok().and_then(|c| c.parse::<usize>().ok()).unwrap_or(120);
+    let column = width.saturating_sub(3).max(40) / 2;
+
+    println!("{}", "─".repeat(column * 2 + 3).bright_blue());
+    println!("{} │ {}", pad("Current", column).bold(), "Proposed".bold());
+    for commit in &plan.commits {
+        println!("{}", "─".repeat(column * 2 + 3).bright_blue());
+        println!("{}", commit.id.bright_yellow());
+        let old: Vec<&str> = commit.old.lines().collect();
+        let new: Vec<&str> = commit.message.lines().collect();
+        for row in 0..old.len().max(new.len()) {
+            let left = pad(old.get(row).copied().unwrap_or_default(), column);
+            let right = truncate(new.get(row).copied().unwrap_or_default(), column);
+            let right = if commit.old == commit.message { right.normal() } else { right.bright_green() };
+            println!("{} │ {right}", left.dimmed());
+        }
+    }
+    println!("{}", "─".repeat(column * 2 + 3).bright_blue());
+}
+
+/// Cut `s` to at most `width` display columns, marking the cut with `…`.
+fn truncate(s: &str, width: usize) -> String {
+    if display_width(s) <= width {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = if is_wide(c) { 2 } else { 1 };
+        if used + w > width.saturating_sub(1) {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let s = truncate(s, width);
+    let fill = width.saturating_sub(display_width(&s));
+    format!("{s}{}", " ".repeat(fill))
+}
+
+enum Action {
+    Accept,
+    Edit,
+    Cancel,
+}
+
+fn prompt_action() -> Result<Action> {
+    print!("Rewrite these commits? [y]es / [e]dit messages / [N]o: ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => Action::Accept,
+        "e" | "edit" => Action::Edit,
+        _ => Action::Cancel,
+    })
+}
+
+/// Round-trip the new messages through the user's editor as TOML. Commits cannot be
+/// added, removed or reordered, only their messages changed.
+fn edit_plan(repo: &git2::Repository, plan: &mut RewordPlan) -> Result<()> {
+    let path = repo.path().join("AI_COMMIT_REWORD.toml");
+    let header = "# Edit the new commit messages, then save and close the editor.\n\
+                  # Commits are listed oldest first; do not change the ids.\n\n";
+    fs::write(&path, format!("{header}{}", toml::to_string_pretty(plan)?))?;
+    edit_file(&path)?;
+    let content = fs::read_to_string(&path)?;
+    let _ = fs::remove_file(&path);
+
+    let edited: RewordPlan =
+        toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse edited messages: {e}"))?;
+    if edited.commits.len() != plan.commits.len()
+        || edited.commits.iter().zip(&plan.commits).any(|(e, p)| e.id.trim() != p.id)
+    {
+        bail!("The edited file must list the same commits in the same order");
+    }
+    for (commit, edited) in plan.commits.iter_mut().zip(edited.commits) {
+        commit.message = edited.message.trim().to_string();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_and_pad() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a longer subject", 8), "a longe…");
+        assert_eq!(truncate("修复提交信息生成", 7), "修复提…");
+        assert_eq!(display_width(&pad("修复", 6)), 6);
+    }
+}
@@ -0,0 +1,390 @@
+//! `ai-commit serve`: JSON-RPC 2.0 for editor integrations.
+//!
+//! Messages are single-line JSON objects separated by newlines, over stdin/stdout or a TCP
+//! connection on the loopback interface. Requests run concurrently and can be cancelled with
+//! a `$/cancelRequest` notification. While a request runs the server sends `$/progress`
+//! notifications, and `generateMessage` streams the text being generated as `$/delta`.
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::ai::cache::CachePolicy;
+use crate::commands::commit::{self, CommitArgs, GitCommitArgs, RunMode};
+use crate::commands::output::{CommitReport, ExitStatus, OutputFormat, WithStatus};
+use crate::commands::review;
+use crate::config::AppConfig;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The code LSP uses for cancelled requests.
+const REQUEST_CANCELLED: i64 = -32800;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// `generateMessage` parameters; the `commit` options that make sense without a terminal.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GenerateParams {
+    language: Option<String>,
+    keywords: Option<String>,
+    body: bool,
+    context_limit: Option<usize>,
+    /// Describe all tracked changes, as `commit -a` would commit them.
+    all: bool,
+    /// Ask the model again instead of reusing a cached message.
+    regenerate: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReviewParams {
+    language: Option<String>,
+    context_limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelParams {
+    id: Value,
+}
+
+/// An error response: a JSON-RPC code, a message and, for command failures, the status.
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+
+    /// Command failures use `-32000 - exit code`, with the exit status name in `data`.
+    fn from_error(error: &anyhow::Error) -> Self {
+        let status = ExitStatus::of(error);
+        let code = match status {
+            ExitStatus::Usage => INVALID_PARAMS,
+            ExitStatus::Cancelled => REQUEST_CANCELLED,
+            _ => -32000 - i64::from(status.code()),
+        };
+        Self { code, message: format!("{error:#}"), data: Some(json!({ "status": status })) }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+/// Sends responses and notifications to the client through the connection's writer task.
+#[derive(Clone)]
+struct Outbox(UnboundedSender<Value>);
+
+impl Outbox {
+    fn respond(&self, id: Value, result: Result<Value, RpcError>) {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error.to_json() }),
+        };
+        // The writer only stops when the client has gone away; nobody is left to tell.
+        let _ = self.0.send(message);
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let _ = self.0.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+/// Requests still running, by id, so `$/cancelRequest` can stop them. Whoever removes an
+/// entry (the finished request or the cancellation) sends the one response for it.
+type Running = Arc<Mutex<HashMap<String, AbortHandle>>>;
+
+pub async fn handle_serve(stdio: bool, port: Option<u16>) -> Result<()> {
+    if stdio {
+        return serve_connection(tokio::io::stdin(), tokio::io::stdout()).await;
+    }
+    let Some(port) = port else {
+        return Err(ExitStatus::Usage.error("Pass --stdio or --port"));
+    };
+    // Only local clients: the server acts on this repository with the user's API key.
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            let (reader, writer) = socket.into_split();
+            if let Err(e) = serve_connection(reader, writer).await {
+                eprintln!("Connection from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+/// Answer requests from `reader` until it closes or the client sends `shutdown`.
+async fn serve_connection<R, W>(reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+            writer.flush().await?;
+        }
+        anyhow::Ok(())
+    });
+
+    let outbox = Outbox(tx);
+    let running: Running = Arc::default();
+    let mut tasks: Vec<JoinHandle<()>> = Vec::new();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let code = if serde_json::from_str::<Value>(&line).is_ok() { INVALID_REQUEST } else { PARSE_ERROR };
+                outbox.respond(Value::Null, Err(RpcError::new(code, e.to_string())));
+                continue;
+            }
+        };
+
+        match (request.method.as_str(), request.id) {
+            ("$/cancelRequest", _) => {
+                if let Ok(params) = serde_json::from_value::<CancelParams>(request.params) {
+                    let handle = running.lock().unwrap_or_else(|e| e.into_inner()).remove(&params.id.to_string());
+                    if let Some(handle) = handle {
+                        handle.abort();
+                        outbox.respond(params.id, Err(RpcError::new(REQUEST_CANCELLED, "Request cancelled")));
+                    }
+                }
+            }
+            ("shutdown", Some(id)) => {
+                // Requests sent before shutdown are still answered.
+                for task in tasks.drain(..) {
+                    let _ = task.await;
+                }
+                outbox.respond(id, Ok(Value::Null));
+                break;
+            }
+            // Other notifications need no answer and there are none this server acts on.
+            (_, None) => {}
+            (_, Some(id)) => {
+                tasks.retain(|task| !task.is_finished());
+                tasks.push(spawn_request(id, request.method, request.params, &outbox, &running));
+            }
+        }
+    }
+
+    // The client is gone or done: stop what it was waiting for.
+    for (_, handle) in running.lock().unwrap_or_else(|e| e.into_inner()).drain() {
+        handle.abort();
+    }
+    drop(outbox);
+    writer_task.await?
+}
+
+fn spawn_request(id: Value, method: String, params: Value, outbox: &Outbox, running: &Running) -> JoinHandle<()> {
+    let key = id.to_string();
+    let (task_outbox, task_running, task_key) = (outbox.clone(), running.clone(), key.clone());
+    // Held until the handle is stored, so the task cannot finish before it is registered.
+    let mut guard = running.lock().unwrap_or_else(|e| e.into_inner());
+    let handle = tokio::spawn(async move {
+        let result = dispatch(&method, params, &id, &task_outbox).await;
+        let still_running = task_running.lock().unwrap_or_else(|e| e.into_inner()).remove(&task_key).is_some();
+        if still_running {
+            task_outbox.respond(id, result);
+        }
+    });
+    guard.insert(key, handle.abort_handle());
+    handle
+}
+
+async fn dispatch(method: &str, params: Value, id: &Value, outbox: &Outbox) -> Result<Value, RpcError> {
+    match method {
+        "generateMessage" => generate_message(parse_params(params)?, id, outbox).await,
+        "review" => review_staged(parse_params(params)?, id, outbox).await,
+        "getConfig" => get_config(),
+        #[cfg(test)]
+        "$/test/hang" => std::future::pending().await,
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{method}'"))),
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned + Default>(params: Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// The `commit --dry-run --format json` document for the current changes.
+async fn generate_message(params: GenerateParams, id: &Value, outbox: &Outbox) -> Result<Value, RpcError> {
+    let args = CommitArgs {
+        language: params.language,
+        keywords: params.keywords,
+        model: None,
+        mode: RunMode { dry_run: Some(true), confirm: Some(false) },
+        context_limit: params.context_limit,
+        body: params.body,
+        git: GitCommitArgs { all: params.all, ..Default::default() },
+        format: OutputFormat::Json,
+        cache: if params.regenerate { CachePolicy::Refresh } else { CachePolicy::Use },
+        reuse: None,
+    };
+
+    let (sink, mut deltas) = mpsc::unbounded_channel::<String>();
+    let (delta_outbox, delta_id) = (outbox.clone(), id.clone());
+    let forward = tokio::spawn(async move {
+        while let Some(text) = deltas.recv().await {
+            delta_outbox.notify("$/delta", json!({ "id": delta_id, "text": text }));
+        }
+    });
+
+    outbox.notify("$/progress", json!({ "id": id, "message": "Generating commit message" }));
+    let mut report = CommitReport::default();
+    let result = commit::generate_commit(&args, &mut report, Some(sink)).await;
+    // The sink was dropped with the client; wait so every delta goes out before the result.
+    let _ = forward.await;
+    result.map_err(|e| RpcError::from_error(&e))?;
+    serde_json::to_value(&report).map_err(|e| RpcError::from_error(&e.into()))
+}
+
+/// Findings for the staged changes, most severe first.
+async fn review_staged(params: ReviewParams, id: &Value, outbox: &Outbox) -> Result<Value, RpcError> {
+    let app_config =
+        AppConfig::load_or_create().status(ExitStatus::ConfigError).map_err(|e| RpcError::from_error(&e))?;
+    outbox.notify("$/progress", json!({ "id": id, "message": "Reviewing staged changes" }));
+    let findings = review::review_staged(&app_config, params.language.as_deref(), params.context_limit)
+        .await
+        .map_err(|e| RpcError::from_error(&e))?;
+    Ok(json!({ "findings": findings }))
+}
+
+/// The effective configuration and where it was read from. API keys live in environment
+/// variables and are never part of it; only whether they are set is reported.
+fn get_config() -> Result<Value, RpcError> {
+    let load = || -> Result<Value> {
+        let app_config = AppConfig::load_or_create().status(ExitStatus::ConfigError)?;
+        let env_set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+        Ok(json!({
+            "path": AppConfig::config_path()?,
+            "config": app_config,
+            "env": {
+                "endpoint": env_set(&app_config.env.endpoint_env),
+                "api_key": env_set(&app_config.env.api_key_env),
+                "model": std::env::var(&app_config.env.model_env).ok(),
+            },
+        }))
+    };
+    load().map_err(|e| RpcError::from_error(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    /// Run a connection over in-memory pipes, send `requests` and collect every message
+    /// the server writes until it closes.
+    async fn exchange(requests: &[&str]) -> Vec<Value> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server);
+        let server = tokio::spawn(serve_connection(server_read, server_write));
+
+        let (client_read, mut client_write) = tokio::io::split(client);
+        for request in requests {
+            client_write.write_all(format!("{request}\n").as_bytes()).await.unwrap();
+        }
+        client_write.shutdown().await.unwrap();
+
+        let mut messages = Vec::new();
+        let mut lines = BufReader::new(client_read).lines();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            messages.push(serde_json::from_str(&line).unwrap());
+        }
+        server.await.unwrap().unwrap();
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors() {
+        let messages = exchange(&[
+            "not json",
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"generateMessage","params":{"colour":"red"}}"#,
+            r#"{"jsonrpc":"2.0","method":"initialized"}"#,
+            r#"{"jsonrpc":"2.0","id":"bye","method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"nope"}"#,
+        ])
+        .await;
+
+        let error = |i: usize| messages[i]["error"]["code"].as_i64().unwrap();
+        assert_eq!(messages.len(), 5, "{messages:?}");
+        assert_eq!((messages[0]["id"].clone(), error(0)), (Value::Null, PARSE_ERROR));
+        assert_eq!(error(1), INVALID_REQUEST);
+        let by_id = |id: Value| messages.iter().find(|m| m["id"] == id).unwrap();
+        assert_eq!(by_id(json!(2))["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(by_id(json!(3))["error"]["code"], INVALID_PARAMS);
+        // Nothing after shutdown is read.
+        assert!(by_id(json!("bye"))["result"].is_null());
+        assert!(messages.iter().all(|m| m["id"] != json!(4)));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_request() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server);
+        let server = tokio::spawn(serve_connection(server_read, server_write));
+        let (client_read, mut client_write) = tokio::io::split(client);
+        let mut lines = BufReader::new(client_read).lines();
+
+        // A request that never finishes on its own stands in for a slow AI call.
+        let hang = r#"{"jsonrpc":"2.0","id":7,"method":"$/test/hang"}"#;
+        client_write.write_all(format!("{hang}\n").as_bytes()).await.unwrap();
+        let cancel = r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":7}}"#;
+        client_write.write_all(format!("{cancel}\n").as_bytes()).await.unwrap();
+
+        let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], REQUEST_CANCELLED);
+
+        client_write.shutdown().await.unwrap();
+        assert!(lines.next_line().await.unwrap().is_none());
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_error_codes_follow_exit_status() {
+        let error = RpcError::from_error(&ExitStatus::NoChanges.error("No changes found to commit"));
+        assert_eq!(error.code, -32003);
+        assert_eq!(error.to_json()["data"]["status"], "no_changes");
+        assert_eq!(RpcError::from_error(&ExitStatus::Usage.error("bad")).code, INVALID_PARAMS);
+        assert_eq!(RpcError::from_error(&anyhow::anyhow!("other")).code, -32001);
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::{Result, bail};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language, parse_json_response};
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::hunks::{self, FilePatch};
+use crate::git::{CommitOptions, commit_tree, edit_file, get_truncated_diff, open_repo};
+
+/// A proposed sequence of commits, as returned by the model and as edited by the user.
+#[derive(Debug, Serialize, Deserialize)]
+struct SplitPlan {
+    commits: Vec<PlannedCommit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlannedCommit {
+    message: String,
+    /// Unit IDs: `path#N` for a hunk, `path` for a whole file.
+    units: Vec<String>,
+}
+
+/// A plan resolved against the staged patches: `(file, unit)` pairs per commit.
+type Resolved = Vec<HashSet<(usize, usize)>>;
+
+const OUTPUT_FORMAT: &str = "OUTPUT FORMAT (follow exactly):\n\
+    Respond with JSON only, no code fences and no explanations:\n\
+    {\"commits\": [{\"message\": \"type(scope): description\", \"units\": [\"src/a.rs#1\", \"docs/guide.md\"]}]}\n\
+    Use the IDs exactly as labelled. Every ID must appear in exactly one commit.";
+
+pub async fn handle_split(language: Option<&str>, dry_run: bool, context_limit: Option<usize>) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let repo = open_repo()?;
+
+    if repo.state() != git2::RepositoryState::Clean {
+        bail!("Cannot split while a merge, rebase or cherry-pick is in progress ({:?})", repo.state());
+    }
+
+    let (base_tree, patches) = hunks::staged_patches(&repo, Some(&app_config.commit))?;
+    if patches.is_empty() {
+        println!("{}", "No staged changes found to split.".red());
+        return Ok(());
+    }
+
+    // Split messages are single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let (system_prompt, _) = app_config.prompts.for_language(&language);
+    let split_template = app_config.prompts.split_prompt_template.clone();
+    let ai_client = AiClient::new(api_config, system_prompt, split_template)?
+        .with_ledger(UsageLedger::open(&app_config.usage, "split"))
+        .with_output_format(OUTPUT_FORMAT.to_string());
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let listing = get_truncated_diff(&hunks::render_for_prompt(&patches), limit);
+
+    let unit_total: usize = patches.iter().map(FilePatch::unit_count).sum();
+    println!("{}", format!("Found {unit_total} staged hunks in {} files.", patches.len()).green());
+    println!("{}", "Asking AI service to group them into commits...".cyan());
+
+    let raw = match ai_client.generate_commit_message(&listing).await {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to generate split plan:".red());
+            return Err(e);
+        }
+    };
+    let mut plan: SplitPlan = parse_json_response(&raw)?;
+
+    let resolved = loop {
+        let resolved = match resolve_plan(&plan, &patches) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                println!("{} {e}", "Invalid plan:".red());
+                if dry_run || !confirm_edit()? {
+                    return Err(e);
+                }
+                plan = edit_plan(&repo, &plan)?;
+                continue;
+            }
+        };
+        print_plan(&plan, &resolved, &patches);
+
+        if dry_run {
+            println!("{}", "(Dry run mode - no commits created)".yellow());
+            return Ok(());
+        }
+        match prompt_action()? {
+            Action::Accept => break resolved,
+            Action::Edit => plan = edit_plan(&repo, &plan)?,
+            Action::Cancel => {
+                println!("{}", "Split cancelled.".red());
+                return Ok(());
+            }
+        }
+    };
+
+    // Each commit gets the configured trailers, signing and hooks, as with `ai-commit commit`
+    let options = CommitOptions::new(&app_config.commit).trailers_from_config(&app_config.trailers)?;
+    create_commits(&repo, &base_tree, &patches, &plan, &resolved, &options)
+}
+
+fn resolve_plan(plan: &SplitPlan, patches: &[FilePatch]) -> Result<Resolved> {
+    let by_id = hunks::unit_lookup(patches);
+
+    let mut seen: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut resolved = Vec::new();
+    for (i, commit) in plan.commits.iter().enumerate() {
+        if commit.message.trim().is_empty() {
+            bail!("commit {} has an empty message", i + 1);
+        }
+        let mut units = HashSet::new();
+        for id in &commit.units {
+            let Some(found) = by_id.get(id.trim()) else {
+                bail!("commit {} refers to unknown hunk '{id}'", i + 1);
+            };
+            for &unit in found {
+                if let Some(prev) = seen.insert(unit, i)
+                    && prev != i
+                {
+                    bail!(
+                        "'{}' is assigned to both commit {} and commit {}",
+                        patches[unit.0].unit_id(unit.1),
+                        prev + 1,
+                        i + 1
+                    );
+                }
+                units.insert(unit);
+            }
+        }
+        if units.is_empty() {
+            bail!("commit {} has no hunks", i + 1);
+        }
+        resolved.push(units);
+    }
+    if resolved.is_empty() {
+        bail!("the plan contains no commits");
+    }
+    Ok(resolved)
+}
+
+fn print_plan(plan: &SplitPlan, resolved: &Resolved, patches: &[FilePatch]) {
+    println!("{}", "Proposed commits:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    for (i, (commit, units)) in plan.commits.iter().zip(resolved).enumerate() {
+        println!("{} {}", format!("{}.", i + 1).bright_cyan(), commit.message.trim().bright_green().bold());
+        let mut units: Vec<_> = units.iter().copied().collect();
+        units.sort();
+        for (file, unit) in units {
+            let patch = &patches[file];
+            let stat = match patch.hunks.get(unit).filter(|_| !patch.is_whole_file()) {
+                Some(h) => format!("+{} -{}", h.added(), h.removed()),
+                None => patch.status_label().to_string(),
+            };
+            println!("     {} {}", patch.unit_id(unit), format!("({stat})").dimmed());
+        }
+    }
+
+    let assigned: HashSet<_> = resolved.iter().flatten().collect();
+    let leftover: Vec<String> = patches
+        .iter()
+        .enumerate()
+        .flat_map(|(f, p)| (0..p.unit_count()).map(move |u| (f, u)))
+        .filter(|unit| !assigned.contains(unit))
+        .map(|(f, u)| patches[f].unit_id(u))
+        .collect();
+    if !leftover.is_empty() {
+        println!("{}", format!("Not in any commit (will stay staged): {}", leftover.join(", ")).yellow());
+    }
+    println!("{}", "─────────────────────".bright_blue());
+}
+
+enum Action {
+    Accept,
+    Edit,
+    Cancel,
+}
+
+fn prompt_action() -> Result<Action> {
+    print!("Create these commits? [y]es / [e]dit plan / [N]o: ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => Action::Accept,
+        "e" | "edit" => Action::Edit,
+        _ => Action::Cancel,
+    })
+}
+
+fn confirm_edit() -> Result<bool> {
+    print!("Edit the plan to fix it? (y/N): ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Round-trip the plan through the user's editor as TOML.
+fn edit_plan(repo: &git2::Repository, plan: &SplitPlan) -> Result<SplitPlan> {
+    let path = repo.path().join("AI_COMMIT_SPLIT_PLAN.toml");
+    let header = "# Edit the proposed commits, then save and close the editor.\n\
+                  # Commits are created in order. Units are hunk IDs (path#N) or whole files (path).\n\
+                  # Units left out of every commit stay staged.\n\n";
+    fs::write(&path, format!("{header}{}", toml::to_string_pretty(plan)?))?;
+    edit_file(&path)?;
+    let content = fs::read_to_string(&path)?;
+    let _ = fs::remove_file(&path);
+    toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse edited plan: {e}"))
+}
+
+/// Create one commit per planned group. Each commit's tree is HEAD's tree plus the hunks of
+/// that group and all earlier groups, so the index and working tree are never modified.
+fn create_commits(
+    repo: &git2::Repository,
+    base_tree: &git2::Tree,
+    patches: &[FilePatch],
+    plan: &SplitPlan,
+    resolved: &Resolved,
+    options: &CommitOptions,
+) -> Result<()> {
+    let mut applied: HashSet<(usize, usize)> = HashSet::new();
+
+    for (i, (commit, units)) in plan.commits.iter().zip(resolved).enumerate() {
+        applied.extend(units);
+        let tree_id = hunks::build_tree(repo, base_tree, patches, |f, u| applied.contains(&(f, u)))?;
+        let message = format!("{}\n", commit.message.trim());
+        let oid = commit_tree(repo, tree_id, &message, options).map_err(|e| {
+            let left = plan.commits.len() - i;
+            e.context(format!("{i} of {} commits created; the other {left} stay staged", plan.commits.len()))
+        })?;
+        let short = oid.to_string()[..7].to_string();
+        println!("{} {}", short.bright_yellow(), message.lines().next().unwrap_or_default());
+    }
+
+    println!("{}", format!("Created {} commits.", plan.commits.len()).green());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::hunks::Hunk;
+
+    fn plan(commits: &[(&str, &[&str])]) -> SplitPlan {
+        SplitPlan {
+            commits: commits
+                .iter()
+                .map(|(m, u)| PlannedCommit {
+                    message: m.to_string(),
+                    units: u.iter().map(|s| s.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_plan_hunks_and_files() {
+        let hunk = Hunk::from_lines(1, 1, &[('+', "x\n")]);
+        let patches = vec![FilePatch::modified("a.rs", vec![hunk.clone(); 2]), FilePatch::modified("b.rs", vec![hunk])];
+        let resolved =
+            resolve_plan(&plan(&[("fix: a", &["a.rs#2"]), ("feat: b", &["b.rs", "a.rs#1"])]), &patches).unwrap();
+        assert_eq!(resolved[0], HashSet::from([(0, 1)]));
+        assert_eq!(resolved[1], HashSet::from([(1, 0), (0, 0)]));
+    }
+
+    #[test]
+    fn test_resolve_plan_rejects_duplicates_and_unknown() {
+        let hunk = Hunk::from_lines(1, 1, &[('+', "x\n")]);
+        let patches = vec![FilePatch::modified("a.rs", vec![hunk; 2])];
+        assert!(resolve_plan(&plan(&[("fix: a", &["a.rs"]), ("fix: b", &["a.rs#1"])]), &patches).is_err());
+        assert!(resolve_plan(&plan(&[("fix: a", &["c.rs"])]), &patches).is_err());
+        assert!(resolve_plan(&plan(&[]), &patches).is_err());
+    }
+}
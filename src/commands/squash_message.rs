@@ -0,0 +1,164 @@
+use anyhow::{Result, bail};
+use colored::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, body, language};
+use crate::commands::pr::format_commit_list;
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::{get_branch_changes, get_staged_diff, get_truncated_diff, open_repo};
+
+/// Generate one message for squashing a series of commits.
+///
+/// With `base`, the commits and diff of the current branch since its merge base with `base`
+/// are used. Without it, the state left by `git merge --squash` is used: the staged diff and
+/// the commit list git wrote to `.git/SQUASH_MSG`. With `output`, the message is written to
+/// that file, keeping git's `#` comment lines (the prepare-commit-msg hook passes its file).
+pub async fn handle_squash_message(
+    base: Option<&str>,
+    language: Option<&str>,
+    output: Option<&Path>,
+    context_limit: Option<usize>,
+) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+
+    let (commits, diff) = match base {
+        Some(base) => {
+            let changes = get_branch_changes(Some(base), Some(&app_config.commit))?;
+            if changes.commits.is_empty() {
+                println!("{}", format!("No commits since '{}'.", changes.base_name).red());
+                return Ok(());
+            }
+            println!(
+                "{}",
+                format!("Squashing {} commits since '{}'.", changes.commits.len(), changes.base_name).green()
+            );
+            (format_commit_list(&changes.commits), changes.diff)
+        }
+        None => {
+            let repo = open_repo()?;
+            let Ok(squash_msg) = fs::read_to_string(repo.path().join("SQUASH_MSG")) else {
+                bail!("No base given and no squash in progress; pass a base branch or run after `git merge --squash`");
+            };
+            let diff = get_staged_diff(Some(&app_config.commit))?;
+            if diff.is_empty() {
+                println!("{}", "No staged changes found for the squash.".red());
+                return Ok(());
+            }
+            println!("{}", "Squash in progress; using SQUASH_MSG and the staged changes.".green());
+            (parse_squash_msg(&squash_msg), diff)
+        }
+    };
+
+    // Squash messages are single-language; a bilingual setting uses its first language.
+    let language = language.unwrap_or(app_config.commit.language.as_str());
+    let language = language::primary_language(language);
+    let (system_prompt, _) = app_config.prompts.for_language(&language);
+    let mut ai_client = AiClient::new(api_config, system_prompt, app_config.prompts.squash_prompt_template.clone())?
+        .with_ledger(UsageLedger::open(&app_config.usage, "squash-message"));
+    if app_config.body.enabled {
+        ai_client = ai_client.with_output_format(body::format_instructions(&app_config.body));
+    }
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let diff = get_truncated_diff(&diff, limit);
+
+    println!("{}", "Generating squash commit message using AI service...".cyan());
+    let message = match ai_client.generate_with_vars(&[("commits", &commits), ("diff", &diff)]).await {
+        Ok(message) => message.trim().to_string(),
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to generate squash message:".red());
+            return Err(e);
+        }
+    };
+    let message = if app_config.body.enabled {
+        let (message, warnings) = body::enforce(&message, &app_config.body);
+        for warning in warnings {
+            println!("{} {warning}", "Warning:".yellow());
+        }
+        message
+    } else {
+        message
+    };
+
+    println!("{}", "Generated squash message:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    println!("{}", message.bright_green().bold());
+    println!("{}", "─────────────────────".bright_blue());
+
+    if let Some(path) = output {
+        let existing = fs::read_to_string(path).unwrap_or_default();
+        fs::write(path, with_git_comments(&message, &existing))?;
+        println!("{}", format!("Message written to {}", path.display()).green());
+    }
+
+    Ok(())
+}
+
+/// Turn git's `SQUASH_MSG` ("commit <sha>", headers, indented message) into the same
+/// one-line-per-commit list used for branches. Git lists the newest commit first.
+fn parse_squash_msg(squash_msg: &str) -> String {
+    let mut commits: Vec<(String, Vec<String>)> = Vec::new();
+    for line in squash_msg.lines() {
+        if let Some(sha) = line.strip_prefix("commit ") {
+            commits.push((sha.trim().chars().take(7).collect(), Vec::new()));
+        } else if let Some(text) = line.strip_prefix("    ")
+            && let Some((_, lines)) = commits.last_mut()
+        {
+            lines.push(text.trim_end().to_string());
+        }
+    }
+
+    let mut out = String::new();
+    for (sha, lines) in commits.iter().rev() {
+        let mut lines = lines.iter().filter(|l| !l.trim().is_empty());
+        let _ = writeln!(out, "- {sha} {}", lines.next().map(String::as_str).unwrap_or_default());
+        for line in lines {
+            let _ = writeln!(out, "    {line}");
+        }
+    }
+    out
+}
+
+/// The new message followed by the `#` comment lines of git's message file.
+pub(crate) fn with_git_comments(message: &str, existing: &str) -> String {
+    let comments: Vec<&str> = existing.lines().filter(|l| l.starts_with('#')).collect();
+    if comments.is_empty() { format!("{message}\n") } else { format!("{message}\n\n{}\n", comments.join("\n")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_squash_msg() {
+        let squash_msg = "Squashed commit of the following:\n\n\
+            commit 2222222222222222222222222222222222222222\n\
+            Author: A <a@example.com>\n\
+            Date:   Mon Jan 1 00:00:00 2024 +0000\n\n\
+            \x20   fix typo\n\n\
+            commit 1111111111111111111111111111111111111111\n\
+            Author: A <a@example.com>\n\
+            Date:   Mon Jan 1 00:00:00 2024 +0000\n\n\
+            \x20   WIP retry logic\n\
+            \x20   \n\
+            \x20   handles 429 too\n";
+        assert_eq!(
+            parse_squash_msg(squash_msg),
+            "- 1111111 WIP retry logic\n    handles 429 too\n- 2222222 fix typo\n"
+        );
+    }
+
+    #[test]
+    fn test_with_git_comments() {
+        let existing = "Squashed commit of the following:\n\n# Please enter the commit message.\n# Lines starting with '#' will be ignored.\n";
+        assert_eq!(
+            with_git_comments("feat: add retry", existing),
+            "feat: add retry\n\n# Please enter the commit message.\n# Lines starting with '#' will be ignored.\n"
+        );
+        assert_eq!(with_git_comments("feat: add retry", ""), "feat: add retry\n");
+    }
+}
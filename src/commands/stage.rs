@@ -0,0 +1,167 @@
+use anyhow::{Result, bail};
+use colored::*;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, language, parse_json_response};
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::hunks::{self, FilePatch};
+use crate::git::{get_truncated_diff, open_repo};
+
+/// The model's classification of unstaged hunks into logical changes.
+#[derive(Debug, Deserialize)]
+struct Classification {
+    changes: Vec<LogicalChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogicalChange {
+    summary: String,
+    #[serde(default)]
+    matches: bool,
+    units: Vec<String>,
+}
+
+const OUTPUT_FORMAT: &str = "OUTPUT FORMAT (follow exactly):\n\
+    Respond with JSON only, no code fences and no explanations:\n\
+    {\"changes\": [{\"summary\": \"retry failed requests in client\", \"matches\": true, \"units\": [\"src/client.rs#1\"]}]}\n\
+    Use the IDs exactly as labelled.";
+
+pub async fn handle_stage(description: &str, dry_run: bool, context_limit: Option<usize>) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let repo = open_repo()?;
+
+    let patches = hunks::unstaged_patches(&repo, Some(&app_config.commit))?;
+    if patches.is_empty() {
+        println!("{}", "No unstaged changes found.".red());
+        return Ok(());
+    }
+
+    // Summaries follow the configured language; a bilingual setting uses its first language.
+    let language = language::primary_language(&app_config.commit.language);
+    let (system_prompt, _) = app_config.prompts.for_language(&language);
+    let ai_client = AiClient::new(api_config, system_prompt, app_config.prompts.stage_prompt_template.clone())?
+        .with_ledger(UsageLedger::open(&app_config.usage, "stage"))
+        .with_output_format(OUTPUT_FORMAT.to_string());
+
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let listing = get_truncated_diff(&hunks::render_for_prompt(&patches), limit);
+
+    let unit_total: usize = patches.iter().map(FilePatch::unit_count).sum();
+    println!("{}", format!("Found {unit_total} unstaged hunks in {} files.", patches.len()).green());
+    println!("{}", format!("Looking for hunks matching: {description}").cyan());
+
+    let raw = match ai_client.generate_with_vars(&[("description", description), ("diff", &listing)]).await {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("{} {e}", "Failed to classify hunks:".red());
+            return Err(e);
+        }
+    };
+    let classification: Classification = parse_json_response(&raw)?;
+    let selected = resolve_matches(&classification, &patches)?;
+
+    print_classification(&classification, &patches);
+
+    if selected.is_empty() {
+        println!("{}", "No hunks match the description; nothing staged.".yellow());
+        return Ok(());
+    }
+    if dry_run {
+        println!("{}", "(Dry run mode - nothing staged)".yellow());
+        return Ok(());
+    }
+    if !(app_config.commit.auto_confirm || confirm_stage(selected.len())?) {
+        println!("{}", "Staging cancelled.".red());
+        return Ok(());
+    }
+
+    let mut index = repo.index()?;
+    hunks::apply_to_index(&repo, &mut index, &patches, |f, u| selected.contains(&(f, u)))?;
+    index.write()?;
+
+    println!("{}", format!("Staged {} hunks.", selected.len()).green());
+    Ok(())
+}
+
+/// Units of every change marked as matching. Unknown IDs are an error so a confused
+/// model cannot silently stage the wrong thing.
+fn resolve_matches(classification: &Classification, patches: &[FilePatch]) -> Result<HashSet<(usize, usize)>> {
+    let by_id = hunks::unit_lookup(patches);
+    let mut selected = HashSet::new();
+    for change in &classification.changes {
+        for id in &change.units {
+            let Some(units) = by_id.get(id.trim()) else {
+                bail!("AI referred to unknown hunk '{id}'");
+            };
+            if change.matches {
+                selected.extend(units.iter().copied());
+            }
+        }
+    }
+    Ok(selected)
+}
+
+fn print_classification(classification: &Classification, patches: &[FilePatch]) {
+    let by_id = hunks::unit_lookup(patches);
+    println!("{}", "Unstaged changes:".bright_cyan().bold());
+    println!("{}", "─────────────────────".bright_blue());
+    for change in &classification.changes {
+        let marker = if change.matches { "[stage]".bright_green().bold() } else { "[skip] ".dimmed() };
+        println!("{marker} {}", change.summary.trim());
+        for id in &change.units {
+            let stat = by_id
+                .get(id.trim())
+                .and_then(|units| units.first())
+                .and_then(|&(f, u)| patches[f].hunks.get(u).filter(|_| !patches[f].is_whole_file()))
+                .map(|h| format!("(+{} -{})", h.added(), h.removed()))
+                .unwrap_or_default();
+            println!("        {id} {}", stat.dimmed());
+        }
+    }
+    println!("{}", "─────────────────────".bright_blue());
+}
+
+fn confirm_stage(count: usize) -> Result<bool> {
+    print!("Stage {count} matching hunks? (y/N): ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::hunks::Hunk;
+
+    #[test]
+    fn test_resolve_matches_only_matching_changes() {
+        let hunk = Hunk::from_lines(1, 1, &[('+', "x\n")]);
+        let patches =
+            vec![FilePatch::modified("client.rs", vec![hunk.clone(); 2]), FilePatch::modified("README.md", vec![hunk])];
+        let classification: Classification = serde_json::from_str(
+            r#"{"changes": [
+                {"summary": "retry fix", "matches": true, "units": ["client.rs#2"]},
+                {"summary": "docs", "matches": false, "units": ["client.rs#1", "README.md"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(resolve_matches(&classification, &patches).unwrap(), HashSet::from([(0, 1)]));
+    }
+
+    #[test]
+    fn test_resolve_matches_unknown_id() {
+        let hunk = Hunk::from_lines(1, 1, &[('+', "x\n")]);
+        let patches = vec![FilePatch::modified("client.rs", vec![hunk])];
+        let classification: Classification =
+            serde_json::from_str(r#"{"changes": [{"summary": "x", "matches": true, "units": ["other.rs#1"]}]}"#)
+                .unwrap();
+        assert!(resolve_matches(&classification, &patches).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use colored::*;
+use std::collections::BTreeMap;
+
+use crate::ai::ledger::{self, UsageLedger, UsageRecord};
+use crate::config::{AppConfig, UsageConfig};
+use crate::git::{current_workdir, open_repo};
+
+/// Tokens and cost summed over a group of usage records.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Totals {
+    runs: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+    /// `None` until a priced record is added.
+    cost: Option<f64>,
+}
+
+impl Totals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.runs += 1;
+        self.prompt_tokens += record.usage.prompt_tokens;
+        self.completion_tokens += record.usage.completion_tokens;
+        self.total_tokens += record.usage.total_tokens;
+        if let Some(cost) = record.cost {
+            *self.cost.get_or_insert(0.0) += cost;
+        }
+    }
+}
+
+/// Key of a row: UTC day (`YYYY-MM-DD`) or month (`YYYY-MM`), repository and model.
+type Row = (String, String, String);
+
+/// Totals per period, repository and model, newest period first.
+fn summarize(records: &[UsageRecord], monthly: bool) -> Vec<(Row, Totals)> {
+    let mut rows: BTreeMap<Row, Totals> = BTreeMap::new();
+    for record in records {
+        let mut period = ledger::day_of(record.timestamp);
+        if monthly {
+            period.truncate(7);
+        }
+        let repo = record.repo.clone().unwrap_or_else(|| "(no repository)".to_string());
+        rows.entry((period, repo, record.model.clone())).or_default().add(record);
+    }
+    let mut rows: Vec<_> = rows.into_iter().collect();
+    rows.sort_by(|(a, _), (b, _)| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)).then_with(|| a.2.cmp(&b.2)));
+    rows
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    cost.map_or_else(|| "-".to_string(), |cost| format!("{cost:.4}"))
+}
+
+/// Show tokens and cost per UTC day (or month), repository and model, and how much of
+/// each budget has been spent.
+pub fn handle_stats(monthly: bool, this_repo: bool) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let Some(path) = UsageLedger::log_path() else {
+        anyhow::bail!("Could not find the data directory for the usage log");
+    };
+    let all = ledger::read_records(&path)?;
+    let mut records = all.clone();
+    if this_repo {
+        open_repo()?;
+        let workdir = current_workdir();
+        records.retain(|r| r.repo == workdir);
+    }
+    if records.is_empty() {
+        println!("{}", "No token usage recorded yet.".yellow());
+        if !app_config.usage.enabled {
+            println!("{}", "Recording is off; set [usage] enabled = true to record it.".dimmed());
+        }
+        return Ok(());
+    }
+
+    let rows = summarize(&records, monthly);
+    let repo_width = rows.iter().map(|((_, repo, _), _)| repo.len()).max().unwrap_or(0).max("Repository".len());
+    let model_width = rows.iter().map(|((_, _, model), _)| model.len()).max().unwrap_or(0).max("Model".len());
+    let period_width = if monthly { 7 } else { 10 };
+
+    let title = if monthly { "Token usage by month (UTC)" } else { "Token usage by day (UTC)" };
+    println!("{}", title.bright_cyan().bold());
+    println!(
+        "{}",
+        format!(
+            "{:<period_width$}  {:<repo_width$}  {:<model_width$}  {:>5}  {:>10}  {:>10}  {:>10}  {:>10}",
+            if monthly { "Month" } else { "Date" },
+            "Repository",
+            "Model",
+            "Runs",
+            "Prompt",
+            "Completion",
+            "Total",
+            "Cost"
+        )
+        .bold()
+    );
+    for ((period, repo, model), totals) in &rows {
+        println!(
+            "{period:<period_width$}  {repo:<repo_width$}  {model:<model_width$}  {:>5}  {:>10}  {:>10}  {:>10}  {:>10}",
+            totals.runs,
+            totals.prompt_tokens,
+            totals.completion_tokens,
+            totals.total_tokens,
+            format_cost(totals.cost)
+        );
+    }
+    let mut grand = Totals::default();
+    records.iter().for_each(|r| grand.add(r));
+    println!(
+        "{}",
+        format!("Total: {} runs, {} tokens, cost {}", grand.runs, grand.total_tokens, format_cost(grand.cost)).green()
+    );
+    if records.iter().any(|r| r.cost.is_none()) {
+        println!("{}", "Models without a [usage.prices] entry are counted without a cost.".dimmed());
+    }
+
+    print_budgets(&app_config.usage, &all);
+    Ok(())
+}
+
+/// Spending against each configured budget, over all repositories.
+fn print_budgets(config: &UsageConfig, records: &[UsageRecord]) {
+    let today = ledger::today();
+    let budgets = [
+        ("Today", config.daily_budget, ledger::spent(records, &today)),
+        ("This month", config.monthly_budget, ledger::spent(records, &today[..7])),
+    ];
+    for (label, budget, spent) in budgets {
+        let Some(budget) = budget else { continue };
+        let line = format!("{label}: {spent:.4} of {budget} budget ({})", config.on_budget);
+        println!("{}", if spent >= budget { line.red() } else { line.normal() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Usage;
+
+    fn record(timestamp: u64, repo: &str, model: &str, tokens: u64, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            timestamp,
+            repo: Some(repo.to_string()),
+            command: "commit".to_string(),
+            model: model.to_string(),
+            usage: Usage { prompt_tokens: tokens, completion_tokens: 0, total_tokens: tokens },
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_summarize_by_day_and_month() {
+        let day = 86_400;
+        let oct_19 = 1_792_368_000;
+        let records = [
+            record(oct_19, "/a", "m1", 10, Some(0.5)),
+            record(oct_19 + 60, "/a", "m1", 5, None),
+            record(oct_19 + 60, "/b", "m1", 1, None),
+            record(oct_19 - 20 * day, "/a", "m1", 7, Some(0.25)),
+        ];
+
+        let daily = summarize(&records, false);
+        let keys: Vec<String> = daily.iter().map(|((period, repo, _), _)| format!("{period} {repo}")).collect();
+        assert_eq!(keys, ["2026-10-19 /a", "2026-10-19 /b", "2026-09-29 /a"]);
+        assert_eq!(daily[0].1.runs, 2);
+        assert_eq!(daily[0].1.total_tokens, 15);
+        assert_eq!(daily[0].1.cost, Some(0.5));
+        assert_eq!(daily[1].1.cost, None);
+
+        let monthly = summarize(&records, true);
+        assert_eq!(monthly[0].0.0, "2026-10");
+        assert_eq!(monthly[2].0.0, "2026-09");
+    }
+}
@@ -0,0 +1,559 @@
+//! `ai-commit tui`: the changed files, their diffs and an editable generated message on
+//! one screen. Toggling a file restages it; the message is regenerated on request.
+
+use anyhow::Result;
+use colored::*;
+use git2::{Repository, Status, StatusOptions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::Style;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tui_textarea::TextArea;
+
+use crate::ai::cache::CachePolicy;
+use crate::ai::language;
+use crate::ai::message_log::MessageLog;
+use crate::commands::commit::{CommitArgs, RunMode, generate_commit};
+use crate::commands::output::{CommitReport, ExitStatus, OutputFormat, WithStatus};
+use crate::config::{ApiConfig, AppConfig, CommitConfig};
+use crate::git::hunks::{self, FilePatch};
+use crate::git::{CommitOptions, execute_commit, open_repo};
+
+const KEY_HINTS: &str = " Tab focus · Space include · r regenerate · l language · m model · c commit · q quit ";
+
+/// How much of a file the next commit takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inclusion {
+    Staged,
+    /// Staged, with further unstaged changes on top.
+    Partial,
+    Unstaged,
+    Untracked,
+}
+
+impl Inclusion {
+    fn marker(self) -> &'static str {
+        match self {
+            Inclusion::Staged => "[x]",
+            Inclusion::Partial => "[~]",
+            Inclusion::Unstaged | Inclusion::Untracked => "[ ]",
+        }
+    }
+
+    fn is_included(self) -> bool {
+        matches!(self, Inclusion::Staged | Inclusion::Partial)
+    }
+}
+
+#[derive(Debug)]
+struct FileEntry {
+    path: String,
+    inclusion: Inclusion,
+    staged: Option<FilePatch>,
+    unstaged: Option<FilePatch>,
+}
+
+/// One entry per changed path, in path order.
+fn merge_entries(staged: Vec<FilePatch>, unstaged: Vec<FilePatch>, untracked: Vec<String>) -> Vec<FileEntry> {
+    fn entry<'m>(entries: &'m mut BTreeMap<String, FileEntry>, path: &str) -> &'m mut FileEntry {
+        entries.entry(path.to_string()).or_insert_with(|| FileEntry {
+            path: path.to_string(),
+            inclusion: Inclusion::Untracked,
+            staged: None,
+            unstaged: None,
+        })
+    }
+
+    let mut entries = BTreeMap::new();
+    for patch in staged {
+        let path = patch.path.clone();
+        entry(&mut entries, &path).staged = Some(patch);
+    }
+    for patch in unstaged {
+        let path = patch.path.clone();
+        entry(&mut entries, &path).unstaged = Some(patch);
+    }
+    for path in untracked {
+        entry(&mut entries, &path);
+    }
+
+    entries
+        .into_values()
+        .map(|mut file| {
+            file.inclusion = match (&file.staged, &file.unstaged) {
+                (Some(_), Some(_)) => Inclusion::Partial,
+                (Some(_), None) => Inclusion::Staged,
+                (None, Some(_)) => Inclusion::Unstaged,
+                (None, None) => Inclusion::Untracked,
+            };
+            file
+        })
+        .collect()
+}
+
+fn load_files(repo: &Repository, commit_config: &CommitConfig) -> Result<Vec<FileEntry>> {
+    let (_, staged) = hunks::staged_patches(repo, Some(commit_config))?;
+    let unstaged = hunks::unstaged_patches(repo, Some(commit_config))?;
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let untracked = repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter(|s| s.status().contains(Status::WT_NEW))
+        .filter_map(|s| s.path().ok().map(str::to_string))
+        .collect();
+
+    Ok(merge_entries(staged, unstaged, untracked))
+}
+
+/// The diff pane for one file: its staged changes, then what is left unstaged.
+fn diff_lines(file: &FileEntry) -> Vec<Line<'static>> {
+    use ratatui::style::Stylize;
+
+    let mut lines = Vec::new();
+    for (title, patch) in [("Staged", &file.staged), ("Not staged", &file.unstaged)] {
+        let Some(patch) = patch else { continue };
+        lines.push(Line::from(format!("{title} ({})", patch.status_label())).bold());
+        if patch.ignored {
+            lines.push(Line::from("(ignored when generating messages)").dim());
+        } else if patch.binary {
+            lines.push(Line::from("(binary file)").dim());
+        }
+        for hunk in &patch.hunks {
+            lines.push(Line::from(hunk.header.trim_end().to_string()).cyan());
+            for line in &hunk.lines {
+                let text = String::from_utf8_lossy(&line.content);
+                let text = format!("{}{}", line.origin, text.trim_end_matches(['\n', '\r']).replace('\t', "    "));
+                lines.push(match line.origin {
+                    '+' => Line::from(text).green(),
+                    '-' => Line::from(text).red(),
+                    _ => Line::from(text),
+                });
+            }
+        }
+        lines.push(Line::default());
+    }
+    if file.inclusion == Inclusion::Untracked {
+        lines.push(Line::from("Untracked file; press Space to include it.").dim());
+    }
+    lines
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Files,
+    Diff,
+    Message,
+}
+
+impl Focus {
+    fn next(self) -> Self {
+        match self {
+            Focus::Files => Focus::Diff,
+            Focus::Diff => Focus::Message,
+            Focus::Message => Focus::Files,
+        }
+    }
+
+    fn previous(self) -> Self {
+        self.next().next()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    Language,
+    Model,
+}
+
+/// A finished generation and its number; only the latest one is shown.
+type Generated = (u64, Result<CommitReport>);
+
+/// What the event loop should do after a key.
+enum Action {
+    Continue,
+    Quit,
+    Commit(String),
+}
+
+struct App<'a> {
+    repo: Repository,
+    app_config: AppConfig,
+    context_limit: Option<usize>,
+    language: String,
+    model: String,
+    files: Vec<FileEntry>,
+    list: ListState,
+    diff_scroll: u16,
+    focus: Focus,
+    editor: TextArea<'a>,
+    /// The language or model being typed at the bottom line.
+    prompt: Option<(Setting, String)>,
+    status: String,
+    /// The running generation, its number and its streamed text.
+    generation: Option<(u64, JoinHandle<()>, UnboundedReceiver<String>)>,
+    generations: u64,
+    results: (UnboundedSender<Generated>, UnboundedReceiver<Generated>),
+    /// History id and text of the last generated message.
+    generated: Option<(u64, String)>,
+}
+
+impl App<'_> {
+    fn reload(&mut self) -> Result<()> {
+        let selected = self.selected().map(|f| f.path.clone());
+        self.files = load_files(&self.repo, &self.app_config.commit)?;
+        let index = selected.and_then(|path| self.files.iter().position(|f| f.path == path));
+        self.list.select(if self.files.is_empty() { None } else { Some(index.unwrap_or(0)) });
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&FileEntry> {
+        self.list.selected().and_then(|i| self.files.get(i))
+    }
+
+    fn has_included(&self) -> bool {
+        self.files.iter().any(|f| f.inclusion.is_included())
+    }
+
+    fn set_message(&mut self, text: &str) {
+        self.editor = TextArea::new(text.lines().map(str::to_string).collect());
+    }
+
+    fn message(&self) -> String {
+        self.editor.lines().join("\n").trim().to_string()
+    }
+
+    /// Restage the selected file: include all of it, or drop it from the commit.
+    fn toggle_selected(&mut self) -> Result<()> {
+        let Some(file) = self.selected() else { return Ok(()) };
+        let path = file.path.clone();
+        if file.inclusion == Inclusion::Staged {
+            hunks::unstage_file(&self.repo, &path)?;
+        } else {
+            hunks::stage_file(&self.repo, &path)?;
+        }
+        self.reload()?;
+        if !self.message().is_empty() {
+            self.status = "Staged files changed; press r to regenerate the message.".to_string();
+        }
+        Ok(())
+    }
+
+    /// Start generating a message for what is staged, replacing any generation in progress.
+    /// `refresh` skips the cached message, for when the user asks for a new one.
+    fn generate(&mut self, refresh: bool) {
+        if let Some((_, task, _)) = self.generation.take() {
+            task.abort();
+        }
+        self.generations += 1;
+        let args = CommitArgs {
+            language: Some(self.language.clone()),
+            model: Some(self.model.clone()),
+            mode: RunMode { dry_run: Some(true), confirm: Some(false) },
+            context_limit: self.context_limit,
+            format: OutputFormat::Json,
+            cache: if refresh { CachePolicy::Refresh } else { CachePolicy::Use },
+            ..Default::default()
+        };
+        let (sink, deltas) = mpsc::unbounded_channel();
+        let (results, number) = (self.results.0.clone(), self.generations);
+        let task = tokio::spawn(async move {
+            let mut report = CommitReport::default();
+            let result = generate_commit(&args, &mut report, Some(sink)).await;
+            let _ = results.send((number, result.map(|_| report)));
+        });
+        self.generation = Some((number, task, deltas));
+        self.set_message("");
+        self.status = "Generating commit message...".to_string();
+    }
+
+    /// Apply streamed text and finished generations.
+    fn poll_generation(&mut self) {
+        if let Some((_, _, deltas)) = &mut self.generation {
+            let mut streamed = self.editor.lines().join("\n");
+            let mut changed = false;
+            while let Ok(text) = deltas.try_recv() {
+                streamed.push_str(&text);
+                changed = true;
+            }
+            if changed {
+                self.set_message(&streamed);
+            }
+        }
+        while let Ok((number, result)) = self.results.1.try_recv() {
+            if self.generation.as_ref().is_none_or(|(current, _, _)| *current != number) {
+                continue;
+            }
+            self.generation = None;
+            match result {
+                Ok(report) => {
+                    let message = report.message.unwrap_or_default();
+                    self.set_message(&message);
+                    self.generated = report.history_id.map(|id| (id, message));
+                    self.status = match report.warnings.first() {
+                        Some(warning) => format!("Warning: {warning}."),
+                        None => "Message generated; edit it or press c to commit.".to_string(),
+                    };
+                }
+                Err(e) => self.status = format!("Failed to generate commit message: {e:#}"),
+            }
+        }
+    }
+
+    fn apply_setting(&mut self, setting: Setting, value: &str) {
+        let value = value.trim();
+        match setting {
+            Setting::Language => match language::parse_language_code(value) {
+                Ok(code) => self.language = code,
+                Err(e) => {
+                    self.status = e;
+                    return;
+                }
+            },
+            Setting::Model if value.is_empty() => {
+                self.status = "The model name cannot be empty.".to_string();
+                return;
+            }
+            Setting::Model => self.model = value.to_string(),
+        }
+        self.generate(false);
+    }
+
+    fn commit(&mut self) -> Action {
+        let message = self.message();
+        if self.generation.is_some() {
+            self.status = "Wait for the message to finish generating.".to_string();
+        } else if !self.has_included() {
+            self.status = "Nothing is staged; press Space to include files.".to_string();
+        } else if message.is_empty() {
+            self.status = "The commit message is empty.".to_string();
+        } else {
+            return Action::Commit(message);
+        }
+        Action::Continue
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Result<Action> {
+        if let Some((setting, input)) = &mut self.prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    let (setting, input) = (*setting, input.clone());
+                    self.prompt = None;
+                    self.apply_setting(setting, &input);
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return Ok(Action::Continue);
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Ok(Action::Quit),
+            KeyCode::Char('s') if ctrl => return Ok(self.commit()),
+            KeyCode::Char('r') if ctrl => {
+                self.generate(true);
+                return Ok(Action::Continue);
+            }
+            KeyCode::Tab => {
+                self.focus = self.focus.next();
+                return Ok(Action::Continue);
+            }
+            KeyCode::BackTab => {
+                self.focus = self.focus.previous();
+                return Ok(Action::Continue);
+            }
+            _ => {}
+        }
+
+        if self.focus == Focus::Message {
+            if key.code == KeyCode::Esc {
+                self.focus = Focus::Files;
+            } else if self.generation.is_none() {
+                self.editor.input(key);
+            }
+            return Ok(Action::Continue);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Action::Quit),
+            KeyCode::Char('c') => return Ok(self.commit()),
+            KeyCode::Char('r') => self.generate(true),
+            KeyCode::Char('l') => self.prompt = Some((Setting::Language, self.language.clone())),
+            KeyCode::Char('m') => self.prompt = Some((Setting::Model, self.model.clone())),
+            KeyCode::Char(' ') | KeyCode::Enter if self.focus == Focus::Files => self.toggle_selected()?,
+            KeyCode::Down | KeyCode::Char('j') if self.focus == Focus::Files => {
+                self.list.select_next();
+                self.diff_scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.focus == Focus::Files => {
+                self.list.select_previous();
+                self.diff_scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.diff_scroll = self.diff_scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.diff_scroll = self.diff_scroll.saturating_sub(1),
+            KeyCode::PageDown => self.diff_scroll = self.diff_scroll.saturating_add(10),
+            KeyCode::PageUp => self.diff_scroll = self.diff_scroll.saturating_sub(10),
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        use ratatui::style::Stylize;
+
+        let [top, message, status] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Min(5), Constraint::Length(1)])
+                .areas(frame.area());
+        let [files, diff] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(top);
+        let block = |title: String, focus: Focus| {
+            let style = if self.focus == focus { Style::new().yellow() } else { Style::new() };
+            Block::bordered().title(title).border_style(style)
+        };
+
+        let items: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|f| {
+                let item = ListItem::new(format!("{} {}", f.inclusion.marker(), f.path));
+                match f.inclusion {
+                    Inclusion::Staged => item.green(),
+                    Inclusion::Partial => item.yellow(),
+                    Inclusion::Unstaged => item,
+                    Inclusion::Untracked => item.dim(),
+                }
+            })
+            .collect();
+        let list = List::new(items).block(block(" Files ".to_string(), Focus::Files)).highlight_symbol("> ");
+        frame.render_stateful_widget(list, files, &mut self.list);
+
+        let (title, lines) = match self.selected() {
+            Some(file) => (format!(" {} ", file.path), diff_lines(file)),
+            None => (" Diff ".to_string(), Vec::new()),
+        };
+        let diff_pane = Paragraph::new(lines).block(block(title, Focus::Diff)).scroll((self.diff_scroll, 0));
+        frame.render_widget(diff_pane, diff);
+
+        let generating = if self.generation.is_some() { " (generating...)" } else { "" };
+        let title = format!(" Message · {} · {}{generating} ", self.language, self.model);
+        self.editor.set_block(block(title, Focus::Message).title_bottom(KEY_HINTS));
+        frame.render_widget(&self.editor, message);
+
+        let line = match &self.prompt {
+            Some((Setting::Language, input)) => Line::from(format!("Language: {input}_")).cyan(),
+            Some((Setting::Model, input)) => Line::from(format!("Model: {input}_")).cyan(),
+            None => Line::from(self.status.as_str()),
+        };
+        frame.render_widget(line, status);
+    }
+
+    /// Draw and handle keys until the user quits or commits.
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<String>> {
+        loop {
+            self.poll_generation();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match self.on_key(key) {
+                    Ok(Action::Continue) => {}
+                    Ok(Action::Quit) => return Ok(None),
+                    Ok(Action::Commit(message)) => return Ok(Some(message)),
+                    Err(e) => self.status = format!("Error: {e:#}"),
+                }
+            }
+        }
+    }
+}
+
+pub async fn handle_tui(language: Option<&str>, context_limit: Option<usize>) -> Result<()> {
+    let app_config = AppConfig::load_or_create().status(ExitStatus::ConfigError)?;
+    let api_config = ApiConfig::from_env(&app_config.env).status(ExitStatus::ConfigError)?;
+    let repo = open_repo()?;
+
+    let files = load_files(&repo, &app_config.commit)?;
+    if files.is_empty() {
+        println!("{}", "No changes found to commit.".red());
+        return Err(ExitStatus::NoChanges.error("No changes found to commit"));
+    }
+
+    let mut app = App {
+        language: language.unwrap_or(app_config.commit.language.as_str()).to_string(),
+        model: api_config.model,
+        repo,
+        app_config,
+        context_limit,
+        files,
+        list: ListState::default().with_selected(Some(0)),
+        diff_scroll: 0,
+        focus: Focus::Files,
+        editor: TextArea::default(),
+        prompt: None,
+        status: "Nothing is staged; press Space to include files, then r to generate.".to_string(),
+        generation: None,
+        generations: 0,
+        results: mpsc::unbounded_channel(),
+        generated: None,
+    };
+    if app.has_included() {
+        app.generate(false);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    let Some(message) = result? else {
+        println!("{}", "No commit made.".yellow());
+        return Ok(());
+    };
+    let options = CommitOptions::new(&app.app_config.commit).trailers_from_config(&app.app_config.trailers)?;
+    execute_commit(&message, &options)?;
+    // Edited messages are the user's own; only an unchanged one counts as committed
+    if let Some((id, generated)) = &app.generated
+        && generated.trim() == message.trim()
+        && let Some(log) = MessageLog::open(&app.app_config.history)
+    {
+        log.mark_accepted(*id)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_entries_inclusion() {
+        let files = merge_entries(
+            vec![FilePatch::modified("b.rs", Vec::new()), FilePatch::modified("c.rs", Vec::new())],
+            vec![FilePatch::modified("a.rs", Vec::new()), FilePatch::modified("c.rs", Vec::new())],
+            vec!["new.txt".to_string()],
+        );
+        let summary: Vec<(&str, Inclusion)> = files.iter().map(|f| (f.path.as_str(), f.inclusion)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("a.rs", Inclusion::Unstaged),
+                ("b.rs", Inclusion::Staged),
+                ("c.rs", Inclusion::Partial),
+                ("new.txt", Inclusion::Untracked),
+            ]
+        );
+        assert!(files[2].inclusion.is_included() && !files[3].inclusion.is_included());
+    }
+}
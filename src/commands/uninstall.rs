@@ -23,6 +23,13 @@ pub fn uninstall_hook() -> Result<()> {
         println!("No post-commit hook found.");
     }
 
+    // Only remove a pre-commit hook that `install --review` put there.
+    let pre_commit = hooks_dir.join("pre-commit");
+    if fs::read_to_string(&pre_commit).is_ok_and(|s| s.contains("ai-commit review")) {
+        fs::remove_file(&pre_commit).with_context(|| format!("Failed to remove {}", pre_commit.display()))?;
+        println!("Removed pre-commit review hook.");
+    }
+
     println!("Uninstallation complete.");
     Ok(())
 }
@@ -0,0 +1,441 @@
+use anyhow::{Result, bail};
+use colored::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::ledger::UsageLedger;
+use crate::ai::{AiClient, parse_json_response};
+use crate::commands::pr::format_commit_list;
+use crate::config::{ApiConfig, AppConfig};
+use crate::git::conventional;
+use crate::git::{
+    CommitInfo, CommitOptions, create_tag_with_cli, execute_commit, get_commits_in_range, get_truncated_diff, open_repo,
+};
+
+/// Semantic version impact of a commit, ordered from least to most significant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Bump {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Bump::None => "none",
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl Version {
+    /// Parse `1.2.3` or `v1.2.3`; pre-release and build metadata are dropped.
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let core = s.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let version = Version { major: parts.next()??, minor: parts.next()??, patch: parts.next()?? };
+        parts.next().is_none().then_some(version)
+    }
+
+    /// Apply a bump. Before 1.0.0 a breaking change bumps the minor version, as Cargo treats
+    /// `0.x` minor releases as incompatible.
+    fn bump(self, bump: Bump) -> Self {
+        let Version { major, minor, patch } = self;
+        match bump {
+            Bump::Major if major == 0 => Version { major, minor: minor + 1, patch: 0 },
+            Bump::Major => Version { major: major + 1, minor: 0, patch: 0 },
+            Bump::Minor => Version { major, minor: minor + 1, patch: 0 },
+            Bump::Patch => Version { major, minor, patch: patch + 1 },
+            Bump::None => self,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Assessment {
+    commits: Vec<CommitBump>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitBump {
+    id: String,
+    bump: Bump,
+    #[serde(default)]
+    reason: String,
+}
+
+const OUTPUT_FORMAT: &str = "OUTPUT FORMAT (follow exactly):\n\
+    Respond with JSON only, no code fences and no explanations:\n\
+    {\"commits\": [{\"id\": \"abc1234\", \"bump\": \"minor\", \"reason\": \"adds --output option\"}]}\n\
+    Use the commit IDs exactly as listed. \"bump\" is one of \"major\", \"minor\", \"patch\", \"none\".";
+
+/// Manifests whose version field can be updated with `--write`.
+const MANIFESTS: &[&str] = &["Cargo.toml", "package.json"];
+
+pub struct VersionBumpOptions {
+    /// Update the version in Cargo.toml / package.json.
+    pub write: bool,
+    /// Create an annotated tag for the new version.
+    pub tag: bool,
+    /// Treat non-conventional commits as patches instead of asking the AI service.
+    pub no_ai: bool,
+    pub context_limit: Option<usize>,
+}
+
+pub async fn handle_version_bump(opts: VersionBumpOptions) -> Result<()> {
+    let app_config = AppConfig::load_or_create()?;
+    let repo = open_repo()?;
+    let workdir = repo.workdir().ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?.to_path_buf();
+
+    let range = get_commits_in_range(None)?;
+    let (prefix, current) = match &range.from_name {
+        Some(tag) => {
+            let Some(version) = Version::parse(&tag[tag_prefix(tag).len()..]) else {
+                bail!("Latest tag '{tag}' is not a semantic version");
+            };
+            (tag_prefix(tag).to_string(), version)
+        }
+        None => ("v".to_string(), manifest_version(&workdir).unwrap_or(Version { major: 0, minor: 0, patch: 0 })),
+    };
+    let since = range.from_name.as_deref().unwrap_or("the first commit");
+
+    if range.commits.is_empty() {
+        println!("{}", format!("No commits since {since}.").red());
+        return Ok(());
+    }
+    println!("{}", format!("Found {} commits since {since}.", range.commits.len()).green());
+
+    let mut bumps: Vec<(&CommitInfo, Bump, String)> = Vec::new();
+    let mut ambiguous: Vec<&CommitInfo> = Vec::new();
+    for commit in range.commits.iter().filter(|c| !c.summary.starts_with("Merge ")) {
+        match classify(commit) {
+            Some((bump, reason)) => bumps.push((commit, bump, reason)),
+            None => ambiguous.push(commit),
+        }
+    }
+
+    if !ambiguous.is_empty() {
+        let assessed = if opts.no_ai {
+            HashMap::new()
+        } else {
+            match assess_with_ai(&app_config, &ambiguous, opts.context_limit).await {
+                Ok(assessed) => assessed,
+                Err(e) => {
+                    println!("{} {e}", "Warning: could not assess non-conventional commits:".yellow());
+                    HashMap::new()
+                }
+            }
+        };
+        for commit in ambiguous {
+            let (bump, reason) = assessed
+                .get(&commit.short_id)
+                .cloned()
+                .unwrap_or((Bump::Patch, "non-conventional, assumed patch".to_string()));
+            bumps.push((commit, bump, reason));
+        }
+    }
+
+    print_bumps(&bumps);
+
+    let level = bumps.iter().map(|(_, bump, _)| *bump).max().unwrap_or(Bump::None);
+    if level == Bump::None {
+        println!("{}", format!("No release-worthy changes since {since}; version stays {current}.").yellow());
+        return Ok(());
+    }
+    let next = current.bump(level);
+    let tag_name = format!("{prefix}{next}");
+    println!(
+        "{} {current} → {} ({level})",
+        "Suggested version:".bright_cyan().bold(),
+        next.to_string().bright_green().bold()
+    );
+
+    let mut updated = Vec::new();
+    if opts.write {
+        if opts.tag && has_staged_changes(&repo)? {
+            bail!("The index has staged changes; commit or unstage them before using --write with --tag");
+        }
+        for name in MANIFESTS {
+            let path = workdir.join(name);
+            if update_manifest(&path, &next)? {
+                println!("{}", format!("Updated {name} to {next}").green());
+                updated.push(*name);
+            }
+        }
+        if updated.is_empty() {
+            println!("{}", "Warning: no Cargo.toml or package.json version field found to update.".yellow());
+        } else if updated.contains(&"Cargo.toml") {
+            println!("{}", "Note: run `cargo update --workspace` to refresh Cargo.lock.".dimmed());
+        }
+    }
+
+    if opts.tag {
+        if !updated.is_empty() {
+            let mut index = repo.index()?;
+            for name in &updated {
+                index.add_path(Path::new(name))?;
+            }
+            index.write()?;
+            let options = CommitOptions::new(&app_config.commit).trailers_from_config(&app_config.trailers)?;
+            execute_commit(&format!("chore(release): {tag_name}\n"), &options)?;
+        }
+        create_tag_with_cli(&tag_name, &format!("Release {tag_name}"))?;
+        println!("Push it with: {}", format!("git push origin {tag_name}").yellow());
+    }
+
+    Ok(())
+}
+
+/// Bump implied by a Conventional Commit; `None` for messages that need the model's judgement.
+fn classify(commit: &CommitInfo) -> Option<(Bump, String)> {
+    let parsed = conventional::parse(&commit.message)?;
+    Some(if parsed.breaking {
+        (Bump::Major, "breaking change".to_string())
+    } else {
+        match parsed.kind.as_str() {
+            "feat" => (Bump::Minor, "feat".to_string()),
+            "fix" | "perf" => (Bump::Patch, parsed.kind.clone()),
+            kind => (Bump::None, kind.to_string()),
+        }
+    })
+}
+
+async fn assess_with_ai(
+    app_config: &AppConfig,
+    commits: &[&CommitInfo],
+    context_limit: Option<usize>,
+) -> Result<HashMap<String, (Bump, String)>> {
+    let api_config = ApiConfig::from_env(&app_config.env)?;
+    let ai_client = AiClient::new(
+        api_config,
+        app_config.prompts.system_prompt.clone(),
+        app_config.prompts.version_bump_prompt_template.clone(),
+    )?
+    .with_ledger(UsageLedger::open(&app_config.usage, "version-bump"))
+    .with_output_format(OUTPUT_FORMAT.to_string());
+
+    let owned: Vec<CommitInfo> = commits.iter().map(|c| (*c).clone()).collect();
+    let limit = context_limit.unwrap_or(app_config.commit.context_limit);
+    let listing = get_truncated_diff(&format_commit_list(&owned), limit);
+
+    println!("{}", format!("Asking AI service about {} non-conventional commits...", commits.len()).cyan());
+    let raw = ai_client.generate_with_vars(&[("commits", &listing)]).await?;
+    let assessment: Assessment = parse_json_response(&raw)?;
+
+    Ok(assessment
+        .commits
+        .into_iter()
+        .map(|c| {
+            let reason =
+                if c.reason.trim().is_empty() { "AI assessment".to_string() } else { c.reason.trim().to_string() };
+            (c.id.trim().to_string(), (c.bump, reason))
+        })
+        .collect())
+}
+
+fn print_bumps(bumps: &[(&CommitInfo, Bump, String)]) {
+    println!("{}", "─────────────────────".bright_blue());
+    for (commit, bump, reason) in bumps {
+        let label = format!("{:<5}", bump.to_string());
+        let label = match bump {
+            Bump::Major => label.bright_red().bold(),
+            Bump::Minor => label.bright_green(),
+            Bump::Patch => label.bright_yellow(),
+            Bump::None => label.dimmed(),
+        };
+        println!("{label} {} {} {}", commit.short_id.bright_yellow(), commit.summary, format!("({reason})").dimmed());
+    }
+    println!("{}", "─────────────────────".bright_blue());
+}
+
+/// Everything before the first digit of a tag, e.g. `v` in `v1.2.3`.
+fn tag_prefix(tag: &str) -> &str {
+    &tag[..tag.find(|c: char| c.is_ascii_digit()).unwrap_or(0)]
+}
+
+fn has_staged_changes(repo: &git2::Repository) -> Result<bool> {
+    let head_tree = crate::git::hunks::head_tree(repo)?;
+    let diff = repo.diff_tree_to_index(Some(&head_tree), None, None)?;
+    Ok(diff.deltas().len() > 0)
+}
+
+/// The version declared in the first manifest that has one, used when there is no tag yet.
+fn manifest_version(workdir: &Path) -> Option<Version> {
+    let cargo = fs::read_to_string(workdir.join("Cargo.toml")).ok().and_then(|content| {
+        let manifest: toml::Table = toml::from_str(&content).ok()?;
+        let version = manifest.get("package")?.get("version")?.as_str()?;
+        Version::parse(version)
+    });
+    cargo.or_else(|| {
+        let content = fs::read_to_string(workdir.join("package.json")).ok()?;
+        let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+        Version::parse(manifest.get("version")?.as_str()?)
+    })
+}
+
+/// Rewrite the version field of a manifest in place. Returns `false` if the file does not
+/// exist or has no version field of its own (e.g. `version.workspace = true`).
+fn update_manifest(path: &Path, version: &Version) -> Result<bool> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let updated = match path.file_name().and_then(|n| n.to_str()) {
+        Some("package.json") => set_package_json_version(&content, version),
+        _ => set_cargo_version(&content, version),
+    };
+    match updated {
+        Some(updated) => {
+            fs::write(path, updated)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Replace `version = "…"` in `[package]` or `[workspace.package]`, keeping the formatting.
+fn set_cargo_version(content: &str, version: &Version) -> Option<String> {
+    let mut section = "";
+    let mut lines: Vec<String> = Vec::new();
+    let mut done = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            section = trimmed;
+        }
+        if !done
+            && matches!(section, "[package]" | "[workspace.package]")
+            && let Some(rest) = trimmed.strip_prefix("version")
+            && rest.trim_start().starts_with('=')
+            && let Some(replaced) = replace_quoted(line, version)
+        {
+            lines.push(replaced);
+            done = true;
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    done.then(|| join_like(&lines, content))
+}
+
+/// Replace the top-level `"version": "…"` field, keeping the formatting.
+fn set_package_json_version(content: &str, version: &Version) -> Option<String> {
+    let mut depth = 0usize;
+    let mut lines: Vec<String> = Vec::new();
+    let mut done = false;
+    for line in content.lines() {
+        if !done
+            && depth == 1
+            && let Some(rest) = line.trim_start().strip_prefix("\"version\"")
+            && rest.trim_start().starts_with(':')
+            && let Some(replaced) = replace_quoted(line, version)
+        {
+            lines.push(replaced);
+            done = true;
+            continue;
+        }
+        depth += line.matches(['{', '[']).count();
+        depth = depth.saturating_sub(line.matches(['}', ']']).count());
+        lines.push(line.to_string());
+    }
+    done.then(|| join_like(&lines, content))
+}
+
+/// Replace the last quoted string on `line` (the value, not the key) with `version`.
+fn replace_quoted(line: &str, version: &Version) -> Option<String> {
+    let end = line.rfind('"')?;
+    let start = line[..end].rfind('"')?;
+    Some(format!("{}\"{version}\"{}", &line[..start], &line[end + 1..]))
+}
+
+fn join_like(lines: &[String], original: &str) -> String {
+    let mut out = lines.join("\n");
+    if original.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    fn commit(message: &str) -> CommitInfo {
+        CommitInfo {
+            id: Oid::ZERO_SHA1,
+            short_id: "abc1234".to_string(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            message: message.to_string(),
+            author: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_version_parse_and_bump() {
+        let v = Version::parse("v1.4.2").unwrap();
+        assert_eq!(v.bump(Bump::Major).to_string(), "2.0.0");
+        assert_eq!(v.bump(Bump::Minor).to_string(), "1.5.0");
+        assert_eq!(v.bump(Bump::Patch).to_string(), "1.4.3");
+        assert_eq!(Version::parse("0.1.6").unwrap().bump(Bump::Major).to_string(), "0.2.0");
+        assert_eq!(Version::parse("2.0.0-rc.1").unwrap().to_string(), "2.0.0");
+        assert!(Version::parse("1.2").is_none());
+        assert!(Version::parse("nightly").is_none());
+        assert_eq!(tag_prefix("release-1.0.0"), "release-");
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&commit("feat(cli): add flag")).unwrap().0, Bump::Minor);
+        assert_eq!(classify(&commit("fix: crash")).unwrap().0, Bump::Patch);
+        assert_eq!(classify(&commit("docs: typo")).unwrap().0, Bump::None);
+        assert_eq!(classify(&commit("refactor!: drop option")).unwrap().0, Bump::Major);
+        assert_eq!(classify(&commit("chore: x\n\nBREAKING CHANGE: y")).unwrap().0, Bump::Major);
+        assert!(classify(&commit("Improve startup time")).is_none());
+    }
+
+    #[test]
+    fn test_set_cargo_version() {
+        let v = Version { major: 0, minor: 2, patch: 0 };
+        let content =
+            "[package]\nname = \"x\"\nversion = \"0.1.6\" # keep\n\n[dependencies]\nfoo = { version = \"1\" }\n";
+        assert_eq!(
+            set_cargo_version(content, &v).unwrap(),
+            "[package]\nname = \"x\"\nversion = \"0.2.0\" # keep\n\n[dependencies]\nfoo = { version = \"1\" }\n"
+        );
+        assert!(set_cargo_version("[package]\nversion.workspace = true\n", &v).is_none());
+        assert!(set_cargo_version("[workspace.package]\nversion = \"1.0.0\"\n", &v).unwrap().contains("\"0.2.0\""));
+    }
+
+    #[test]
+    fn test_set_package_json_version() {
+        let v = Version { major: 1, minor: 3, patch: 0 };
+        let content = "{\n  \"name\": \"x\",\n  \"dependencies\": {\n    \"version\": \"9.9.9\"\n  },\n  \"version\": \"1.2.0\"\n}\n";
+        let updated = set_package_json_version(content, &v).unwrap();
+        assert!(updated.contains("\"version\": \"9.9.9\""));
+        assert!(updated.contains("  \"version\": \"1.3.0\"\n}"));
+    }
+}
@@ -0,0 +1,88 @@
+//! Language codes as written in the config and on the command line: parsing, normalizing
+//! and the names used to fill `{language}` in prompts.
+
+/// Lower-case a language code and accept `_` as a subtag separator (`zh_CN` → `zh-cn`).
+pub fn normalize_language_code(language: &str) -> String {
+    language.trim().replace('_', "-").to_lowercase()
+}
+
+/// The first language of a bilingual code (`en+zh` → `en`), for output that is always single-language.
+pub fn primary_language(code: &str) -> String {
+    let code = normalize_language_code(code);
+    code.split('+').next().unwrap_or_default().to_string()
+}
+
+/// Human-readable name used to fill `{language}` in prompt templates.
+/// Unknown codes are passed through unchanged so custom values still work.
+pub fn display_name(code: &str) -> String {
+    let code = normalize_language_code(code);
+    let name = match code.as_str() {
+        "en" => "English",
+        "zh" | "zh-cn" | "zh-hans" => "Simplified Chinese",
+        "zh-tw" | "zh-hk" | "zh-hant" => "Traditional Chinese",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "de" => "German",
+        "es" => "Spanish",
+        "fr" => "French",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "pt-br" => "Brazilian Portuguese",
+        "nl" => "Dutch",
+        "pl" => "Polish",
+        "ru" => "Russian",
+        "uk" => "Ukrainian",
+        "tr" => "Turkish",
+        "vi" => "Vietnamese",
+        "ar" => "Arabic",
+        "he" => "Hebrew",
+        "hi" => "Hindi",
+        "th" => "Thai",
+        "el" => "Greek",
+        _ => return code,
+    };
+    name.to_string()
+}
+
+/// Accept BCP 47-style codes such as `ja`, `pt-BR` or `zh_TW`, or a bilingual pair such as `en+zh`.
+pub fn parse_language_code(value: &str) -> Result<String, String> {
+    let code = normalize_language_code(value);
+    let parts: Vec<&str> = code.split('+').collect();
+    let valid = parts.len() <= 2
+        && parts.iter().all(|p| is_valid_single_code(p))
+        && !(parts.len() == 2 && parts[0] == parts[1]);
+    if valid {
+        Ok(code)
+    } else {
+        Err(format!("invalid language code '{value}' (expected e.g. en, zh, ja, pt-BR or en+zh)"))
+    }
+}
+
+fn is_valid_single_code(code: &str) -> bool {
+    let mut parts = code.split('-');
+    let primary_ok =
+        parts.next().is_some_and(|p| (2..=3).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphabetic()));
+    primary_ok && parts.all(|p| (2..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_language_code() {
+        assert_eq!(parse_language_code("ja").unwrap(), "ja");
+        assert_eq!(parse_language_code("pt_BR").unwrap(), "pt-br");
+        assert!(parse_language_code("japanese!").is_err());
+        assert!(parse_language_code("").is_err());
+        assert_eq!(parse_language_code("EN+zh").unwrap(), "en+zh");
+        assert!(parse_language_code("en+en").is_err());
+        assert!(parse_language_code("en+zh+ja").is_err());
+    }
+
+    #[test]
+    fn test_display_name_unknown_passthrough() {
+        assert_eq!(display_name("de"), "German");
+        assert_eq!(display_name("eo"), "eo");
+    }
+}
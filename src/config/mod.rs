@@ -1,3 +1,7 @@
+pub mod language;
 pub mod prompt;
 mod settings;
-pub use settings::{ApiConfig, AppConfig, CommitConfig};
+pub use settings::{
+    ApiConfig, AppConfig, BilingualStyle, BodyConfig, BudgetAction, CacheConfig, CommitBackend, CommitConfig,
+    HistoryConfig, ModelPrice, PrConfig, ReviewConfig, RewordConfig, Severity, TrailersConfig, UsageConfig,
+};
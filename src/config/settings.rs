@@ -1,9 +1,12 @@
 use anyhow::Result;
 use log::debug;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
+use super::language;
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
@@ -11,6 +14,22 @@ pub struct AppConfig {
     #[serde(default)]
     pub hooks: HookConfig,
     #[serde(default)]
+    pub body: BodyConfig,
+    #[serde(default)]
+    pub pr: PrConfig,
+    #[serde(default)]
+    pub review: ReviewConfig,
+    #[serde(default)]
+    pub reword: RewordConfig,
+    #[serde(default)]
+    pub trailers: TrailersConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
     pub prompts: PromptConfig,
     #[serde(default)]
     pub env: EnvConfig,
@@ -86,6 +105,56 @@ pub struct CommitConfig {
     pub context_limit: usize,
     #[serde(default = "default_language")]
     pub language: String,
+    /// Layout for bilingual languages such as `en+zh`.
+    #[serde(default)]
+    pub bilingual_style: BilingualStyle,
+    /// How commits are created: in-process through libgit2, or by running `git commit`.
+    #[serde(default)]
+    pub backend: CommitBackend,
+    /// Run the repository's commit hooks when committing in-process.
+    #[serde(default = "default_true")]
+    pub run_hooks: bool,
+}
+
+/// How `ai-commit` creates commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitBackend {
+    /// Write the commit with libgit2, signing it and running hooks itself. Falls back to
+    /// `git commit` for situations it does not handle, such as an in-progress rebase.
+    #[default]
+    Git2,
+    /// Run `git commit`.
+    Cli,
+}
+
+impl std::fmt::Display for CommitBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CommitBackend::Git2 => "git2",
+            CommitBackend::Cli => "cli",
+        })
+    }
+}
+
+/// How the two parts of a bilingual (`a+b`) message are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BilingualStyle {
+    /// The full message in the first language, then the same message in the second.
+    #[default]
+    Stacked,
+    /// Subject line in the first language, body in the second.
+    SubjectBody,
+}
+
+impl std::fmt::Display for BilingualStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BilingualStyle::Stacked => "stacked",
+            BilingualStyle::SubjectBody => "subject-body",
+        })
+    }
 }
 
 fn default_false() -> bool {
@@ -112,6 +181,232 @@ impl Default for CommitConfig {
             custom_ignore_patterns: Vec::new(),
             context_limit: 800000,
             language: default_language(),
+            bilingual_style: BilingualStyle::default(),
+            backend: CommitBackend::default(),
+            run_hooks: true,
+        }
+    }
+}
+
+/// Settings for `--body` mode: a subject line plus a wrapped explanatory body.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BodyConfig {
+    /// Generate a body by default, as if `--body` was passed.
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    #[serde(default = "default_wrap_width")]
+    pub wrap_width: usize,
+    #[serde(default = "default_max_bullets")]
+    pub max_bullets: usize,
+    /// Keep footers such as `BREAKING CHANGE:` or `Refs:` when the model adds them.
+    #[serde(default = "default_true")]
+    pub footers: bool,
+}
+
+fn default_wrap_width() -> usize {
+    72
+}
+fn default_max_bullets() -> usize {
+    8
+}
+
+impl Default for BodyConfig {
+    fn default() -> Self {
+        Self { enabled: false, wrap_width: default_wrap_width(), max_bullets: default_max_bullets(), footers: true }
+    }
+}
+
+/// Settings for `ai-commit pr`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PrConfig {
+    /// Branch to compare against; when unset, `origin/HEAD`, `main` and `master` are tried.
+    pub base_branch: Option<String>,
+}
+
+/// Settings for `ai-commit review`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReviewConfig {
+    /// Lowest severity that blocks the commit when run with `--hook` (the pre-commit hook).
+    #[serde(default)]
+    pub fail_on: Severity,
+}
+
+/// Settings for `ai-commit reword`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewordConfig {
+    /// Commits reachable from any of these branches are already published and never rewritten.
+    #[serde(default = "default_protected_branches")]
+    pub protected_branches: Vec<String>,
+}
+
+fn default_protected_branches() -> Vec<String> {
+    vec!["origin/main".to_string(), "origin/master".to_string()]
+}
+
+impl Default for RewordConfig {
+    fn default() -> Self {
+        Self { protected_branches: default_protected_branches() }
+    }
+}
+
+/// Trailers added to the commits ai-commit creates.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrailersConfig {
+    /// Add `Signed-off-by` for the committer to every commit, as `git commit --signoff`.
+    #[serde(default = "default_false")]
+    pub signoff: bool,
+    /// Extra trailers for every commit, each written `Key: value`.
+    #[serde(default)]
+    pub custom: Vec<String>,
+    /// How many recent authors `--co-author` offers to choose from.
+    #[serde(default = "default_recent_authors")]
+    pub recent_authors: usize,
+}
+
+fn default_recent_authors() -> usize {
+    10
+}
+
+impl Default for TrailersConfig {
+    fn default() -> Self {
+        Self { signoff: false, custom: Vec::new(), recent_authors: default_recent_authors() }
+    }
+}
+
+/// The local cache of generated commit messages.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Reuse the message generated earlier for the same staged tree, prompts, model and options.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How long a cached message stays usable.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub ttl_hours: u64,
+    /// How many messages are kept; the oldest are removed first.
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24 * 7
+}
+
+fn default_cache_max_entries() -> usize {
+    200
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, ttl_hours: default_cache_ttl_hours(), max_entries: default_cache_max_entries() }
+    }
+}
+
+/// Token usage accounting: the usage log, prices and budgets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageConfig {
+    /// Record the tokens each invocation uses, for `ai-commit stats`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Price per million tokens by model name; unpriced models are counted without a cost.
+    #[serde(default)]
+    pub prices: BTreeMap<String, ModelPrice>,
+    /// Spending limits per UTC day and month, in the unit of `prices`.
+    pub daily_budget: Option<f64>,
+    pub monthly_budget: Option<f64>,
+    /// What happens once a budget is reached.
+    #[serde(default)]
+    pub on_budget: BudgetAction,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prices: BTreeMap::new(),
+            daily_budget: None,
+            monthly_budget: None,
+            on_budget: BudgetAction::Warn,
+        }
+    }
+}
+
+/// The history of generated messages, for `ai-commit history` and `commit --reuse`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    /// Keep every generated message, whether or not it was committed.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How many messages are kept over all repositories; the oldest are removed first.
+    #[serde(default = "default_history_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_history_max_entries() -> usize {
+    500
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, max_entries: default_history_max_entries() }
+    }
+}
+
+/// Price of a model per million prompt (`input`) and completion (`output`) tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// What to do when a usage budget is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Print a warning and send the request anyway.
+    #[default]
+    Warn,
+    /// Do not send the request.
+    Refuse,
+}
+
+impl std::fmt::Display for BudgetAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BudgetAction::Warn => "warn",
+            BudgetAction::Refuse => "refuse",
+        })
+    }
+}
+
+/// Severity of a review finding, ordered from least to most serious.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        })
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            other => Err(format!("unknown severity '{other}' (expected low, medium or high)")),
         }
     }
 }
@@ -126,14 +421,65 @@ pub struct HookConfig {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PromptConfig {
+    /// English prompts, used for `en` unless `[prompts.languages.en]` overrides them.
     #[serde(default = "default_system_prompt")]
     pub system_prompt: String,
-    #[serde(default = "default_system_prompt_zh")]
-    pub system_prompt_zh: String,
     #[serde(default = "default_user_prompt_template")]
     pub user_prompt_template: String,
-    #[serde(default = "default_user_prompt_template_zh")]
-    pub user_prompt_template_zh: String,
+    /// Fallback prompts for languages without an entry in `languages`; `{language}` is replaced by its name.
+    #[serde(default = "default_generic_system_prompt")]
+    pub generic_system_prompt: String,
+    #[serde(default = "default_generic_user_prompt_template")]
+    pub generic_user_prompt_template: String,
+    /// Prompts for bilingual languages (`en+zh`); `{primary}` and `{secondary}` are replaced by language names.
+    #[serde(default = "default_bilingual_system_prompt")]
+    pub bilingual_system_prompt: String,
+    #[serde(default = "default_bilingual_user_prompt_template")]
+    pub bilingual_user_prompt_template: String,
+    /// Template for `ai-commit split`; `{diff}` receives the staged hunks labelled with IDs.
+    #[serde(default = "default_split_prompt_template")]
+    pub split_prompt_template: String,
+    /// Template for `ai-commit stage`; `{diff}` receives the unstaged hunks, `{description}` the user's request.
+    #[serde(default = "default_stage_prompt_template")]
+    pub stage_prompt_template: String,
+    /// Prompts for `ai-commit pr`; the template receives `{branch}`, `{base}`, `{commits}` and `{diff}`.
+    #[serde(default = "default_pr_system_prompt")]
+    pub pr_system_prompt: String,
+    #[serde(default = "default_pr_user_prompt_template")]
+    pub pr_user_prompt_template: String,
+    /// Template for the body of merge commits; receives `{header}`, `{commits}`, `{conflicts}`,
+    /// `{resolution}` and `{diff}`.
+    #[serde(default = "default_merge_prompt_template")]
+    pub merge_prompt_template: String,
+    /// Template for `ai-commit squash-message`; receives `{commits}` and `{diff}`.
+    #[serde(default = "default_squash_prompt_template")]
+    pub squash_prompt_template: String,
+    /// Prompts for `ai-commit review`; the template receives `{diff}` with new-file line numbers.
+    #[serde(default = "default_review_system_prompt")]
+    pub review_system_prompt: String,
+    #[serde(default = "default_review_prompt_template")]
+    pub review_prompt_template: String,
+    /// Template for `ai-commit version-bump`; `{commits}` receives the non-conventional commits.
+    #[serde(default = "default_version_bump_prompt_template")]
+    pub version_bump_prompt_template: String,
+    /// Template for `ai-commit changelog --ai`; `{changes}` receives the grouped commit list.
+    #[serde(default = "default_changelog_prompt_template")]
+    pub changelog_prompt_template: String,
+    /// Per-language prompts keyed by language code, e.g. `[prompts.languages.ja]`.
+    #[serde(default = "default_languages")]
+    pub languages: BTreeMap<String, LanguagePrompts>,
+    /// Older configs stored the Chinese prompts as `system_prompt_zh` / `user_prompt_template_zh`.
+    /// They are read for compatibility and migrated into `languages.zh`.
+    #[serde(rename = "system_prompt_zh", default, skip_serializing)]
+    legacy_system_prompt_zh: Option<String>,
+    #[serde(rename = "user_prompt_template_zh", default, skip_serializing)]
+    legacy_user_prompt_template_zh: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguagePrompts {
+    pub system_prompt: String,
+    pub user_prompt_template: String,
 }
 
 fn default_system_prompt() -> String {
@@ -231,13 +577,340 @@ Git diff:
         .to_string()
 }
 
+fn default_generic_system_prompt() -> String {
+    r#"You are an expert software developer and git commit message writer.
+
+Generate concise, clear commit messages following the Conventional Commits specification:
+- feat: A new feature
+- fix: A bug fix
+- docs: Documentation only changes
+- style: Changes that do not affect the meaning of the code
+- refactor: A code change that neither fixes a bug nor adds a feature
+- perf: A code change that improves performance
+- test: Adding missing tests or correcting existing tests
+- chore: Changes to the build process or auxiliary tools
+
+Format: type(scope): description
+
+IMPORTANT: ALWAYS write the description in {language}, regardless of the language used in the diff content.
+The type and scope stay in English.
+
+PREFERRED FORMAT: Single line under 72 characters
+Use bullet points ONLY when there are truly MULTIPLE UNRELATED functional changes.
+
+Default to single line. Only use bullets for truly unrelated changes."#
+        .to_string()
+}
+
+fn default_generic_user_prompt_template() -> String {
+    r#"Analyze the following git diff and generate a commit message in {language}.
+
+Git diff:
+```diff
+{diff}
+```
+
+REQUIREMENTS (follow strictly):
+1. Write the commit message description in {language} ONLY. Keep the Conventional Commits type (feat, fix, ...) in English.
+2. Default to a single descriptive line under 72 characters.
+3. Only use bullet points if there are multiple COMPLETELY UNRELATED functional changes.
+
+Output ONLY the commit message, nothing else."#
+        .to_string()
+}
+
+fn default_bilingual_system_prompt() -> String {
+    r#"You are an expert software developer and git commit message writer who writes fluently in {primary} and {secondary}.
+
+Generate concise, clear commit messages following the Conventional Commits specification:
+- feat: A new feature
+- fix: A bug fix
+- docs: Documentation only changes
+- style: Changes that do not affect the meaning of the code
+- refactor: A code change that neither fixes a bug nor adds a feature
+- perf: A code change that improves performance
+- test: Adding missing tests or correcting existing tests
+- chore: Changes to the build process or auxiliary tools
+
+Format: type(scope): description
+
+The type and scope always stay in English. Both language versions must describe exactly the same changes."#
+        .to_string()
+}
+
+fn default_bilingual_user_prompt_template() -> String {
+    r#"Analyze the following git diff and generate a bilingual commit message in {primary} and {secondary}.
+
+Git diff:
+```diff
+{diff}
+```
+
+Keep each part concise: a subject line under 72 characters, and bullet points only for COMPLETELY UNRELATED changes."#
+        .to_string()
+}
+
+fn default_split_prompt_template() -> String {
+    r#"Group the following staged changes into coherent logical commits.
+
+Each hunk is labelled with an ID such as [src/main.rs#2]. Files without text hunks
+(binary files, mode changes, lock files) are labelled with their path only.
+
+Staged changes:
+{diff}
+
+REQUIREMENTS (follow strictly):
+1. Put hunks that belong to the same logical change (one feature, one fix, one refactor) in the same commit.
+2. Split only where the changes are genuinely independent; a single commit is fine if everything is related.
+3. Order the commits so that each one makes sense on top of the previous ones.
+4. Write each commit message following the conventions above, as a single line under 72 characters."#
+        .to_string()
+}
+
+fn default_stage_prompt_template() -> String {
+    r#"Classify the following unstaged changes by the logical change each hunk belongs to,
+then decide which of those changes match this description:
+
+"{description}"
+
+Each hunk is labelled with an ID such as [src/main.rs#2]. Files without text hunks
+(binary files, mode changes, lock files) are labelled with their path only.
+
+Unstaged changes:
+{diff}
+
+REQUIREMENTS (follow strictly):
+1. Every ID belongs to exactly one logical change.
+2. Mark a change as matching only if it clearly implements what the description refers to.
+3. Summarise each change in one short line."#
+        .to_string()
+}
+
+fn default_pr_system_prompt() -> String {
+    r#"You are an expert software developer writing pull request descriptions for code reviewers.
+
+Write in {language}. Be concrete and concise: describe what the branch changes and why,
+point reviewers at the parts that need attention, and never invent tests or results
+that are not visible in the commits or the diff."#
+        .to_string()
+}
+
+fn default_pr_user_prompt_template() -> String {
+    r#"Write a pull request title and description for merging branch `{branch}` into `{base}`.
+
+Commits on the branch:
+{commits}
+
+Combined diff:
+```diff
+{diff}
+```
+
+OUTPUT FORMAT (follow exactly):
+Line 1: the pull request title, under 72 characters, no Markdown.
+Line 2: blank.
+Then the description in Markdown with these sections:
+## Summary
+One or two sentences on what this PR does and why.
+## Changes
+Bullet points grouped by area.
+## Testing
+How the change was or should be tested, based on the tests visible in the diff.
+## Risk
+Possible regressions, migrations or compatibility concerns; say "Low" with a reason if there are none."#
+        .to_string()
+}
+
+fn default_merge_prompt_template() -> String {
+    r#"A merge is being committed with this subject line, which will be kept as is:
+{header}
+
+Commits being merged in (oldest first):
+{commits}
+
+Files that had conflicts: {conflicts}
+
+How the conflicts were resolved (the final content of those files compared with each side):
+{resolution}
+
+Everything the merge brings in, compared with the current branch:
+```diff
+{diff}
+```
+
+REQUIREMENTS (follow strictly):
+1. Write ONLY the message body; do not repeat the subject line.
+2. Start with one to three bullet points summarising what the merged commits bring in.
+3. If there were conflicts, add a line "Conflicts resolved:" followed by one bullet per file
+   saying how it was resolved (which side was kept, or how both sides were combined).
+4. Wrap lines at 72 characters."#
+        .to_string()
+}
+
+fn default_squash_prompt_template() -> String {
+    r#"The following commits are being squashed into a single commit. Write its message.
+
+Original commits (oldest first):
+{commits}
+
+Combined diff:
+```diff
+{diff}
+```
+
+REQUIREMENTS (follow strictly):
+1. Describe the net effect of the combined diff, not the history of how it was written.
+2. Use the original messages only to understand intent; ignore WIP, fixup, typo and review-feedback commits,
+   and changes that were reverted within the series.
+3. Prefer a single line under 72 characters; add a blank line and bullet points only for genuinely separate changes."#
+        .to_string()
+}
+
+fn default_review_system_prompt() -> String {
+    r#"You are a senior software engineer reviewing a change before it is committed.
+
+Report only real problems: bugs, security issues, data loss, race conditions, broken error
+handling, and clear maintainability hazards. Do not comment on style that a formatter or
+linter would catch, and do not praise the change. Write the findings in {language}."#
+        .to_string()
+}
+
+fn default_review_prompt_template() -> String {
+    r#"Review the following staged changes.
+
+Each line is prefixed with its line number in the new version of the file; removed lines
+have no number. Only the lines marked `+` are part of this change.
+
+Staged changes:
+{diff}
+
+REQUIREMENTS (follow strictly):
+1. Refer to files by the path in the `###` heading and to lines by the number shown.
+2. Prefer pointing at an added (`+`) line; use the nearest one when the problem is a removal.
+3. "high": will break behaviour, lose data or open a security hole.
+   "medium": likely bug or missing error handling in some cases.
+   "low": minor issue worth a follow-up.
+4. Return an empty list if there is nothing worth reporting."#
+        .to_string()
+}
+
+fn default_version_bump_prompt_template() -> String {
+    r#"Decide the semantic version impact of each of the following commits, which do not follow
+the Conventional Commits format. Each commit is listed with its short ID and full message.
+
+Commits:
+{commits}
+
+REQUIREMENTS (follow strictly):
+1. "major": removes or incompatibly changes existing behaviour, APIs, options or file formats.
+2. "minor": adds a new user-facing feature or option in a backwards compatible way.
+3. "patch": fixes a bug or changes behaviour users can observe without adding features.
+4. "none": internal only (refactoring, tests, documentation, CI, formatting, dependencies).
+5. When unsure between two levels, pick the lower one."#
+        .to_string()
+}
+
+fn default_changelog_prompt_template() -> String {
+    r#"Rewrite the following changelog entries, generated from commit messages, as user-facing release notes in {language}.
+
+Entries grouped by Keep a Changelog section:
+{changes}
+
+REQUIREMENTS (follow strictly):
+1. Keep the same `### Section` headings (Added, Changed, Deprecated, Removed, Fixed, Security); drop empty sections.
+2. Describe each change from the user's point of view; merge entries that describe the same change.
+3. Drop purely internal entries (refactors, CI, tests) unless they affect users.
+4. Keep **BREAKING:** markers and commit hashes in parentheses.
+5. Output ONLY the sections as Markdown, with no version heading and no other text."#
+        .to_string()
+}
+
+fn default_languages() -> BTreeMap<String, LanguagePrompts> {
+    BTreeMap::from([(
+        "zh".to_string(),
+        LanguagePrompts {
+            system_prompt: default_system_prompt_zh(),
+            user_prompt_template: default_user_prompt_template_zh(),
+        },
+    )])
+}
+
 impl Default for PromptConfig {
     fn default() -> Self {
         Self {
             system_prompt: default_system_prompt(),
-            system_prompt_zh: default_system_prompt_zh(),
             user_prompt_template: default_user_prompt_template(),
-            user_prompt_template_zh: default_user_prompt_template_zh(),
+            generic_system_prompt: default_generic_system_prompt(),
+            generic_user_prompt_template: default_generic_user_prompt_template(),
+            bilingual_system_prompt: default_bilingual_system_prompt(),
+            bilingual_user_prompt_template: default_bilingual_user_prompt_template(),
+            split_prompt_template: default_split_prompt_template(),
+            stage_prompt_template: default_stage_prompt_template(),
+            pr_system_prompt: default_pr_system_prompt(),
+            pr_user_prompt_template: default_pr_user_prompt_template(),
+            merge_prompt_template: default_merge_prompt_template(),
+            squash_prompt_template: default_squash_prompt_template(),
+            review_system_prompt: default_review_system_prompt(),
+            review_prompt_template: default_review_prompt_template(),
+            version_bump_prompt_template: default_version_bump_prompt_template(),
+            changelog_prompt_template: default_changelog_prompt_template(),
+            languages: default_languages(),
+            legacy_system_prompt_zh: None,
+            legacy_user_prompt_template_zh: None,
+        }
+    }
+}
+
+impl PromptConfig {
+    /// Resolve the `(system_prompt, user_prompt_template)` pair for a language code.
+    ///
+    /// Lookup order: exact code in `languages`, then its primary subtag (`pt-br` → `pt`),
+    /// then the built-in language prompts (so adding `[prompts.languages.ja]` keeps `zh`),
+    /// then the top-level English prompts for `en`, and finally the generic prompts.
+    /// `{language}` is substituted in every case; `{diff}` is left for the client.
+    pub fn for_language(&self, language: &str) -> (String, String) {
+        let code = language::normalize_language_code(language);
+        let primary = code.split('-').next().unwrap_or(&code);
+
+        let builtin = default_languages();
+        let found = [&self.languages, &builtin].into_iter().find_map(|m| m.get(&code).or_else(|| m.get(primary)));
+
+        let (system, user) = if let Some(p) = found {
+            (p.system_prompt.as_str(), p.user_prompt_template.as_str())
+        } else if primary == "en" {
+            (self.system_prompt.as_str(), self.user_prompt_template.as_str())
+        } else {
+            (self.generic_system_prompt.as_str(), self.generic_user_prompt_template.as_str())
+        };
+
+        let name = language::display_name(&code);
+        (system.replace("{language}", &name), user.replace("{language}", &name))
+    }
+
+    /// Resolve the bilingual prompt pair with `{primary}` / `{secondary}` filled in.
+    /// The section layout itself is appended by the client (see `ai::bilingual`).
+    pub fn for_bilingual(&self, primary: &str, secondary: &str) -> (String, String) {
+        let (primary, secondary) = (language::display_name(primary), language::display_name(secondary));
+        let fill = |s: &str| s.replace("{primary}", &primary).replace("{secondary}", &secondary);
+        (fill(&self.bilingual_system_prompt), fill(&self.bilingual_user_prompt_template))
+    }
+
+    /// Move legacy `*_zh` fields into `languages.zh`.
+    fn migrate_legacy(&mut self) {
+        let system = self.legacy_system_prompt_zh.take();
+        let user = self.legacy_user_prompt_template_zh.take();
+        if system.is_none() && user.is_none() {
+            return;
+        }
+        let entry = self.languages.entry("zh".to_string()).or_insert_with(|| LanguagePrompts {
+            system_prompt: default_system_prompt_zh(),
+            user_prompt_template: default_user_prompt_template_zh(),
+        });
+        if let Some(system) = system {
+            entry.system_prompt = system;
+        }
+        if let Some(user) = user {
+            entry.user_prompt_template = user;
         }
     }
 }
@@ -265,7 +938,14 @@ impl AppConfig {
             ));
         }
         let config_content = fs::read_to_string(&config_path)?;
-        let config: AppConfig = toml::from_str(&config_content)?;
+        Self::from_toml(&config_content).map_err(|e| anyhow::anyhow!("{}: {e}", config_path.display()))
+    }
+
+    fn from_toml(content: &str) -> Result<Self> {
+        let mut config: AppConfig = toml::from_str(content)?;
+        config.commit.language = language::parse_language_code(&config.commit.language)
+            .map_err(|e| anyhow::anyhow!("commit.language: {e}"))?;
+        config.prompts.migrate_legacy();
         Ok(config)
     }
 
@@ -310,6 +990,14 @@ mod tests {
         assert_eq!(config.commit.context_limit, 800000);
     }
 
+    #[test]
+    fn test_load_validates_language() {
+        let config = AppConfig::from_toml("[commit]\nlanguage = \"pt_BR\"\n").unwrap();
+        assert_eq!(config.commit.language, "pt-br");
+        let error = AppConfig::from_toml("[commit]\nlanguage = \"en+en\"\n").unwrap_err();
+        assert!(error.to_string().contains("commit.language"), "{error}");
+    }
+
     #[test]
     fn test_env_config_default() {
         let env = EnvConfig::default();
@@ -352,6 +1040,55 @@ endpoint_env = "MY_ENDPOINT"
         assert_eq!(config.env.api_key_env, "AI_COMMIT_API_KEY");
     }
 
+    #[test]
+    fn test_prompts_for_language_lookup() {
+        let toml_str = r#"
+[prompts.languages.ja]
+system_prompt = "ja system"
+user_prompt_template = "ja {diff}"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.prompts.for_language("ja").0, "ja system");
+        assert_eq!(config.prompts.for_language("en").0, config.prompts.system_prompt);
+        assert!(config.prompts.for_language("zh").0.contains("中文"));
+
+        let (system, user) = config.prompts.for_language("de");
+        assert!(system.contains("German"));
+        assert!(user.contains("German") && user.contains("{diff}"));
+    }
+
+    #[test]
+    fn test_body_config_defaults() {
+        let config: AppConfig = toml::from_str("[body]\nenabled = true").unwrap();
+        assert!(config.body.enabled);
+        assert_eq!(config.body.wrap_width, 72);
+        assert_eq!(config.body.max_bullets, 8);
+        assert!(config.body.footers);
+    }
+
+    #[test]
+    fn test_parse_bilingual_style() {
+        let config: AppConfig = toml::from_str("[commit]\nbilingual_style = \"subject-body\"").unwrap();
+        assert_eq!(config.commit.bilingual_style, BilingualStyle::SubjectBody);
+        assert_eq!(CommitConfig::default().bilingual_style, BilingualStyle::Stacked);
+
+        let (system, user) = config.prompts.for_bilingual("en", "zh");
+        assert!(system.contains("English") && system.contains("Simplified Chinese"));
+        assert!(user.contains("{diff}"));
+    }
+
+    #[test]
+    fn test_legacy_zh_prompts_migrated() {
+        let toml_str = r#"
+[prompts]
+system_prompt_zh = "旧的系统提示"
+"#;
+        let mut config: AppConfig = toml::from_str(toml_str).unwrap();
+        config.prompts.migrate_legacy();
+        assert_eq!(config.prompts.for_language("zh").0, "旧的系统提示");
+        assert!(!toml::to_string(&config).unwrap().contains("system_prompt_zh"));
+    }
+
     #[test]
     fn test_parse_empty_config_uses_defaults() {
         let toml_str = "";
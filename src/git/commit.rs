@@ -0,0 +1,753 @@
+//! Creating commits in-process with libgit2.
+//!
+//! This mirrors what `git commit --file -` does for the cases ai-commit needs: the index
+//! is written as a tree, author and committer come from the repository configuration (and
+//! `GIT_AUTHOR_*` / `GIT_COMMITTER_*`), the commit is signed when `commit.gpgsign` is set,
+//! and the commit hooks run around it. Anything else falls back to running `git commit`.
+
+use anyhow::{Result, bail};
+use git2::{Config, Index, Oid, Repository, RepositoryState};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::diff::open_repo;
+use super::repository::{execute_amend_with_cli, execute_commit_with_cli, gpg_tty, show_commit_info};
+use super::trailers::{self, Trailer};
+use crate::config::{CommitBackend, CommitConfig, TrailersConfig};
+
+/// What to commit and how.
+#[derive(Debug, Clone)]
+pub struct CommitOptions {
+    /// Replace HEAD instead of adding a commit on top of it.
+    pub amend: bool,
+    /// Stage modified and deleted tracked files first, as `git commit -a`.
+    pub all: bool,
+    /// Trailers appended to the message, e.g. `Co-authored-by: Name <email>`.
+    pub trailers: Vec<Trailer>,
+    /// Add a `Signed-off-by` trailer for the committer, as `git commit --signoff`.
+    pub signoff: bool,
+    /// Run `pre-commit`, `prepare-commit-msg`, `commit-msg` and `post-commit`.
+    pub run_hooks: bool,
+    /// Skip `pre-commit` and `commit-msg` for this commit, as `git commit --no-verify`.
+    pub no_verify: bool,
+    /// Force signing on or off; `None` follows `commit.gpgsign`.
+    pub sign: Option<bool>,
+    pub backend: CommitBackend,
+    /// Extra `git commit` arguments. These can only be honoured by `git commit` itself, so
+    /// any present send the commit through the CLI.
+    pub git_args: Vec<String>,
+}
+
+impl CommitOptions {
+    pub fn new(commit_config: &CommitConfig) -> Self {
+        Self {
+            amend: false,
+            all: false,
+            trailers: Vec::new(),
+            signoff: false,
+            run_hooks: commit_config.run_hooks,
+            no_verify: false,
+            sign: commit_config.gpg_sign,
+            backend: commit_config.backend,
+            git_args: Vec::new(),
+        }
+    }
+
+    /// Override signing for this commit (`-S` / `--no-gpg-sign`); `None` keeps the config.
+    pub fn sign(mut self, sign: Option<bool>) -> Self {
+        self.sign = sign.or(self.sign);
+        self
+    }
+
+    pub fn amend(mut self) -> Self {
+        self.amend = true;
+        self
+    }
+
+    /// Add the trailers from `[trailers]`.
+    pub fn trailers_from_config(mut self, config: &TrailersConfig) -> Result<Self> {
+        self.signoff |= config.signoff;
+        for trailer in &config.custom {
+            let trailer: Trailer =
+                trailer.parse().map_err(|e| anyhow::anyhow!("Invalid [trailers] custom entry: {e}"))?;
+            self.trailers.push(trailer);
+        }
+        Ok(self)
+    }
+}
+
+/// How a commit is signed, from `gpg.format`, `user.signingkey` and the program settings.
+#[derive(Debug, PartialEq, Eq)]
+enum Signer {
+    OpenPgp { program: String, key: String },
+    X509 { program: String, key: String },
+    Ssh { program: String, key: String },
+}
+
+const NO_SIGN_HINT: &str = "To commit without signing, pass --no-gpg-sign or set `gpg_sign = false` under [commit].";
+
+/// Commit the staged changes with `message`, in-process unless the backend is `cli` or
+/// the repository is in a state only `git commit` handles.
+pub fn execute_commit(message: &str, options: &CommitOptions) -> Result<Oid> {
+    let repo = open_repo()?;
+    let message = trailers::append(message, &all_trailers(&repo, options)?);
+
+    if options.backend == CommitBackend::Cli {
+        return commit_with_cli(&repo, &message, options);
+    }
+    if !options.git_args.is_empty() {
+        println!("Passing {} through to `git commit`.", options.git_args.join(" "));
+        return commit_with_cli(&repo, &message, options);
+    }
+    if let Some(reason) = needs_cli(&repo) {
+        println!("{reason}; committing with `git commit` instead.");
+        return commit_with_cli(&repo, &message, options);
+    }
+
+    println!("{}", if options.amend { "Amending last commit..." } else { "Committing changes..." });
+    let id = commit_in_process(&repo, &message, options)?;
+    println!("{}", if options.amend { "Commit amended successfully!" } else { "Commit successful!" });
+    show_commit_info()?;
+    Ok(id)
+}
+
+/// Commit `tree` on top of HEAD with `message`, as `git commit` does for part of the staged
+/// changes: the hooks see a temporary index holding `tree`, and trailers and signing follow
+/// `options`. The repository index is left as it is. Always in-process.
+pub fn commit_tree(repo: &Repository, tree: Oid, message: &str, options: &CommitOptions) -> Result<Oid> {
+    if options.amend || options.all || !options.git_args.is_empty() {
+        bail!("Committing a prepared tree cannot amend, stage or pass git arguments");
+    }
+    if let Some(reason) = needs_cli(repo) {
+        bail!("{reason}; cannot commit a prepared tree");
+    }
+    let message = trailers::append(message, &all_trailers(repo, options)?);
+
+    let index_path = repo.path().join(format!("ai-commit-index.{}", std::process::id()));
+    let mut index = Index::open(&index_path)?;
+    index.read_tree(&repo.find_tree(tree)?)?;
+    index.write()?;
+    let result = commit_index(repo, &message, options, Some(&index_path));
+    let _ = fs::remove_file(&index_path);
+    result
+}
+
+fn commit_with_cli(repo: &Repository, message: &str, options: &CommitOptions) -> Result<Oid> {
+    let mut args = Vec::new();
+    if options.all {
+        args.push("--all".to_string());
+    }
+    if options.no_verify {
+        args.push("--no-verify".to_string());
+    }
+    args.extend(options.git_args.iter().cloned());
+
+    if options.amend {
+        execute_amend_with_cli(message, options.sign, &args)?
+    } else {
+        execute_commit_with_cli(message, options.sign, &args)?
+    }
+    Ok(repo.head()?.peel_to_commit()?.id())
+}
+
+/// The configured trailers plus `Signed-off-by` for the committer when signing off.
+fn all_trailers(repo: &Repository, options: &CommitOptions) -> Result<Vec<Trailer>> {
+    let mut trailers = options.trailers.clone();
+    if options.signoff {
+        let committer = repo.committer_from_env()?;
+        let ident = format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default());
+        trailers.push(Trailer::new("Signed-off-by", ident));
+    }
+    Ok(trailers)
+}
+
+/// Why the in-process path cannot make this commit, if it cannot.
+fn needs_cli(repo: &Repository) -> Option<String> {
+    match repo.state() {
+        RepositoryState::Clean | RepositoryState::Merge => None,
+        state => Some(format!("Repository is in the middle of an operation ({state:?})")),
+    }
+}
+
+pub(crate) fn commit_in_process(repo: &Repository, message: &str, options: &CommitOptions) -> Result<Oid> {
+    commit_index(repo, message, options, None)
+}
+
+/// Commit the repository index, or the index file at `index_path` when given; the hooks
+/// run with `GIT_INDEX_FILE` set to it.
+fn commit_index(repo: &Repository, message: &str, options: &CommitOptions, index_path: Option<&Path>) -> Result<Oid> {
+    let merge_heads = read_merge_heads(repo);
+    if options.amend && !merge_heads.is_empty() {
+        bail!("You are in the middle of a merge -- cannot amend");
+    }
+    let head = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.into()),
+    };
+    if options.amend && head.is_none() {
+        bail!("You have nothing to amend");
+    }
+
+    if options.all {
+        let mut index = repo.index()?;
+        index.update_all(["*"], None)?;
+        index.write()?;
+    }
+
+    // `--no-verify` skips the hooks that can reject the commit, as with git.
+    let verify = options.run_hooks && !options.no_verify;
+    if verify {
+        run_hook(repo, "pre-commit", &[], index_path)?;
+    }
+
+    // Hooks may restage files and rewrite the message, so both are read back afterwards.
+    let message_path = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&message_path, message)?;
+    if options.run_hooks {
+        run_hook(repo, "prepare-commit-msg", &[message_path.as_os_str(), "message".as_ref()], index_path)?;
+    }
+    if verify {
+        run_hook(repo, "commit-msg", &[message_path.as_os_str()], index_path)?;
+    }
+    let message = git2::message_prettify(fs::read_to_string(&message_path)?, None)?;
+    if message.trim().is_empty() {
+        bail!("Aborting commit due to empty commit message");
+    }
+
+    let mut index = match index_path {
+        Some(path) => Index::open(path)?,
+        None => repo.index()?,
+    };
+    index.read(true)?;
+    if index.has_conflicts() {
+        bail!("Committing is not possible because you have unmerged files");
+    }
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+
+    let mut parents = Vec::new();
+    match &head {
+        Some(head) if options.amend => parents.extend(head.parents()),
+        Some(head) => parents.push(head.clone()),
+        None => {}
+    }
+    for id in &merge_heads {
+        parents.push(repo.find_commit(*id)?);
+    }
+    if !options.amend
+        && merge_heads.is_empty()
+        && let Some(head) = &head
+        && head.tree_id() == tree.id()
+    {
+        bail!("Nothing to commit: no changes are staged");
+    }
+
+    let committer = repo.committer_from_env()?;
+    let author = match &head {
+        Some(head) if options.amend => head.author().to_owned(),
+        _ => repo.author_from_env()?,
+    };
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+    let id = write_commit(repo, &author, &committer, &message, &tree, &parent_refs, options.sign)?;
+
+    let kind = match (&head, options.amend, merge_heads.is_empty()) {
+        (None, ..) => "commit (initial)",
+        (_, true, _) => "commit (amend)",
+        (_, _, false) => "commit (merge)",
+        _ => "commit",
+    };
+    let subject = message.lines().next().unwrap_or_default();
+    update_head(repo, id, &format!("{kind}: {subject}"))?;
+
+    if !merge_heads.is_empty() {
+        repo.cleanup_state()?;
+    }
+    let _ = fs::remove_file(repo.path().join("SQUASH_MSG"));
+
+    if options.run_hooks {
+        // As with git, a failing post-commit hook does not undo the commit.
+        if let Err(e) = run_hook(repo, "post-commit", &[], index_path) {
+            eprintln!("Warning: {e}");
+        }
+    }
+    Ok(id)
+}
+
+/// Move the branch HEAD points to (or HEAD itself when detached) to `id`.
+fn update_head(repo: &Repository, id: Oid, reflog: &str) -> Result<()> {
+    let head = repo.find_reference("HEAD")?;
+    match head.symbolic_target()? {
+        Some(branch) => {
+            repo.reference(branch, id, true, reflog)?;
+        }
+        None => repo.set_head_detached(id)?,
+    }
+    Ok(())
+}
+
+fn read_merge_heads(repo: &Repository) -> Vec<Oid> {
+    fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| Oid::from_str(l.trim()).ok())
+        .collect()
+}
+
+/// Write a commit object without moving any ref, signed when `sign` (or, if `None`,
+/// `commit.gpgsign`) asks for it.
+pub(crate) fn write_commit(
+    repo: &Repository,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+    sign: Option<bool>,
+) -> Result<Oid> {
+    let config = repo.config()?;
+    if !signing_enabled(&config, sign) {
+        return Ok(repo.commit(None, author, committer, message, tree, parents)?);
+    }
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let Ok(buffer) = buffer.as_str() else {
+        bail!("Commit contents are not valid UTF-8 and cannot be signed");
+    };
+    let signature = sign_buffer(repo, &signer(repo, &config)?, buffer)?;
+    Ok(repo.commit_signed(buffer, &signature, None)?)
+}
+
+fn signing_enabled(config: &Config, sign: Option<bool>) -> bool {
+    sign.unwrap_or_else(|| config.get_bool("commit.gpgsign").unwrap_or(false))
+}
+
+fn signer(repo: &Repository, config: &Config) -> Result<Signer> {
+    let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+    let key = config.get_string("user.signingkey").ok();
+    // Like git, gpg and gpgsm fall back to the committer identity when no key is configured.
+    let key_or_committer = |key: Option<String>| -> Result<String> {
+        Ok(match key {
+            Some(key) => key,
+            None => {
+                let committer = repo.committer_from_env()?;
+                format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default())
+            }
+        })
+    };
+    Ok(match format.as_str() {
+        "openpgp" => {
+            let program = config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| "gpg".to_string());
+            Signer::OpenPgp { program, key: key_or_committer(key)? }
+        }
+        "x509" => {
+            let program = config.get_string("gpg.x509.program").unwrap_or_else(|_| "gpgsm".to_string());
+            Signer::X509 { program, key: key_or_committer(key)? }
+        }
+        "ssh" => {
+            let program = config.get_string("gpg.ssh.program").unwrap_or_else(|_| "ssh-keygen".to_string());
+            let Some(key) = key else {
+                bail!(
+                    "gpg.format is ssh but user.signingkey is not set. Point it at your key, e.g. \
+                     `git config user.signingkey ~/.ssh/id_ed25519.pub`.\n{NO_SIGN_HINT}"
+                );
+            };
+            Signer::Ssh { program, key }
+        }
+        other => bail!("Unsupported gpg.format '{other}'; use openpgp, x509 or ssh.\n{NO_SIGN_HINT}"),
+    })
+}
+
+/// Produce the armored signature for a commit buffer, the way git invokes each program.
+fn sign_buffer(repo: &Repository, signer: &Signer, buffer: &str) -> Result<String> {
+    match signer {
+        Signer::OpenPgp { program, key } | Signer::X509 { program, key } => sign_gpg(signer, program, key, buffer),
+        Signer::Ssh { program, key } => {
+            let scratch = repo.path().join(format!("ai-commit-sign-{}", std::process::id()));
+            let result = sign_ssh(signer, program, key, buffer, &scratch);
+            for suffix in ["", ".sig", ".key"] {
+                let _ = fs::remove_file(with_suffix(&scratch, suffix));
+            }
+            result
+        }
+    }
+}
+
+fn sign_gpg(signer: &Signer, program: &str, key: &str, buffer: &str) -> Result<String> {
+    let mut cmd = Command::new(program);
+    cmd.args(["--status-fd=2", "-bsau", key]).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    if let Some(tty) = gpg_tty() {
+        cmd.env("GPG_TTY", tty);
+    }
+    let mut child = cmd.spawn().map_err(|e| spawn_error(program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(buffer.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !stderr.contains("[GNUPG:] SIG_CREATED ") {
+        // The status lines are meant for git; show only what the program said to the user.
+        let messages: Vec<&str> = stderr.lines().filter(|l| !l.starts_with("[GNUPG:]")).collect();
+        return Err(signing_error(signer, program, &messages.join("\n")));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn sign_ssh(signer: &Signer, program: &str, key: &str, buffer: &str, scratch: &Path) -> Result<String> {
+    fs::write(scratch, buffer)?;
+    let mut cmd = Command::new(program);
+    cmd.args(["-Y", "sign", "-n", "git", "-f"]);
+    // A literal public key means the private key is in ssh-agent, as with git.
+    let literal = key.strip_prefix("key::").or_else(|| key.starts_with("ssh-").then_some(key));
+    match literal {
+        Some(public_key) => {
+            let key_file = with_suffix(scratch, ".key");
+            fs::write(&key_file, public_key)?;
+            cmd.arg(&key_file).arg("-U");
+        }
+        None => {
+            let path = match key.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().map(|h| h.join(rest)).unwrap_or_else(|| PathBuf::from(key)),
+                None => PathBuf::from(key),
+            };
+            cmd.arg(path);
+        }
+    }
+    let output = cmd.arg(scratch).output().map_err(|e| spawn_error(program, e))?;
+    if !output.status.success() {
+        return Err(signing_error(signer, program, &String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(fs::read_to_string(with_suffix(scratch, ".sig"))?)
+}
+
+fn spawn_error(program: &str, e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        anyhow::anyhow!(
+            "Signing program '{program}' was not found. Install it or set gpg.program, gpg.x509.program \
+             or gpg.ssh.program to its path.\n{NO_SIGN_HINT}"
+        )
+    } else {
+        anyhow::anyhow!("Failed to run {program}: {e}\n{NO_SIGN_HINT}")
+    }
+}
+
+fn signing_error(signer: &Signer, program: &str, output: &str) -> anyhow::Error {
+    let mut message = format!("{program} failed to sign the commit");
+    let output = output.trim();
+    if !output.is_empty() {
+        let _ = write!(message, ":\n{output}");
+    }
+    if let Some(hint) = signing_hint(signer, output) {
+        let _ = write!(message, "\n{hint}");
+    }
+    anyhow::anyhow!("{message}\n{NO_SIGN_HINT}")
+}
+
+/// A likely fix for a failed signing attempt, based on what the signing program printed.
+fn signing_hint(signer: &Signer, output: &str) -> Option<String> {
+    let output = output.to_lowercase();
+    match signer {
+        Signer::OpenPgp { key, .. } | Signer::X509 { key, .. } => {
+            let list = if matches!(signer, Signer::X509 { .. }) { "gpgsm" } else { "gpg" };
+            if output.contains("no secret key") || output.contains("unusable secret key") {
+                Some(format!(
+                    "No usable secret key for '{key}'. Set user.signingkey to a key listed by \
+                     `{list} --list-secret-keys`."
+                ))
+            } else if output.contains("inappropriate ioctl") || output.contains("pinentry") || output.contains("tty") {
+                Some(
+                    "The passphrase could not be requested. Run `export GPG_TTY=$(tty)` or unlock the key \
+                     in gpg-agent first."
+                        .to_string(),
+                )
+            } else {
+                None
+            }
+        }
+        Signer::Ssh { key, .. } => {
+            if output.contains("agent") {
+                Some(format!(
+                    "Add the key for '{key}' to ssh-agent with `ssh-add`, or set user.signingkey to the key file."
+                ))
+            } else if output.contains("no such file") || output.contains("load") {
+                Some(format!(
+                    "Could not read '{key}'. Set user.signingkey to your SSH key file, or to the public key \
+                     when the private key is in ssh-agent."
+                ))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Run a hook from `core.hooksPath` (or `.git/hooks`) if it exists and is executable.
+/// `index_path` is passed as `GIT_INDEX_FILE` when committing another index than the
+/// repository's.
+fn run_hook(repo: &Repository, name: &str, args: &[&std::ffi::OsStr], index_path: Option<&Path>) -> Result<()> {
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(dir) if dir.is_relative() => repo.workdir().unwrap_or(repo.path()).join(dir),
+        Ok(dir) => dir,
+        Err(_) => repo.path().join("hooks"),
+    };
+    let hook = hooks_dir.join(name);
+    if !is_executable(&hook) {
+        return Ok(());
+    }
+
+    let mut command = Command::new(&hook);
+    command.args(args).current_dir(repo.workdir().unwrap_or(repo.path())).env("GIT_EDITOR", ":");
+    if let Some(path) = index_path {
+        command.env("GIT_INDEX_FILE", path);
+    }
+    let status = command.status().map_err(|e| anyhow::anyhow!("Failed to run the {name} hook: {e}"))?;
+    if !status.success() {
+        bail!("The {name} hook failed ({status}); nothing was committed");
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestRepo, write_script};
+
+    fn options() -> CommitOptions {
+        CommitOptions::new(&CommitConfig::default())
+    }
+
+    #[test]
+    fn test_commit_and_amend_in_process() {
+        let fixture = TestRepo::new("commit-basic");
+        let repo = &fixture.repo;
+        fixture.stage("a.txt", "one\n");
+        let first = commit_in_process(repo, "feat: add a\n", &options()).unwrap();
+        let commit = repo.find_commit(first).unwrap();
+        assert_eq!(commit.parent_count(), 0);
+        assert_eq!(commit.author().name().ok(), Some("Test"));
+        assert_eq!(repo.head().unwrap().target(), Some(first));
+
+        assert!(commit_in_process(repo, "chore: nothing\n", &options()).is_err());
+
+        fixture.stage("b.txt", "two\n");
+        let second = commit_in_process(repo, "feat: add b\n", &options()).unwrap();
+        assert_eq!(repo.find_commit(second).unwrap().parent_id(0).ok(), Some(first));
+
+        fixture.stage("b.txt", "two, amended\n");
+        let amended = commit_in_process(repo, "feat: add b properly\n", &options().amend()).unwrap();
+        let amended = repo.find_commit(amended).unwrap();
+        assert_eq!(amended.parent_id(0).ok(), Some(first));
+        assert_eq!(amended.message().ok(), Some("feat: add b properly\n"));
+        assert_eq!(repo.head().unwrap().target(), Some(amended.id()));
+    }
+
+    #[test]
+    fn test_merge_commit_has_both_parents() {
+        let fixture = TestRepo::new("commit-merge");
+        let repo = &fixture.repo;
+        fixture.stage("a.txt", "one\n");
+        let base = commit_in_process(repo, "init\n", &options()).unwrap();
+        fixture.stage("b.txt", "theirs\n");
+        let theirs = commit_in_process(repo, "theirs\n", &options()).unwrap();
+        repo.reference("refs/heads/feature", theirs, true, "test").unwrap();
+        repo.reset(repo.find_commit(base).unwrap().as_object(), git2::ResetType::Hard, None).unwrap();
+        fixture.stage("c.txt", "ours\n");
+        let ours = commit_in_process(repo, "ours\n", &options()).unwrap();
+
+        let annotated = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&annotated], None, None).unwrap();
+        let merge = commit_in_process(repo, "Merge branch 'feature'\n", &options()).unwrap();
+        let merge = repo.find_commit(merge).unwrap();
+        assert_eq!(merge.parent_ids().collect::<Vec<_>>(), vec![ours, theirs]);
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert!(!repo.path().join("MERGE_HEAD").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_run_and_can_abort() {
+        let fixture = TestRepo::new("commit-hooks");
+        let repo = &fixture.repo;
+        let hooks = repo.path().join("hooks");
+        write_script(&hooks.join("commit-msg"), "printf '\\nReviewed-by: hook\\n' >> \"$1\"");
+        fixture.stage("a.txt", "one\n");
+        let id = commit_in_process(repo, "feat: add a\n", &options()).unwrap();
+        assert_eq!(repo.find_commit(id).unwrap().message().ok(), Some("feat: add a\n\nReviewed-by: hook\n"));
+
+        write_script(&hooks.join("pre-commit"), "exit 1");
+        fixture.stage("a.txt", "two\n");
+        assert!(commit_in_process(repo, "feat: change a\n", &options()).is_err());
+        assert_eq!(repo.head().unwrap().target(), Some(id));
+
+        // --no-verify skips pre-commit and commit-msg but not prepare-commit-msg.
+        write_script(&hooks.join("prepare-commit-msg"), "printf 'Prepared: yes\\n' >> \"$1\"");
+        let mut no_verify = options();
+        no_verify.no_verify = true;
+        let skipped = commit_in_process(repo, "feat: change a\n\n", &no_verify).unwrap();
+        assert_eq!(repo.find_commit(skipped).unwrap().message().ok(), Some("feat: change a\n\nPrepared: yes\n"));
+
+        let mut no_hooks = options();
+        no_hooks.run_hooks = false;
+        fixture.stage("a.txt", "three\n");
+        let skipped = commit_in_process(repo, "feat: change a again\n", &no_hooks).unwrap();
+        assert_eq!(repo.find_commit(skipped).unwrap().message().ok(), Some("feat: change a again\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_tree_leaves_the_index() {
+        let fixture = TestRepo::new("commit-tree");
+        let repo = &fixture.repo;
+        fixture.stage("a.txt", "one\n");
+        let base = commit_in_process(repo, "init\n", &options()).unwrap();
+
+        // Commit only b.txt of the staged b.txt and c.txt; the hooks see just that
+        fixture.stage("b.txt", "two\n");
+        let mut partial = Index::new().unwrap();
+        partial.read_tree(&repo.find_commit(base).unwrap().tree().unwrap()).unwrap();
+        partial.add(&repo.index().unwrap().get_path(Path::new("b.txt"), 0).unwrap()).unwrap();
+        let tree = partial.write_tree_to(repo).unwrap();
+        fixture.stage("c.txt", "three\n");
+
+        let hooks = repo.path().join("hooks");
+        write_script(&hooks.join("pre-commit"), "git ls-files > .git/seen");
+        write_script(&hooks.join("commit-msg"), "printf 'Reviewed-by: hook\\n' >> \"$1\"");
+        let mut options = options();
+        options.signoff = true;
+        let id = commit_tree(repo, tree, "feat: add b\n", &options).unwrap();
+
+        let commit = repo.find_commit(id).unwrap();
+        assert_eq!(commit.tree_id(), tree);
+        assert_eq!(commit.parent_id(0).ok(), Some(base));
+        assert_eq!(
+            commit.message().ok(),
+            Some("feat: add b\n\nSigned-off-by: Test <test@example.com>\nReviewed-by: hook\n")
+        );
+        assert_eq!(repo.head().unwrap().target(), Some(id));
+        assert_eq!(fs::read_to_string(repo.path().join("seen")).unwrap(), "a.txt\nb.txt\n");
+        assert!(repo.index().unwrap().get_path(Path::new("c.txt"), 0).is_some());
+        assert!(!repo.path().join(format!("ai-commit-index.{}", std::process::id())).exists());
+    }
+
+    #[test]
+    fn test_all_stages_tracked_files_and_signoff() {
+        let fixture = TestRepo::new("commit-all");
+        let repo = &fixture.repo;
+        fixture.stage("a.txt", "one\n");
+        fixture.stage("b.txt", "one\n");
+        commit_in_process(repo, "init\n", &options()).unwrap();
+
+        fixture.write("a.txt", "modified\n");
+        fs::remove_file(fixture.dir.join("b.txt")).unwrap();
+        fixture.write("c.txt", "untracked\n");
+        let mut all = options();
+        all.all = true;
+        let id = commit_in_process(repo, "chore: update\n", &all).unwrap();
+        let tree = repo.find_commit(id).unwrap().tree().unwrap();
+        let blob = tree.get_name("a.txt").unwrap().to_object(repo).unwrap().peel_to_blob().unwrap();
+        assert_eq!(blob.content(), b"modified\n");
+        assert!(tree.get_name("b.txt").is_none());
+        assert!(tree.get_name("c.txt").is_none());
+
+        let mut signoff = options();
+        signoff.signoff = true;
+        signoff.trailers.push(Trailer::new("Co-authored-by", "B <b@example.com>"));
+        assert_eq!(
+            trailers::append("chore: update", &all_trailers(repo, &signoff).unwrap()),
+            "chore: update\n\nCo-authored-by: B <b@example.com>\nSigned-off-by: Test <test@example.com>\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signed_commits() {
+        let fixture = TestRepo::new("commit-signing");
+        let repo = &fixture.repo;
+        let gpg = fixture.dir.join("fake-gpg");
+        write_script(&gpg, "cat >/dev/null\necho '[GNUPG:] SIG_CREATED D 1 8 00' >&2\necho 'FAKE PGP SIGNATURE'");
+        let ssh = fixture.dir.join("fake-ssh-keygen");
+        write_script(&ssh, "for last; do :; done\necho 'FAKE SSH SIGNATURE' > \"$last.sig\"");
+        let mut config = repo.config().unwrap();
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.program", gpg.to_str().unwrap()).unwrap();
+        config.set_str("gpg.ssh.program", ssh.to_str().unwrap()).unwrap();
+
+        fixture.stage("a.txt", "one\n");
+        let id = commit_in_process(repo, "feat: add a\n", &options()).unwrap();
+        let (signature, _) = repo.extract_signature(&id, None).unwrap();
+        assert_eq!(signature.as_str().ok(), Some("FAKE PGP SIGNATURE\n"));
+
+        config.set_str("gpg.format", "ssh").unwrap();
+        config.set_str("user.signingkey", "ssh-ed25519 AAAATEST").unwrap();
+        fixture.stage("a.txt", "two\n");
+        let id = commit_in_process(repo, "feat: change a\n", &options()).unwrap();
+        let (signature, _) = repo.extract_signature(&id, None).unwrap();
+        assert_eq!(signature.as_str().ok(), Some("FAKE SSH SIGNATURE\n"));
+
+        config.set_str("gpg.format", "x509").unwrap();
+        config.set_str("gpg.x509.program", gpg.to_str().unwrap()).unwrap();
+        fixture.stage("a.txt", "three\n");
+        let id = commit_in_process(repo, "feat: change a again\n", &options()).unwrap();
+        assert!(repo.extract_signature(&id, None).is_ok());
+
+        // An explicit override wins over commit.gpgsign.
+        fixture.stage("a.txt", "four\n");
+        let id = commit_in_process(repo, "feat: unsigned\n", &options().sign(Some(false))).unwrap();
+        assert!(repo.extract_signature(&id, None).is_err());
+
+        config.set_str("gpg.format", "smime").unwrap();
+        fixture.stage("a.txt", "five\n");
+        let err = commit_in_process(repo, "feat: unknown format\n", &options()).unwrap_err().to_string();
+        assert!(err.contains("Unsupported gpg.format 'smime'") && err.contains("--no-gpg-sign"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signing_failure_is_actionable() {
+        let fixture = TestRepo::new("commit-sign-fail");
+        let repo = &fixture.repo;
+        let gpg = fixture.dir.join("failing-gpg");
+        write_script(&gpg, "cat >/dev/null\necho 'gpg: signing failed: No secret key' >&2\nexit 2");
+        let mut config = repo.config().unwrap();
+        config.set_str("gpg.program", gpg.to_str().unwrap()).unwrap();
+        config.set_str("user.signingkey", "ABCD1234").unwrap();
+
+        fixture.stage("a.txt", "one\n");
+        let err = commit_in_process(repo, "feat: add a\n", &options().sign(Some(true))).unwrap_err().to_string();
+        assert!(err.contains("No usable secret key for 'ABCD1234'"), "{err}");
+        assert!(err.contains("--no-gpg-sign"));
+        assert!(repo.head().is_err(), "nothing should have been committed");
+    }
+
+    #[test]
+    fn test_signing_hint() {
+        let ssh = Signer::Ssh { program: "ssh-keygen".into(), key: "~/.ssh/id_ed25519".into() };
+        assert!(signing_hint(&ssh, "Couldn't load public key ~/.ssh/id_ed25519: No such file or directory").is_some());
+        assert!(signing_hint(&ssh, "agent refused operation").unwrap().contains("ssh-add"));
+        let gpg = Signer::OpenPgp { program: "gpg".into(), key: "A".into() };
+        assert!(signing_hint(&gpg, "gpg: signing failed: Inappropriate ioctl for device").unwrap().contains("GPG_TTY"));
+        assert!(signing_hint(&gpg, "gpg: something else").is_none());
+    }
+}
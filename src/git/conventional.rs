@@ -0,0 +1,78 @@
+//! Parsing of Conventional Commits messages (`type(scope)!: description`).
+
+/// A commit message that follows the Conventional Commits format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    /// `!` after the type/scope, or a `BREAKING CHANGE:` footer.
+    pub breaking: bool,
+    pub description: String,
+}
+
+/// Parse the first line of `message`; returns `None` for non-conventional messages.
+pub fn parse(message: &str) -> Option<ConventionalCommit> {
+    let subject = message.lines().next()?.trim();
+    let (prefix, description) = subject.split_once(':')?;
+    let description = description.trim();
+    if description.is_empty() || prefix.contains(' ') {
+        return None;
+    }
+
+    let (prefix, bang) = match prefix.strip_suffix('!') {
+        Some(p) => (p, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, rest)) => (kind, Some(rest.strip_suffix(')')?.trim().to_string())),
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    Some(ConventionalCommit {
+        kind: kind.to_lowercase(),
+        scope: scope.filter(|s| !s.is_empty()),
+        breaking: bang || has_breaking_footer(message),
+        description: description.to_string(),
+    })
+}
+
+/// `BREAKING CHANGE:` / `BREAKING-CHANGE:` anywhere after the subject line.
+pub fn has_breaking_footer(message: &str) -> bool {
+    message.lines().skip(1).any(|l| {
+        let l = l.trim_start();
+        l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_scope_breaking() {
+        let c = parse("feat(api)!: drop v1 endpoints").unwrap();
+        assert_eq!(c.kind, "feat");
+        assert_eq!(c.scope.as_deref(), Some("api"));
+        assert!(c.breaking);
+        assert_eq!(c.description, "drop v1 endpoints");
+    }
+
+    #[test]
+    fn test_parse_breaking_footer() {
+        let c = parse("fix: change default\n\nBREAKING CHANGE: timeout is now 30s").unwrap();
+        assert_eq!(c.kind, "fix");
+        assert!(c.scope.is_none());
+        assert!(c.breaking);
+    }
+
+    #[test]
+    fn test_parse_non_conventional() {
+        assert!(parse("Update README").is_none());
+        assert!(parse("Merge branch 'main': sync").is_none());
+        assert!(parse("fix:").is_none());
+        assert!(parse("fix(scope: missing paren").is_none());
+    }
+}
@@ -1,59 +1,117 @@
 use anyhow::Result;
-use git2::{DiffOptions, Repository};
+use git2::{Diff, DiffOptions, Patch, Repository};
+use serde::Serialize;
 use std::path::Path;
 use std::str;
 
 use crate::config::CommitConfig;
 
-fn open_repo() -> Result<Repository> {
+pub(crate) fn open_repo() -> Result<Repository> {
     Repository::open_from_env().map_err(|e| anyhow::anyhow!("Not in a git repository: {e}"))
 }
 
-pub fn get_staged_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
-    let repo = open_repo()?;
-
-    let head = repo.head()?.peel_to_tree()?;
-    let mut index = repo.index()?;
-    let oid = index.write_tree()?;
-    let index_tree = repo.find_tree(oid)?;
+/// Working directory of the current repository, without a trailing slash; it identifies
+/// the repository in the usage log and message history. `None` outside a repository.
+pub(crate) fn current_workdir() -> Option<String> {
+    let repo = open_repo().ok()?;
+    Some(repo.workdir()?.display().to_string().trim_end_matches('/').to_string())
+}
 
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.context_lines(3);
+/// Which change a diff describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffSource {
+    /// HEAD against the index.
+    Staged,
+    /// The index against the working tree.
+    Unstaged,
+    /// HEAD against the working tree, as `git commit -a` records it.
+    Tracked,
+}
 
-    let diff = repo.diff_tree_to_tree(Some(&head), Some(&index_tree), Some(&mut diff_opts))?;
+/// Size of a change as `git diff --shortstat` reports it, leaving out ignored files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
 
-    format_diff(diff, commit_config)
+pub fn get_staged_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
+    let repo = open_repo()?;
+    format_diff(build_diff(&repo, DiffSource::Staged)?, commit_config)
 }
 
 pub fn get_unstaged_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
     let repo = open_repo()?;
+    format_diff(build_diff(&repo, DiffSource::Unstaged)?, commit_config)
+}
+
+pub fn get_diff_stats(source: DiffSource, commit_config: Option<&CommitConfig>) -> Result<DiffStats> {
+    let repo = open_repo()?;
+    diff_stats(&build_diff(&repo, source)?, commit_config)
+}
 
+fn build_diff(repo: &Repository, source: DiffSource) -> Result<Diff<'_>> {
     let mut diff_opts = DiffOptions::new();
     diff_opts.context_lines(3);
-    diff_opts.include_untracked(false);
 
-    let diff = repo.diff_index_to_workdir(None, Some(&mut diff_opts))?;
+    let diff = match source {
+        DiffSource::Staged => {
+            let head = repo.head()?.peel_to_tree()?;
+            let mut index = repo.index()?;
+            let oid = index.write_tree()?;
+            let index_tree = repo.find_tree(oid)?;
+            repo.diff_tree_to_tree(Some(&head), Some(&index_tree), Some(&mut diff_opts))?
+        }
+        DiffSource::Unstaged => {
+            diff_opts.include_untracked(false);
+            repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
+        }
+        DiffSource::Tracked => {
+            let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+            diff_opts.include_untracked(false);
+            repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut diff_opts))?
+        }
+    };
+    Ok(diff)
+}
 
-    format_diff(diff, commit_config)
+pub(crate) fn diff_stats(diff: &Diff, commit_config: Option<&CommitConfig>) -> Result<DiffStats> {
+    let mut stats = DiffStats::default();
+    for (idx, delta) in diff.deltas().enumerate() {
+        if let Some(path) = delta.new_file().path().or(delta.old_file().path())
+            && is_ignored_path(path, commit_config)
+        {
+            continue;
+        }
+        stats.files_changed += 1;
+        if let Some(patch) = Patch::from_diff(diff, idx)? {
+            let (_, insertions, deletions) = patch.line_stats()?;
+            stats.insertions += insertions;
+            stats.deletions += deletions;
+        }
+    }
+    Ok(stats)
 }
 
-fn format_diff(diff: git2::Diff, commit_config: Option<&CommitConfig>) -> Result<String> {
+pub(crate) fn format_diff(diff: git2::Diff, commit_config: Option<&CommitConfig>) -> Result<String> {
     let mut diff_content = String::new();
     let ignore_lock_files = commit_config.map(|c| c.ignore_lock_files).unwrap_or(true);
     let custom_patterns: &[String] = commit_config.map(|c| c.custom_ignore_patterns.as_slice()).unwrap_or(&[]);
 
     diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
-        if let Some(path) = delta.new_file().path() {
-            if ignore_lock_files && should_ignore_file(path) {
-                return true;
-            }
-
-            if should_ignore_by_custom_patterns(path, custom_patterns) {
-                return true;
-            }
+        if let Some(path) = delta.new_file().path()
+            && is_ignored(path, ignore_lock_files, custom_patterns)
+        {
+            return true;
         }
 
         if let Ok(content) = str::from_utf8(line.content()) {
+            // Content lines carry their `+`/`-`/` ` marker separately from the text
+            if matches!(line.origin(), ' ' | '+' | '-') {
+                diff_content.push(line.origin());
+            }
             diff_content.push_str(content);
         }
         true
@@ -62,6 +120,17 @@ fn format_diff(diff: git2::Diff, commit_config: Option<&CommitConfig>) -> Result
     Ok(diff_content)
 }
 
+fn is_ignored(path: &Path, ignore_lock_files: bool, custom_patterns: &[String]) -> bool {
+    (ignore_lock_files && should_ignore_file(path)) || should_ignore_by_custom_patterns(path, custom_patterns)
+}
+
+/// Whether `path` is excluded from the diff sent to the model (lock files, custom patterns).
+pub(crate) fn is_ignored_path(path: &Path, commit_config: Option<&CommitConfig>) -> bool {
+    let ignore_lock_files = commit_config.map(|c| c.ignore_lock_files).unwrap_or(true);
+    let custom_patterns: &[String] = commit_config.map(|c| c.custom_ignore_patterns.as_slice()).unwrap_or(&[]);
+    is_ignored(path, ignore_lock_files, custom_patterns)
+}
+
 fn should_ignore_file(path: &Path) -> bool {
     let ignored_files = [
         "Cargo.lock",
@@ -99,9 +168,13 @@ fn should_ignore_by_custom_patterns(path: &Path, patterns: &[String]) -> bool {
     false
 }
 
+/// The change `git commit --amend` would record: HEAD's parent (or the empty tree for a root
+/// commit) against the index. Unstaged working-tree changes are not part of it.
 pub fn get_amend_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
-    let repo = open_repo()?;
+    amend_diff(&open_repo()?, commit_config)
+}
 
+pub(crate) fn amend_diff(repo: &Repository, commit_config: Option<&CommitConfig>) -> Result<String> {
     let head_commit = repo.head()?.peel_to_commit()?;
     let parent_tree = if head_commit.parent_count() > 0 {
         head_commit.parent(0)?.tree()?
@@ -112,13 +185,23 @@ pub fn get_amend_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
 
     let mut diff_opts = DiffOptions::new();
     diff_opts.context_lines(3);
-    diff_opts.include_untracked(false);
 
-    let diff = repo.diff_tree_to_workdir_with_index(Some(&parent_tree), Some(&mut diff_opts))?;
+    let index = repo.index()?;
+    let diff = repo.diff_tree_to_index(Some(&parent_tree), Some(&index), Some(&mut diff_opts))?;
 
     format_diff(diff, commit_config)
 }
 
+/// The change `git commit -a` would record: HEAD against the working tree for tracked
+/// files, staged or not. Untracked files are left out, as git does.
+pub fn get_tracked_diff(commit_config: Option<&CommitConfig>) -> Result<String> {
+    tracked_diff(&open_repo()?, commit_config)
+}
+
+pub(crate) fn tracked_diff(repo: &Repository, commit_config: Option<&CommitConfig>) -> Result<String> {
+    format_diff(build_diff(repo, DiffSource::Tracked)?, commit_config)
+}
+
 pub fn get_last_commit_message() -> Result<String> {
     let repo = open_repo()?;
 
@@ -142,6 +225,7 @@ pub fn get_truncated_diff(diff: &str, limit: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TestRepo;
     use std::path::Path;
 
     #[test]
@@ -176,6 +260,16 @@ mod tests {
         assert!(!should_ignore_by_custom_patterns(Path::new("anything"), &patterns));
     }
 
+    #[test]
+    fn test_format_diff_keeps_line_markers() {
+        let patch = "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n keep\n-old\n+new\n\
+                     diff --git a/Cargo.lock b/Cargo.lock\n--- a/Cargo.lock\n+++ b/Cargo.lock\n@@ -1 +1 @@\n-1\n+2\n";
+        let diff = git2::Diff::from_buffer(patch.as_bytes()).unwrap();
+        let formatted = format_diff(diff, None).unwrap();
+        assert!(formatted.contains("@@ -1,2 +1,2 @@\n keep\n-old\n+new\n"), "{formatted}");
+        assert!(!formatted.contains("Cargo.lock"), "{formatted}");
+    }
+
     #[test]
     fn test_truncate_diff_within_limit() {
         let diff = "some diff content";
@@ -194,4 +288,52 @@ mod tests {
     fn test_truncate_diff_empty() {
         assert_eq!(get_truncated_diff("", 100), "");
     }
+
+    #[test]
+    fn test_tracked_diff_includes_unstaged_but_not_untracked() {
+        let fixture = TestRepo::new("tracked");
+        let repo = &fixture.repo;
+        fixture.stage("a.txt", "base\n");
+        fixture.commit_file("b.txt", "base\n", "init");
+
+        fixture.stage("a.txt", "staged edit\n");
+        fixture.write("b.txt", "unstaged edit\n");
+        fixture.write("c.txt", "untracked\n");
+
+        let diff = tracked_diff(repo, None).unwrap();
+        assert!(diff.contains("staged edit"));
+        assert!(diff.contains("unstaged edit"));
+        assert!(!diff.contains("untracked"));
+
+        let stats = |source| diff_stats(&build_diff(repo, source).unwrap(), None).unwrap();
+        assert_eq!(stats(DiffSource::Tracked), DiffStats { files_changed: 2, insertions: 2, deletions: 2 });
+        assert_eq!(stats(DiffSource::Staged), DiffStats { files_changed: 1, insertions: 1, deletions: 1 });
+        assert_eq!(stats(DiffSource::Unstaged), DiffStats { files_changed: 1, insertions: 1, deletions: 1 });
+    }
+
+    #[test]
+    fn test_amend_diff_is_parent_against_index() {
+        let fixture = TestRepo::new("amend");
+        fixture.commit_file("a.txt", "base\n", "init");
+        fixture.commit_file("a.txt", "amended commit content\n", "change a");
+
+        fixture.stage("b.txt", "staged addition\n");
+        fixture.write("a.txt", "unstaged edit\n");
+        fixture.write("c.txt", "untracked\n");
+
+        let diff = amend_diff(&fixture.repo, None).unwrap();
+        assert!(diff.contains("-base\n+amended commit content\n"));
+        assert!(diff.contains("+staged addition"));
+        assert!(!diff.contains("unstaged edit"));
+        assert!(!diff.contains("untracked"));
+    }
+
+    #[test]
+    fn test_amend_diff_root_commit() {
+        let fixture = TestRepo::new("amend-root");
+        fixture.commit_file("a.txt", "first\n", "init");
+
+        let diff = amend_diff(&fixture.repo, None).unwrap();
+        assert!(diff.contains("b/a.txt") && diff.contains("first"));
+    }
 }
@@ -0,0 +1,319 @@
+use anyhow::Result;
+use git2::{DiffOptions, Oid, Repository, Sort};
+
+use super::commit::write_commit;
+use super::diff::{format_diff, open_repo};
+use crate::config::CommitConfig;
+
+/// A commit as shown to the model or the user.
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    pub id: Oid,
+    pub short_id: String,
+    pub summary: String,
+    pub message: String,
+    pub author: String,
+}
+
+impl CommitInfo {
+    fn from_commit(commit: &git2::Commit) -> Self {
+        let message = commit.message().unwrap_or("").to_string();
+        Self {
+            id: commit.id(),
+            short_id: commit.id().to_string()[..7].to_string(),
+            summary: message.lines().next().unwrap_or("").to_string(),
+            message,
+            author: commit.author().name().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// Branches tried, in order, when no base branch is configured.
+const DEFAULT_BASES: &[&str] = &["origin/HEAD", "main", "master", "origin/main", "origin/master"];
+
+/// Resolve a base branch (or any revision) to a commit, falling back to common defaults.
+/// Returns the name that was used together with the commit id.
+pub fn resolve_base(repo: &Repository, base: Option<&str>) -> Result<(String, Oid)> {
+    if let Some(base) = base {
+        let object = repo
+            .revparse_single(base)
+            .map_err(|e| anyhow::anyhow!("Base branch '{base}' not found: {}", e.message()))?;
+        return Ok((base.to_string(), object.peel_to_commit()?.id()));
+    }
+    for candidate in DEFAULT_BASES {
+        if let Ok(object) = repo.revparse_single(candidate)
+            && let Ok(commit) = object.peel_to_commit()
+        {
+            return Ok((candidate.to_string(), commit.id()));
+        }
+    }
+    Err(anyhow::anyhow!("Could not determine a base branch (tried {}). Pass --base.", DEFAULT_BASES.join(", ")))
+}
+
+/// Commits reachable from `to` but not from `from`, oldest first.
+pub fn commits_between(repo: &Repository, from: Option<Oid>, to: Oid) -> Result<Vec<CommitInfo>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    walk.push(to)?;
+    if let Some(from) = from {
+        walk.hide(from)?;
+    }
+    let mut commits = Vec::new();
+    for oid in walk {
+        commits.push(CommitInfo::from_commit(&repo.find_commit(oid?)?));
+    }
+    Ok(commits)
+}
+
+/// Whether `tag` names a release: `MAJOR.MINOR.PATCH` after an optional prefix without digits
+/// (`v`, `release-`), with optional pre-release or build metadata (`v2.0.0-rc.1`).
+pub fn is_version_tag(tag: &str) -> bool {
+    let start = tag.find(|c: char| c.is_ascii_digit()).unwrap_or(tag.len());
+    let core = tag[start..].split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The most recent version tag reachable from `head` (by commit time), if any. Other tags
+/// (`deploy`, `nightly`) are not releases and are skipped.
+pub fn latest_tag(repo: &Repository, head: Oid) -> Result<Option<(String, Oid)>> {
+    let mut best: Option<(String, Oid, i64)> = None;
+    for name in repo.tag_names(None)?.iter().flatten().flatten().filter(|name| is_version_tag(name)) {
+        let Ok(commit) = repo.revparse_single(name).and_then(|o| o.peel_to_commit()) else {
+            continue;
+        };
+        let reachable = commit.id() == head || repo.graph_descendant_of(head, commit.id())?;
+        let time = commit.time().seconds();
+        if reachable && best.as_ref().is_none_or(|(_, _, t)| time > *t) {
+            best = Some((name.to_string(), commit.id(), time));
+        }
+    }
+    Ok(best.map(|(name, id, _)| (name, id)))
+}
+
+/// Commits selected by a revision range.
+pub struct RangeCommits {
+    /// The exclusive start of the range (`v0.1.5` in `v0.1.5..HEAD`), if any.
+    pub from_name: Option<String>,
+    pub to_name: String,
+    pub commits: Vec<CommitInfo>,
+}
+
+/// Resolve `A..B`, `A` (meaning `A..HEAD`) or nothing (latest tag `..HEAD`, or all history).
+pub fn get_commits_in_range(range: Option<&str>) -> Result<RangeCommits> {
+    let repo = open_repo()?;
+    let peel = |spec: &str| -> Result<Oid> {
+        let object =
+            repo.revparse_single(spec).map_err(|e| anyhow::anyhow!("Unknown revision '{spec}': {}", e.message()))?;
+        Ok(object.peel_to_commit()?.id())
+    };
+
+    let (from_name, to_name) = match range.map(str::trim) {
+        Some(range) if range.contains("...") => {
+            return Err(anyhow::anyhow!("Symmetric ranges (A...B) are not supported; use A..B"));
+        }
+        Some(range) if range.contains("..") => {
+            let (from, to) = range.split_once("..").unwrap_or_default();
+            let to = if to.is_empty() { "HEAD" } else { to };
+            (Some(from.to_string()).filter(|f| !f.is_empty()), to.to_string())
+        }
+        Some(from) if !from.is_empty() => (Some(from.to_string()), "HEAD".to_string()),
+        _ => {
+            let head = peel("HEAD")?;
+            (latest_tag(&repo, head)?.map(|(name, _)| name), "HEAD".to_string())
+        }
+    };
+
+    let to = peel(&to_name)?;
+    let from = from_name.as_deref().map(peel).transpose()?;
+    let commits = commits_between(&repo, from, to)?;
+    Ok(RangeCommits { from_name, to_name, commits })
+}
+
+/// Diff of a commit against its first parent, or against the empty tree for a root commit.
+pub fn get_commit_diff(repo: &Repository, id: Oid, commit_config: Option<&CommitConfig>) -> Result<String> {
+    let commit = repo.find_commit(id)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(3);
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), Some(&mut diff_opts))?;
+    format_diff(diff, commit_config)
+}
+
+/// The first of `branches` that already contains commit `id`. Branches that do not exist
+/// locally (e.g. a remote that was never fetched) are skipped.
+pub fn protected_branch_containing(repo: &Repository, id: Oid, branches: &[String]) -> Result<Option<String>> {
+    for branch in branches {
+        let Ok(tip) = repo.revparse_single(branch).and_then(|o| o.peel_to_commit()) else {
+            continue;
+        };
+        if tip.id() == id || repo.graph_descendant_of(tip.id(), id)? {
+            return Ok(Some(branch.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Recreate a linear run of commits ending at HEAD with new messages, oldest first, and
+/// move HEAD (or the branch it points to) to the last new commit. Trees and authors are
+/// kept, so the index and working tree stay as they are; the committer is taken from
+/// `GIT_COMMITTER_*` or the config, as with `git rebase`. The new commits are signed like
+/// `git commit` would sign them; `sign` overrides `commit.gpgsign`.
+pub fn reword_commits(repo: &Repository, rewrites: &[(Oid, String)], sign: Option<bool>) -> Result<Oid> {
+    let head = repo.head()?;
+    let head_id = head.peel_to_commit()?.id();
+    if rewrites.last().map(|(id, _)| *id) != Some(head_id) {
+        return Err(anyhow::anyhow!("Only a range of commits ending at HEAD can be reworded"));
+    }
+
+    let committer = repo.committer_from_env()?;
+    let mut parent: Option<git2::Commit> = None;
+    for (i, (id, message)) in rewrites.iter().enumerate() {
+        let original = repo.find_commit(*id)?;
+        if original.parent_count() > 1 {
+            return Err(anyhow::anyhow!("Cannot reword merge commit {}", &id.to_string()[..7]));
+        }
+        if i > 0 && original.parent_id(0).ok() != Some(rewrites[i - 1].0) {
+            return Err(anyhow::anyhow!("Commits to reword must form a linear history"));
+        }
+        let parents: Vec<git2::Commit> = match &parent {
+            Some(p) => vec![p.clone()],
+            None => original.parents().collect(),
+        };
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        let new_id =
+            write_commit(repo, &original.author(), &committer, message, &original.tree()?, &parent_refs, sign)?;
+        parent = Some(repo.find_commit(new_id)?);
+    }
+
+    let new_head = parent.map(|c| c.id()).unwrap_or(head_id);
+    if head.is_branch() {
+        let mut branch = head;
+        branch.set_target(new_head, "ai-commit reword")?;
+    } else {
+        repo.set_head_detached(new_head)?;
+    }
+    Ok(new_head)
+}
+
+/// Everything the current branch adds on top of `base`: the commits since the merge base
+/// and the diff from the merge base to HEAD.
+pub struct BranchChanges {
+    pub base_name: String,
+    pub merge_base: Oid,
+    pub commits: Vec<CommitInfo>,
+    pub diff: String,
+}
+
+pub fn get_branch_changes(base: Option<&str>, commit_config: Option<&CommitConfig>) -> Result<BranchChanges> {
+    let repo = open_repo()?;
+    let head = repo.head()?.peel_to_commit()?;
+    let (base_name, base_id) = resolve_base(&repo, base)?;
+    let merge_base = repo.merge_base(base_id, head.id())?;
+
+    let commits = commits_between(&repo, Some(merge_base), head.id())?;
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(3);
+    let base_tree = repo.find_commit(merge_base)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head.tree()?), Some(&mut diff_opts))?;
+    let diff = format_diff(diff, commit_config)?;
+
+    Ok(BranchChanges { base_name, merge_base, commits, diff })
+}
+
+/// Current branch name, or `None` on a detached HEAD.
+pub fn current_branch() -> Result<Option<String>> {
+    let repo = open_repo()?;
+    let head = repo.head()?;
+    Ok(if head.is_branch() { head.shorthand().ok().map(str::to_string) } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestRepo, write_script};
+
+    #[test]
+    fn test_reword_commits_keeps_trees() {
+        let fixture = TestRepo::new("reword");
+        let repo = &fixture.repo;
+        let root = fixture.commit_file("a.txt", "init", "init");
+        let second = fixture.commit_file("b.txt", "wip", "wip");
+        let third = fixture.commit_file("c.txt", "more wip", "more wip");
+
+        let new_head =
+            reword_commits(repo, &[(second, "feat: add b".to_string()), (third, "feat: add c".to_string())], None)
+                .unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id(), new_head);
+        assert_eq!(head.message().ok(), Some("feat: add c"));
+        assert_eq!(head.tree_id(), repo.find_commit(third).unwrap().tree_id());
+        let parent = head.parent(0).unwrap();
+        assert_eq!(parent.message().ok(), Some("feat: add b"));
+        assert_eq!(parent.parent_id(0).unwrap(), root);
+
+        assert!(get_commit_diff(repo, second, None).unwrap().contains("b.txt"));
+        assert!(reword_commits(repo, &[(root, "x".to_string())], None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reword_commits_signs_when_enabled() {
+        let fixture = TestRepo::new("reword-sign");
+        let repo = &fixture.repo;
+        let gpg = fixture.dir.join("fake-gpg");
+        write_script(&gpg, "cat >/dev/null\necho '[GNUPG:] SIG_CREATED D 1 8 00' >&2\necho 'FAKE PGP SIGNATURE'");
+        let mut config = repo.config().unwrap();
+        config.set_str("gpg.program", gpg.to_str().unwrap()).unwrap();
+        fixture.commit_file("a.txt", "init", "init");
+        let wip = fixture.commit_file("b.txt", "wip", "wip");
+
+        config.set_bool("commit.gpgsign", true).unwrap();
+        let signed = reword_commits(repo, &[(wip, "feat: add b".to_string())], None).unwrap();
+        let (signature, _) = repo.extract_signature(&signed, None).unwrap();
+        assert_eq!(signature.as_str().ok(), Some("FAKE PGP SIGNATURE\n"));
+
+        let unsigned = reword_commits(repo, &[(signed, "feat: add b!".to_string())], Some(false)).unwrap();
+        assert!(repo.extract_signature(&unsigned, None).is_err());
+    }
+
+    #[test]
+    fn test_latest_tag_skips_non_version_tags() {
+        let fixture = TestRepo::new("latest-tag");
+        let repo = &fixture.repo;
+        let tag = |name: &str, id: Oid| {
+            repo.tag_lightweight(name, &repo.find_object(id, None).unwrap(), false).unwrap();
+        };
+        let root = fixture.commit_file("a.txt", "init", "init");
+        tag("v0.1.0", root);
+        let second = fixture.commit_file("b.txt", "b", "feat: add b");
+        tag("deploy", second);
+        tag("release-candidate", second);
+        let head = fixture.commit_file("c.txt", "c", "feat: add c");
+
+        assert_eq!(latest_tag(repo, head).unwrap(), Some(("v0.1.0".to_string(), root)));
+        tag("release-0.2.0", second);
+        assert_eq!(latest_tag(repo, head).unwrap(), Some(("release-0.2.0".to_string(), second)));
+
+        assert!(is_version_tag("1.2.3") && is_version_tag("v2.0.0-rc.1"));
+        assert!(!is_version_tag("v1.2") && !is_version_tag("nightly") && !is_version_tag("v1.2.3.4"));
+    }
+
+    #[test]
+    fn test_protected_branch_containing() {
+        let fixture = TestRepo::new("protected");
+        let repo = &fixture.repo;
+        let root = fixture.commit_file("a.txt", "init", "init");
+        repo.branch("published", &repo.find_commit(root).unwrap(), false).unwrap();
+        let local = fixture.commit_file("b.txt", "local", "local");
+
+        let branches = vec!["origin/main".to_string(), "published".to_string()];
+        assert_eq!(protected_branch_containing(repo, root, &branches).unwrap().as_deref(), Some("published"));
+        assert_eq!(protected_branch_containing(repo, local, &branches).unwrap(), None);
+    }
+}
@@ -0,0 +1,543 @@
+//! Structured diff hunks and partial application of them.
+//!
+//! `format_diff` flattens a diff into text for the model. Splitting commits and staging
+//! individual hunks need the structure instead: which file, which hunk, which lines.
+//! Selected hunks are applied by their position in the *old* side of the diff, so any
+//! subset of hunks from one diff can be applied to that diff's base content exactly.
+
+use anyhow::Result;
+use git2::{Delta, Diff, DiffOptions, Index, IndexEntry, IndexTime, Oid, Patch, Repository, Tree};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::diff::is_ignored_path;
+use crate::config::CommitConfig;
+
+#[derive(Debug, Clone)]
+pub struct HunkLine {
+    /// `' '` context, `'+'` addition or `'-'` deletion.
+    pub origin: char,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    pub fn added(&self) -> usize {
+        self.lines.iter().filter(|l| l.origin == '+').count()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.lines.iter().filter(|l| l.origin == '-').count()
+    }
+
+    /// The hunk as unified-diff text (header plus lines).
+    pub fn to_patch_text(&self) -> String {
+        let mut out = self.header.trim_end().to_string();
+        out.push('\n');
+        for line in &self.lines {
+            out.push(line.origin);
+            out.push_str(&String::from_utf8_lossy(&line.content));
+            if !line.content.ends_with(b"\n") {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        out
+    }
+}
+
+/// One changed file of a diff.
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    pub path: String,
+    pub status: Delta,
+    /// Blob id and mode on each side; `None` when the file does not exist on that side.
+    pub old: Option<(Oid, u32)>,
+    pub new: Option<(Oid, u32)>,
+    pub binary: bool,
+    /// Matched by lock-file or custom ignore patterns: shown by name only, never by content.
+    pub ignored: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// Whether the file is selected as one unit rather than by hunk: binary files, ignored
+    /// files (whose hunks are never shown to the model) and changes without text hunks.
+    pub fn is_whole_file(&self) -> bool {
+        self.binary || self.ignored || self.hunks.is_empty()
+    }
+
+    /// Number of independently selectable units: one per hunk, or one for a whole-file unit.
+    pub fn unit_count(&self) -> usize {
+        if self.is_whole_file() { 1 } else { self.hunks.len() }
+    }
+
+    /// Stable ID of a unit: `path#N` for hunks, `path` for whole-file units.
+    pub fn unit_id(&self, unit: usize) -> String {
+        if self.is_whole_file() { self.path.clone() } else { format!("{}#{}", self.path, unit + 1) }
+    }
+
+    pub fn status_label(&self) -> &'static str {
+        match self.status {
+            Delta::Added | Delta::Untracked => "added",
+            Delta::Deleted => "deleted",
+            Delta::Renamed => "renamed",
+            Delta::Typechange => "type changed",
+            _ => "modified",
+        }
+    }
+}
+
+#[cfg(test)]
+impl Hunk {
+    /// A hunk starting at `old_start` / `new_start` with `(origin, content)` lines; the line
+    /// counts and header are derived from the lines.
+    pub(crate) fn from_lines(old_start: u32, new_start: u32, lines: &[(char, &str)]) -> Self {
+        let old_lines = lines.iter().filter(|(origin, _)| *origin != '+').count() as u32;
+        let new_lines = lines.iter().filter(|(origin, _)| *origin != '-').count() as u32;
+        Hunk {
+            header: format!("@@ -{old_start},{old_lines} +{new_start},{new_lines} @@"),
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            lines: lines.iter().map(|(o, c)| HunkLine { origin: *o, content: c.as_bytes().to_vec() }).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl FilePatch {
+    /// A modified text file made of `hunks`, for tests that never read its blobs.
+    pub(crate) fn modified(path: &str, hunks: Vec<Hunk>) -> Self {
+        FilePatch {
+            path: path.to_string(),
+            status: Delta::Modified,
+            old: Some((Oid::ZERO_SHA1, 0o100644)),
+            new: Some((Oid::ZERO_SHA1, 0o100644)),
+            binary: false,
+            ignored: false,
+            hunks,
+        }
+    }
+}
+
+/// Convert a diff into per-file patches. `new` ids are only reliable for tree/index sides;
+/// `unstaged_patches` clears them so the working-tree content is read when applied.
+pub fn collect_patches(diff: &Diff, commit_config: Option<&CommitConfig>) -> Result<Vec<FilePatch>> {
+    let mut patches = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(idx) else {
+            continue;
+        };
+        let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        let path_str = path.to_string_lossy().replace('\\', "/");
+        let side = |file: git2::DiffFile| file.exists().then(|| (file.id(), u32::from(file.mode())));
+
+        let patch = Patch::from_diff(diff, idx)?;
+        let binary = delta.flags().is_binary() || patch.as_ref().is_some_and(|p| p.delta().flags().is_binary());
+        let mut hunks = Vec::new();
+        if let Some(patch) = patch.as_ref().filter(|_| !binary) {
+            for h in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(h)?;
+                let mut lines = Vec::with_capacity(line_count);
+                for l in 0..line_count {
+                    let line = patch.line_in_hunk(h, l)?;
+                    // Skip the "\ No newline at end of file" markers; the preceding
+                    // line's content already lacks the trailing newline.
+                    if matches!(line.origin(), ' ' | '+' | '-') {
+                        lines.push(HunkLine { origin: line.origin(), content: line.content().to_vec() });
+                    }
+                }
+                hunks.push(Hunk {
+                    header: String::from_utf8_lossy(hunk.header()).to_string(),
+                    old_start: hunk.old_start(),
+                    old_lines: hunk.old_lines(),
+                    new_start: hunk.new_start(),
+                    new_lines: hunk.new_lines(),
+                    lines,
+                });
+            }
+        }
+
+        patches.push(FilePatch {
+            ignored: is_ignored_path(path, commit_config),
+            path: path_str,
+            status: delta.status(),
+            old: side(delta.old_file()),
+            new: side(delta.new_file()),
+            binary,
+            hunks,
+        });
+    }
+    Ok(patches)
+}
+
+/// HEAD's tree, or the empty tree before the first commit.
+pub fn head_tree(repo: &Repository) -> Result<Tree<'_>> {
+    match repo.head() {
+        Ok(head) => Ok(head.peel_to_tree()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(repo.find_tree(repo.treebuilder(None)?.write()?)?),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Staged changes (HEAD → index) as patches, plus the HEAD tree they apply to.
+pub fn staged_patches<'r>(
+    repo: &'r Repository,
+    commit_config: Option<&CommitConfig>,
+) -> Result<(Tree<'r>, Vec<FilePatch>)> {
+    let head = head_tree(repo)?;
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(3);
+    let diff = repo.diff_tree_to_tree(Some(&head), Some(&index_tree), Some(&mut diff_opts))?;
+    let patches = collect_patches(&diff, commit_config)?;
+    Ok((head, patches))
+}
+
+/// Unstaged changes (index → working tree) as patches. Untracked files are not included.
+pub fn unstaged_patches(repo: &Repository, commit_config: Option<&CommitConfig>) -> Result<Vec<FilePatch>> {
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(3);
+    diff_opts.include_untracked(false);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut diff_opts))?;
+    let mut patches = collect_patches(&diff, commit_config)?;
+    // The working-tree side may carry a computed hash, but no blob was written for it
+    for patch in &mut patches {
+        if let Some((id, _)) = patch.new.as_mut() {
+            *id = Oid::ZERO_SHA1;
+        }
+    }
+    Ok(patches)
+}
+
+/// Map every unit ID (`path#N`) and every bare path (meaning the whole file)
+/// to the `(file, unit)` pairs it selects.
+pub fn unit_lookup(patches: &[FilePatch]) -> HashMap<String, Vec<(usize, usize)>> {
+    let mut by_id = HashMap::new();
+    for (file, patch) in patches.iter().enumerate() {
+        let all: Vec<(usize, usize)> = (0..patch.unit_count()).map(|u| (file, u)).collect();
+        for &(file, unit) in &all {
+            by_id.insert(patch.unit_id(unit), vec![(file, unit)]);
+        }
+        by_id.insert(patch.path.clone(), all);
+    }
+    by_id
+}
+
+/// Render patches with unit IDs so the model can refer to individual hunks.
+pub fn render_for_prompt(patches: &[FilePatch]) -> String {
+    let mut out = String::new();
+    for p in patches {
+        let _ = writeln!(out, "### {} ({})", p.path, p.status_label());
+        if p.ignored {
+            let _ = writeln!(out, "[{}] (lock or ignored file, contents omitted)\n", p.unit_id(0));
+            continue;
+        }
+        if p.binary || p.hunks.is_empty() {
+            let what = if p.binary { "binary file" } else { "no text changes" };
+            let _ = writeln!(out, "[{}] ({what})\n", p.unit_id(0));
+            continue;
+        }
+        for (i, hunk) in p.hunks.iter().enumerate() {
+            let _ = writeln!(out, "[{}]", p.unit_id(i));
+            out.push_str(&hunk.to_patch_text());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Apply the given hunks (all from one diff, in any order) to that diff's old content.
+pub fn apply_hunks(base: &[u8], hunks: &[&Hunk]) -> Vec<u8> {
+    let base_lines: Vec<&[u8]> = base.split_inclusive(|&b| b == b'\n').collect();
+    let mut hunks = hunks.to_vec();
+    hunks.sort_by_key(|h| h.old_start);
+
+    let mut out = Vec::with_capacity(base.len());
+    let mut pos = 0usize;
+    for hunk in hunks {
+        // For pure insertions `old_start` is the line *after which* lines are inserted.
+        let start = if hunk.old_lines == 0 { hunk.old_start as usize } else { hunk.old_start as usize - 1 };
+        let start = start.clamp(pos, base_lines.len());
+        for line in &base_lines[pos..start] {
+            out.extend_from_slice(line);
+        }
+        for line in hunk.lines.iter().filter(|l| l.origin != '-') {
+            out.extend_from_slice(&line.content);
+        }
+        pos = (start + hunk.old_lines as usize).min(base_lines.len());
+    }
+    for line in &base_lines[pos..] {
+        out.extend_from_slice(line);
+    }
+    out
+}
+
+/// Content of a file as it would be after applying the selected units.
+/// Returns `None` when the file should be absent (deleted, nothing left).
+fn selected_content(repo: &Repository, patch: &FilePatch, units: &[usize]) -> Result<Option<(Oid, u32)>> {
+    if units.len() == patch.unit_count() {
+        return match patch.new {
+            // Working-tree side: store the file as it is on disk.
+            Some((id, mode)) if id.is_zero() => Ok(Some((workdir_blob(repo, &patch.path)?, mode))),
+            new => Ok(new),
+        };
+    }
+
+    // A strict subset of the hunks of a text file.
+    let base = match patch.old {
+        Some((id, _)) => repo.find_blob(id)?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let hunks: Vec<&Hunk> = units.iter().filter_map(|&u| patch.hunks.get(u)).collect();
+    let content = apply_hunks(&base, &hunks);
+    let Some((_, mode)) = patch.old.or(patch.new) else {
+        return Ok(None);
+    };
+    Ok(Some((repo.blob(&content)?, mode)))
+}
+
+fn workdir_blob(repo: &Repository, path: &str) -> Result<Oid> {
+    let workdir = repo.workdir().ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+    Ok(repo.blob_path(&workdir.join(path))?)
+}
+
+fn index_entry(path: &str, id: Oid, mode: u32) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+/// Write the selected units of `patches` into `index`, which must currently hold the
+/// diff's old side for those paths. `selected(file, unit)` picks the units to apply.
+pub fn apply_to_index(
+    repo: &Repository,
+    index: &mut Index,
+    patches: &[FilePatch],
+    selected: impl Fn(usize, usize) -> bool,
+) -> Result<()> {
+    for (file, patch) in patches.iter().enumerate() {
+        let units: Vec<usize> = (0..patch.unit_count()).filter(|&u| selected(file, u)).collect();
+        if units.is_empty() {
+            continue;
+        }
+        match selected_content(repo, patch, &units)? {
+            Some((id, mode)) => index.add(&index_entry(&patch.path, id, mode))?,
+            None => index.remove_path(Path::new(&patch.path))?,
+        }
+    }
+    Ok(())
+}
+
+/// Stage `path` as it is in the working tree, like `git add` (a deleted file is removed).
+pub fn stage_file(repo: &Repository, path: &str) -> Result<()> {
+    let workdir = repo.workdir().ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+    let mut index = repo.index()?;
+    if workdir.join(path).symlink_metadata().is_ok() {
+        index.add_path(Path::new(path))?;
+    } else {
+        index.remove_path(Path::new(path))?;
+    }
+    Ok(index.write()?)
+}
+
+/// Put `path` back to its HEAD version in the index, like `git restore --staged`.
+pub fn unstage_file(repo: &Repository, path: &str) -> Result<()> {
+    let head = head_tree(repo)?;
+    let mut index = repo.index()?;
+    match head.get_path(Path::new(path)) {
+        Ok(entry) => index.add(&index_entry(path, entry.id(), entry.filemode() as u32))?,
+        Err(_) => index.remove_path(Path::new(path))?,
+    }
+    Ok(index.write()?)
+}
+
+/// Build a tree from `base` plus the selected units, without touching the repository index.
+pub fn build_tree(
+    repo: &Repository,
+    base: &Tree,
+    patches: &[FilePatch],
+    selected: impl Fn(usize, usize) -> bool,
+) -> Result<Oid> {
+    let mut index = Index::new()?;
+    index.read_tree(base)?;
+    apply_to_index(repo, &mut index, patches, selected)?;
+    Ok(index.write_tree_to(repo)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestRepo;
+
+    #[test]
+    fn test_apply_hunks_subset() {
+        let base = b"a\nb\nc\nd\ne\nf\n";
+        let first = Hunk::from_lines(2, 2, &[('-', "b\n"), ('+', "B\n")]);
+        let second = Hunk::from_lines(5, 5, &[('-', "e\n"), ('+', "E\n"), ('+', "E2\n")]);
+
+        assert_eq!(apply_hunks(base, &[&first, &second]), b"a\nB\nc\nd\nE\nE2\nf\n");
+        assert_eq!(apply_hunks(base, &[&second]), b"a\nb\nc\nd\nE\nE2\nf\n");
+        assert_eq!(apply_hunks(base, &[&first]), b"a\nB\nc\nd\ne\nf\n");
+        assert_eq!(apply_hunks(base, &[]), base);
+    }
+
+    #[test]
+    fn test_apply_hunks_insertion_and_eof() {
+        let base = b"a\nb";
+        let insert = Hunk::from_lines(1, 1, &[('+', "x\n")]);
+        let tail = Hunk::from_lines(2, 2, &[('-', "b"), ('+', "b\n"), ('+', "c")]);
+        assert_eq!(apply_hunks(base, &[&insert]), b"a\nx\nb");
+        assert_eq!(apply_hunks(base, &[&insert, &tail]), b"a\nx\nb\nc");
+    }
+
+    /// A fresh repository with one commit containing `a.txt` (twenty numbered lines).
+    fn fixture_repo(name: &str) -> (TestRepo, String) {
+        let fixture = TestRepo::new(name);
+        let original: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        fixture.commit_file("a.txt", &original, "init");
+        (fixture, original)
+    }
+
+    #[test]
+    fn test_build_tree_from_staged_subset() {
+        let (fixture, original) = fixture_repo("split");
+        let repo = &fixture.repo;
+
+        let changed = original.replace("line 2\n", "line two\n").replace("line 19\n", "line nineteen\n");
+        fixture.stage("a.txt", &changed);
+        fixture.stage("b.txt", "new\n");
+
+        let (base, patches) = staged_patches(repo, None).unwrap();
+        let a = patches.iter().position(|p| p.path == "a.txt").unwrap();
+        assert_eq!(patches[a].unit_count(), 2);
+
+        let only_second = build_tree(repo, &base, &patches, |f, u| f == a && u == 1).unwrap();
+        let tree = repo.find_tree(only_second).unwrap();
+        let blob = repo.find_blob(tree.get_path(Path::new("a.txt")).unwrap().id()).unwrap();
+        assert_eq!(blob.content(), original.replace("line 19\n", "line nineteen\n").as_bytes());
+        assert!(tree.get_path(Path::new("b.txt")).is_err());
+
+        let everything = build_tree(repo, &base, &patches, |_, _| true).unwrap();
+        assert_eq!(everything, repo.index().unwrap().write_tree().unwrap());
+    }
+
+    #[test]
+    fn test_ignored_file_is_one_unit() {
+        let (fixture, original) = fixture_repo("split-ignored");
+        let repo = &fixture.repo;
+        fixture.commit_file("Cargo.lock", &original, "lock");
+        let changed = original.replace("line 2\n", "line two\n").replace("line 19\n", "line nineteen\n");
+        fixture.stage("Cargo.lock", &changed);
+
+        let (base, patches) = staged_patches(repo, None).unwrap();
+        assert!(patches[0].ignored && patches[0].hunks.len() == 2);
+        assert_eq!(patches[0].unit_count(), 1);
+        assert_eq!(patches[0].unit_id(0), "Cargo.lock");
+        assert!(render_for_prompt(&patches).contains("[Cargo.lock] (lock or ignored file"));
+
+        let tree = repo.find_tree(build_tree(repo, &base, &patches, |_, u| u == 0).unwrap()).unwrap();
+        let blob = repo.find_blob(tree.get_path(Path::new("Cargo.lock")).unwrap().id()).unwrap();
+        assert_eq!(blob.content(), changed.as_bytes());
+
+        fixture.write("Cargo.lock", &original);
+        let patches = unstaged_patches(repo, None).unwrap();
+        let mut index = repo.index().unwrap();
+        apply_to_index(repo, &mut index, &patches, |_, u| u == 0).unwrap();
+        index.write().unwrap();
+        assert!(unstaged_patches(repo, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_unstaged_subset_to_index() {
+        let (fixture, original) = fixture_repo("stage");
+        let repo = &fixture.repo;
+        let changed = original.replace("line 2\n", "line two\n").replace("line 19\n", "line nineteen\n");
+        fixture.write("a.txt", &changed);
+
+        let patches = unstaged_patches(repo, None).unwrap();
+        assert_eq!(patches[0].unit_count(), 2);
+
+        let mut index = repo.index().unwrap();
+        apply_to_index(repo, &mut index, &patches, |_, u| u == 0).unwrap();
+        index.write().unwrap();
+
+        let staged = repo.index().unwrap().get_path(Path::new("a.txt"), 0).unwrap();
+        let blob = repo.find_blob(staged.id).unwrap();
+        assert_eq!(blob.content(), original.replace("line 2\n", "line two\n").as_bytes());
+        assert_eq!(std::fs::read_to_string(fixture.dir.join("a.txt")).unwrap(), changed);
+    }
+
+    #[test]
+    fn test_apply_every_unstaged_unit_to_index() {
+        let (fixture, original) = fixture_repo("stage-all");
+        let repo = &fixture.repo;
+        let changed = original.replace("line 2\n", "line two\n").replace("line 19\n", "line nineteen\n");
+        fixture.write("a.txt", &changed);
+
+        let patches = unstaged_patches(repo, None).unwrap();
+        assert_eq!(patches[0].unit_count(), 2);
+
+        let mut index = repo.index().unwrap();
+        apply_to_index(repo, &mut index, &patches, |_, _| true).unwrap();
+        index.write().unwrap();
+
+        let staged = repo.index().unwrap().get_path(Path::new("a.txt"), 0).unwrap();
+        assert_eq!(repo.find_blob(staged.id).unwrap().content(), changed.as_bytes());
+        assert!(unstaged_patches(repo, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stage_and_unstage_file() {
+        let (fixture, original) = fixture_repo("restage");
+        let repo = &fixture.repo;
+        fixture.write("a.txt", &original.replace("line 5\n", "line five\n"));
+        fixture.write("b.txt", "new\n");
+
+        stage_file(repo, "a.txt").unwrap();
+        stage_file(repo, "b.txt").unwrap();
+        let (_, staged) = staged_patches(repo, None).unwrap();
+        assert_eq!(staged.len(), 2);
+
+        unstage_file(repo, "a.txt").unwrap();
+        unstage_file(repo, "b.txt").unwrap();
+        let (_, staged) = staged_patches(repo, None).unwrap();
+        assert!(staged.is_empty());
+        assert_eq!(unstaged_patches(repo, None).unwrap().len(), 1);
+
+        std::fs::remove_file(fixture.dir.join("a.txt")).unwrap();
+        stage_file(repo, "a.txt").unwrap();
+        let (_, staged) = staged_patches(repo, None).unwrap();
+        assert_eq!(staged[0].status, Delta::Deleted);
+    }
+
+    #[test]
+    fn test_apply_hunks_new_file() {
+        let add = Hunk::from_lines(0, 1, &[('+', "hello\n")]);
+        assert_eq!(apply_hunks(b"", &[&add]), b"hello\n");
+    }
+}
@@ -0,0 +1,161 @@
+//! In-progress merges: what is being merged and how conflicts were resolved.
+//!
+//! A merge commit has two parents, so the staged diff against HEAD alone does not say how
+//! conflicts were resolved. The conflicted files are therefore also diffed against the
+//! merged-in side, and git's own `Merge branch '…'` header from `MERGE_MSG` is kept.
+
+use anyhow::{Result, bail};
+use git2::{DiffOptions, Oid, Repository, RepositoryState, Tree};
+use std::fmt::Write as _;
+use std::fs;
+
+use super::diff::{format_diff, open_repo};
+use super::history::{CommitInfo, commits_between};
+use crate::config::CommitConfig;
+
+pub struct MergeState {
+    /// Git's subject line, e.g. `Merge branch 'feature' into main`.
+    pub header: String,
+    /// Commits being merged in that are not on HEAD yet, oldest first.
+    pub commits: Vec<CommitInfo>,
+    /// Files git reported as conflicted.
+    pub conflicts: Vec<String>,
+    /// The resolved conflicted files diffed against each side of the merge.
+    pub resolution: String,
+    /// Everything the merge brings in, relative to HEAD.
+    pub diff: String,
+}
+
+/// The merge in progress in the current repository, if any.
+pub fn get_merge_state(commit_config: Option<&CommitConfig>) -> Result<Option<MergeState>> {
+    merge_state(&open_repo()?, commit_config)
+}
+
+pub fn merge_state(repo: &Repository, commit_config: Option<&CommitConfig>) -> Result<Option<MergeState>> {
+    if repo.state() != RepositoryState::Merge {
+        return Ok(None);
+    }
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        bail!("The merge still has unresolved conflicts; resolve them and stage the files first");
+    }
+
+    let merge_heads: Vec<Oid> = fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| Oid::from_str(l.trim()).ok())
+        .collect();
+    let Some(&theirs) = merge_heads.first() else {
+        return Ok(None);
+    };
+
+    let merge_msg = fs::read_to_string(repo.path().join("MERGE_MSG")).unwrap_or_default();
+    let (header, conflicts) = parse_merge_msg(&merge_msg);
+    let header = header.unwrap_or_else(|| format!("Merge commit '{}'", &theirs.to_string()[..7]));
+
+    let head = repo.head()?.peel_to_commit()?;
+    let mut commits = Vec::new();
+    for merge_head in &merge_heads {
+        commits.extend(commits_between(repo, Some(head.id()), *merge_head)?);
+    }
+
+    let result = repo.find_tree(index.write_tree()?)?;
+    let head_tree = head.tree()?;
+    let diff = diff_trees(repo, &head_tree, &result, &[], commit_config)?;
+
+    let mut resolution = String::new();
+    if !conflicts.is_empty() {
+        let theirs_tree = repo.find_commit(theirs)?.tree()?;
+        for (side, tree) in [("HEAD (ours)", &head_tree), ("the merged branch (theirs)", &theirs_tree)] {
+            let _ = writeln!(resolution, "Resolved files compared with {side}:");
+            resolution.push_str(&diff_trees(repo, tree, &result, &conflicts, commit_config)?);
+            resolution.push('\n');
+        }
+    }
+
+    Ok(Some(MergeState { header, commits, conflicts, resolution, diff }))
+}
+
+/// Split a merge message into its subject line and the files listed under `Conflicts:`.
+/// Git writes the list commented out (`# Conflicts:` / `#\tpath`); older versions and
+/// libgit2 write it plain.
+pub fn parse_merge_msg(merge_msg: &str) -> (Option<String>, Vec<String>) {
+    let header = merge_msg.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#')).map(str::to_string);
+
+    let mut conflicts = Vec::new();
+    let mut in_conflicts = false;
+    for line in merge_msg.lines() {
+        let uncommented = line.strip_prefix('#').unwrap_or(line);
+        if uncommented.trim() == "Conflicts:" {
+            in_conflicts = true;
+        } else if in_conflicts {
+            match uncommented.strip_prefix('\t') {
+                Some(path) if !path.trim().is_empty() => conflicts.push(path.trim().to_string()),
+                _ if uncommented.trim().is_empty() && conflicts.is_empty() => {}
+                _ => in_conflicts = false,
+            }
+        }
+    }
+    (header, conflicts)
+}
+
+fn diff_trees(
+    repo: &Repository,
+    old: &Tree,
+    new: &Tree,
+    paths: &[String],
+    commit_config: Option<&CommitConfig>,
+) -> Result<String> {
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(3);
+    for path in paths {
+        diff_opts.pathspec(path);
+    }
+    let diff = repo.diff_tree_to_tree(Some(old), Some(new), Some(&mut diff_opts))?;
+    format_diff(diff, commit_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestRepo;
+
+    #[test]
+    fn test_parse_merge_msg() {
+        let (header, conflicts) = parse_merge_msg(
+            "Merge branch 'feature' into main\n\n# Conflicts:\n#\tsrc/a.rs\n#\tsrc/b.rs\n#\n# It looks like...\n",
+        );
+        assert_eq!(header.as_deref(), Some("Merge branch 'feature' into main"));
+        assert_eq!(conflicts, vec!["src/a.rs", "src/b.rs"]);
+
+        let (_, conflicts) = parse_merge_msg("Merge branch 'x'\n\nConflicts:\n\ta.txt\n");
+        assert_eq!(conflicts, vec!["a.txt"]);
+        assert!(parse_merge_msg("Merge branch 'x'\n").1.is_empty());
+    }
+
+    #[test]
+    fn test_merge_state_with_resolved_conflict() {
+        let fixture = TestRepo::new("merge");
+        let repo = &fixture.repo;
+        let base = fixture.commit_file("a.txt", "one\n", "init");
+        let theirs = fixture.commit_file("a.txt", "two\n", "theirs");
+        repo.branch("feature", &repo.find_commit(theirs).unwrap(), false).unwrap();
+        repo.reset(repo.find_commit(base).unwrap().as_object(), git2::ResetType::Hard, None).unwrap();
+        fixture.commit_file("a.txt", "three\n", "ours");
+
+        assert!(merge_state(repo, None).unwrap().is_none());
+
+        let annotated = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&annotated], None, None).unwrap();
+        assert!(merge_state(repo, None).is_err());
+
+        fixture.stage("a.txt", "two and three\n");
+
+        let state = merge_state(repo, None).unwrap().unwrap();
+        assert_eq!(state.conflicts, vec!["a.txt"]);
+        assert_eq!(state.commits.len(), 1);
+        assert!(state.resolution.contains("compared with HEAD (ours):\ndiff --git a/a.txt b/a.txt"));
+        assert!(state.resolution.contains("compared with the merged branch (theirs):\ndiff --git"));
+        assert!(state.diff.contains("two and three"));
+    }
+}
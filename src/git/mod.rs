@@ -1,5 +1,14 @@
+mod commit;
+pub mod conventional;
 mod diff;
+mod history;
+pub mod hunks;
+mod merge;
 mod repository;
+pub mod trailers;
 
+pub use commit::*;
 pub use diff::*;
+pub use history::*;
+pub use merge::*;
 pub use repository::*;
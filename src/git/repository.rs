@@ -2,13 +2,17 @@ use anyhow::Result;
 use std::io::Write;
 use std::process::{Command, Stdio};
 
-pub fn execute_commit_with_cli(message: &str) -> Result<()> {
+use super::diff::open_repo;
+
+/// `sign` forces signing on (`-S`) or off (`--no-gpg-sign`); `None` follows `commit.gpgsign`.
+/// `args` are passed to `git commit` as given.
+pub fn execute_commit_with_cli(message: &str, sign: Option<bool>, args: &[String]) -> Result<()> {
     println!("Committing changes...");
 
     let mut cmd = Command::new("git");
-    cmd.args(["commit", "--file", "-"]).stdin(Stdio::piped());
+    cmd.args(["commit", "--file", "-"]).args(args).stdin(Stdio::piped());
 
-    setup_gpg_signing(&mut cmd)?;
+    let signing = setup_gpg_signing(&mut cmd, sign)?;
 
     let mut child = cmd.spawn()?;
     if let Some(mut stdin) = child.stdin.take() {
@@ -20,19 +24,19 @@ pub fn execute_commit_with_cli(message: &str) -> Result<()> {
         println!("Commit successful!");
         show_commit_info()?;
     } else {
-        return Err(anyhow::anyhow!("Commit failed"));
+        return Err(commit_failed("Commit failed", signing));
     }
 
     Ok(())
 }
 
-pub fn execute_amend_with_cli(message: &str) -> Result<()> {
+pub fn execute_amend_with_cli(message: &str, sign: Option<bool>, args: &[String]) -> Result<()> {
     println!("Amending last commit...");
 
     let mut cmd = Command::new("git");
-    cmd.args(["commit", "--amend", "--file", "-"]).stdin(Stdio::piped());
+    cmd.args(["commit", "--amend", "--file", "-"]).args(args).stdin(Stdio::piped());
 
-    setup_gpg_signing(&mut cmd)?;
+    let signing = setup_gpg_signing(&mut cmd, sign)?;
 
     let mut child = cmd.spawn()?;
     if let Some(mut stdin) = child.stdin.take() {
@@ -44,50 +48,104 @@ pub fn execute_amend_with_cli(message: &str) -> Result<()> {
         println!("Commit amended successfully!");
         show_commit_info()?;
     } else {
-        return Err(anyhow::anyhow!("Amend failed"));
+        return Err(commit_failed("Amend failed", signing));
     }
 
     Ok(())
 }
 
-fn setup_gpg_signing(cmd: &mut Command) -> Result<()> {
-    if is_gpg_signing_enabled()? {
-        println!("GPG signing is enabled...");
-        if let Ok(tty) = Command::new("tty").output()
-            && tty.status.success()
-        {
-            let tty_path = String::from_utf8_lossy(&tty.stdout).trim().to_string();
-            cmd.env("GPG_TTY", tty_path);
-        }
+/// Create an annotated tag at HEAD; `git tag` signs it when `tag.gpgSign` is set.
+pub fn create_tag_with_cli(name: &str, message: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(["tag", "-a", name, "-m", message]);
+
+    setup_gpg_signing(&mut cmd, None)?;
+
+    if !cmd.status()?.success() {
+        return Err(anyhow::anyhow!("Failed to create tag {name}"));
     }
+    println!("Created tag {name}");
     Ok(())
 }
 
-pub fn is_gpg_signing_enabled() -> Result<bool> {
-    let output = Command::new("git").args(["config", "--get", "commit.gpgsign"]).output()?;
+/// Apply a signing override to `cmd` and make sure gpg can prompt for a passphrase.
+/// Returns whether the command will sign.
+fn setup_gpg_signing(cmd: &mut Command, sign: Option<bool>) -> Result<bool> {
+    match sign {
+        Some(true) => {
+            cmd.arg("-S");
+        }
+        Some(false) => {
+            cmd.arg("--no-gpg-sign");
+        }
+        None => {}
+    }
+    let signing = match sign {
+        Some(sign) => sign,
+        None => is_gpg_signing_enabled()?,
+    };
+    if signing {
+        println!("GPG signing is enabled...");
+        if let Some(tty) = gpg_tty() {
+            cmd.env("GPG_TTY", tty);
+        }
+    }
+    Ok(signing)
+}
 
-    if output.status.success() {
-        let value = String::from_utf8_lossy(&output.stdout);
-        Ok(value.trim() == "true")
+fn commit_failed(what: &str, signing: bool) -> anyhow::Error {
+    if signing {
+        anyhow::anyhow!(
+            "{what}. If git reported a signing error, check gpg.format and user.signingkey, \
+             or pass --no-gpg-sign to commit without signing"
+        )
     } else {
-        let output = Command::new("git").args(["config", "--global", "--get", "commit.gpgsign"]).output()?;
+        anyhow::anyhow!("{what}")
+    }
+}
+
+/// The terminal gpg should prompt for a passphrase on, if there is one.
+pub(crate) fn gpg_tty() -> Option<String> {
+    let output = Command::new("tty").stdin(Stdio::inherit()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether `commit.gpgsign` is set in the repository, global or system configuration.
+pub fn is_gpg_signing_enabled() -> Result<bool> {
+    Ok(open_repo()?.config()?.get_bool("commit.gpgsign").unwrap_or(false))
+}
 
-        if output.status.success() {
-            let value = String::from_utf8_lossy(&output.stdout);
-            Ok(value.trim() == "true")
-        } else {
-            Ok(false)
+/// The editor git would use: `GIT_EDITOR`, `core.editor`, `VISUAL`, `EDITOR`, then `vi`.
+pub fn git_editor() -> String {
+    if let Ok(editor) = std::env::var("GIT_EDITOR") {
+        return editor;
+    }
+    if let Ok(output) = Command::new("git").args(["config", "--get", "core.editor"]).output()
+        && output.status.success()
+    {
+        let editor = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !editor.is_empty() {
+            return editor;
         }
     }
+    std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string())
 }
 
-pub fn show_commit_info() -> Result<()> {
-    let output = Command::new("git").args(["log", "-1", "--oneline"]).output()?;
-
-    if output.status.success() {
-        let commit_info = String::from_utf8_lossy(&output.stdout);
-        println!("Latest commit: {}", commit_info.trim());
+/// Open `path` in the user's editor and wait for it to exit.
+/// The editor value is run through the shell, as git does, so it may contain arguments.
+pub fn edit_file(path: &std::path::Path) -> Result<()> {
+    let editor = git_editor();
+    let status = Command::new("sh").arg("-c").arg(format!("{editor} \"$@\"")).arg(&editor).arg(path).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Editor '{editor}' exited with {status}"));
     }
+    Ok(())
+}
 
+pub fn show_commit_info() -> Result<()> {
+    let repo = open_repo()?;
+    let commit = repo.head()?.peel_to_commit()?;
+    let summary = commit.summary().ok().flatten().unwrap_or_default();
+    println!("Latest commit: {} {summary}", &commit.id().to_string()[..7]);
     Ok(())
 }
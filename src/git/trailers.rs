@@ -0,0 +1,284 @@
+//! Commit message trailers such as `Signed-off-by:` and `Co-authored-by:`.
+//!
+//! Trailer blocks are found the way `git interpret-trailers` finds them: the last
+//! paragraph of the message, not counting the subject, in which every line is a trailer
+//! or a continuation line, or at least a quarter of the lines are trailers and one of them
+//! is a well-known or git-generated one. New trailers join that block, or start a new
+//! paragraph when there is none, and trailers already present are not added again.
+
+use anyhow::{Result, bail};
+use git2::{Repository, Sort};
+use std::fmt;
+
+/// Trailer keys that mark a mixed paragraph as a trailer block.
+const KNOWN_KEYS: &[&str] = &["Signed-off-by", "Co-authored-by", "Reviewed-by", "Acked-by", "Tested-by"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self { key: key.into(), value: value.into() }
+    }
+
+    /// Parse a `Key: value` line. Keys are letters, digits and `-`, as git requires.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (key, value) = line.split_once(':')?;
+        let key = key.trim_end();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return None;
+        }
+        Some(Self::new(key, value.trim()))
+    }
+
+    /// The same key (case-insensitively, like git) and value.
+    fn same_as(&self, other: &Trailer) -> bool {
+        self.key.eq_ignore_ascii_case(&other.key) && self.value == other.value
+    }
+}
+
+impl fmt::Display for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+
+impl std::str::FromStr for Trailer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Trailer::parse(s) {
+            Some(t) if !t.value.is_empty() => Ok(t),
+            _ => Err(format!("'{s}' is not a trailer; expected 'Key: value'")),
+        }
+    }
+}
+
+/// The trailers at the end of `message`, continuation lines folded into their trailer.
+pub fn parse(message: &str) -> Vec<Trailer> {
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let Some(start) = trailer_block_start(&lines) else {
+        return Vec::new();
+    };
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in &lines[start..] {
+        if line.starts_with([' ', '\t']) {
+            if let Some(last) = trailers.last_mut() {
+                last.value.push(' ');
+                last.value.push_str(line.trim());
+            }
+        } else if let Some(trailer) = Trailer::parse(line) {
+            trailers.push(trailer);
+        }
+    }
+    trailers
+}
+
+/// Append `trailers` to `message`, skipping any that the message (or an earlier entry of
+/// `trailers`) already has. The result ends with a newline.
+pub fn append(message: &str, trailers: &[Trailer]) -> String {
+    let message = message.trim_end();
+    let mut present = parse(message);
+    let mut new = Vec::new();
+    for trailer in trailers {
+        if !present.iter().any(|t| t.same_as(trailer)) {
+            present.push(trailer.clone());
+            new.push(trailer.to_string());
+        }
+    }
+    if new.is_empty() {
+        return format!("{message}\n");
+    }
+
+    let lines: Vec<&str> = message.lines().collect();
+    let separator = if trailer_block_start(&lines).is_some() || message.is_empty() { "\n" } else { "\n\n" };
+    format!("{message}{separator}{}\n", new.join("\n"))
+}
+
+/// Index of the first line of the trailer block, if the last paragraph is one.
+fn trailer_block_start(lines: &[&str]) -> Option<usize> {
+    let start = lines.iter().rposition(|l| l.trim().is_empty())? + 1;
+    // The subject paragraph is never a trailer block.
+    if start >= lines.len() || lines[..start].iter().all(|l| l.trim().is_empty()) {
+        return None;
+    }
+    let paragraph = &lines[start..];
+    if paragraph[0].starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let mut trailers = 0;
+    let mut others = 0;
+    let mut known = false;
+    for line in paragraph {
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        match Trailer::parse(line) {
+            Some(t) => {
+                trailers += 1;
+                known |= KNOWN_KEYS.iter().any(|k| k.eq_ignore_ascii_case(&t.key));
+            }
+            None if line.starts_with("(cherry picked from commit ") => {
+                trailers += 1;
+                known = true;
+            }
+            None => others += 1,
+        }
+    }
+    let is_block = trailers > 0 && (others == 0 || (known && trailers * 3 >= others));
+    is_block.then_some(start)
+}
+
+/// A `Name <email>` identity from history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+}
+
+impl fmt::Display for Author {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+/// Distinct authors of the most recent commits reachable from HEAD, newest first, with
+/// `.mailmap` applied. The committing user is left out.
+pub fn recent_authors(repo: &Repository, limit: usize) -> Result<Vec<Author>> {
+    let head = match repo.head() {
+        Ok(head) => head.peel_to_commit()?.id(),
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mailmap = repo.mailmap()?;
+    let me = repo.committer_from_env().ok().and_then(|s| mailmap.resolve_signature(&s).ok());
+    let me = me.and_then(|s| s.email().ok().map(str::to_lowercase));
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push(head)?;
+
+    let mut authors: Vec<Author> = Vec::new();
+    // Bounded so huge histories stay fast; recent collaborators show up early.
+    for oid in walk.take(2000) {
+        let commit = repo.find_commit(oid?)?;
+        let signature = commit.author_with_mailmap(&mailmap)?;
+        let (Ok(name), Ok(email)) = (signature.name(), signature.email()) else {
+            continue;
+        };
+        let email_lower = email.to_lowercase();
+        if me.as_deref() == Some(email_lower.as_str()) || authors.iter().any(|a| a.email.to_lowercase() == email_lower)
+        {
+            continue;
+        }
+        authors.push(Author { name: name.to_string(), email: email.to_string() });
+        if authors.len() >= limit {
+            break;
+        }
+    }
+    Ok(authors)
+}
+
+/// Pick the authors matching `query` (name or email, case-insensitive). A literal
+/// `Name <email>` is accepted as-is when nobody in history matches.
+pub fn match_authors(authors: &[Author], query: &str) -> Result<Vec<Author>> {
+    let needle = query.trim().to_lowercase();
+    let matches: Vec<Author> = authors
+        .iter()
+        .filter(|a| a.name.to_lowercase().contains(&needle) || a.email.to_lowercase().contains(&needle))
+        .cloned()
+        .collect();
+    if !matches.is_empty() {
+        return Ok(matches);
+    }
+    if let Some((name, email)) = query.trim().strip_suffix('>').and_then(|q| q.split_once('<'))
+        && !name.trim().is_empty()
+        && email.contains('@')
+    {
+        return Ok(vec![Author { name: name.trim().to_string(), email: email.trim().to_string() }]);
+    }
+    bail!("No recent author matches '{query}'; use 'Name <email>' to add someone new")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestRepo;
+
+    fn t(key: &str, value: &str) -> Trailer {
+        Trailer::new(key, value)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("fix: typo").is_empty());
+        assert!(parse("fix: typo\n\nCloses: #1 is not a trailer because\nthis line is prose").is_empty());
+        assert_eq!(
+            parse("feat: x\n\nBody.\n\nRefs: #12\nSigned-off-by: A <a@example.com>\n"),
+            vec![t("Refs", "#12"), t("Signed-off-by", "A <a@example.com>")]
+        );
+        assert_eq!(parse("feat: x\n\nNote: long\n  wrapped value"), vec![t("Note", "long wrapped value")]);
+        // A mostly-prose paragraph still counts when it has a well-known trailer.
+        assert_eq!(
+            parse("feat: x\n\nsee the issue\nSigned-off-by: A <a@example.com>"),
+            vec![t("Signed-off-by", "A <a@example.com>")]
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let signoff = [t("Signed-off-by", "A <a@example.com>")];
+        assert_eq!(append("feat: add x\n", &signoff), "feat: add x\n\nSigned-off-by: A <a@example.com>\n");
+        assert_eq!(
+            append("feat: add x\n\nRefs: #12", &signoff),
+            "feat: add x\n\nRefs: #12\nSigned-off-by: A <a@example.com>\n"
+        );
+        assert_eq!(
+            append("feat: add x\n\nsigned-off-by: A <a@example.com>\n", &[signoff[0].clone(), signoff[0].clone()]),
+            "feat: add x\n\nsigned-off-by: A <a@example.com>\n"
+        );
+        assert_eq!(
+            append("feat: add x\n\nExplains why: it was slow.\nMore text", &signoff),
+            "feat: add x\n\nExplains why: it was slow.\nMore text\n\nSigned-off-by: A <a@example.com>\n"
+        );
+        assert_eq!(append("Refs: #1", &signoff), "Refs: #1\n\nSigned-off-by: A <a@example.com>\n");
+    }
+
+    #[test]
+    fn test_trailer_from_str() {
+        assert_eq!("Reviewed-by: B <b@example.com>".parse::<Trailer>(), Ok(t("Reviewed-by", "B <b@example.com>")));
+        assert!("Not a trailer".parse::<Trailer>().is_err());
+        assert!("Bad key: x".parse::<Trailer>().is_err());
+        assert!("Empty:".parse::<Trailer>().is_err());
+    }
+
+    #[test]
+    fn test_recent_authors_uses_mailmap() {
+        let fixture = TestRepo::new("trailers");
+        let mut config = fixture.repo.config().unwrap();
+        config.set_str("user.name", "Me").unwrap();
+        config.set_str("user.email", "me@example.com").unwrap();
+
+        let commit = |name: &str, email: &str, time: i64| {
+            fixture.commit_as(&git2::Signature::new(name, email, &git2::Time::new(time, 0)).unwrap(), "c");
+        };
+        commit("Ann", "ann@old.example.com", 100);
+        commit("Bob", "bob@example.com", 200);
+        commit("Ann Lee", "ann@example.com", 300);
+        commit("Me", "me@example.com", 400);
+        fixture.write(".mailmap", "Ann Lee <ann@example.com> <ann@old.example.com>\n");
+
+        let repo = &fixture.repo;
+        let authors = recent_authors(repo, 10).unwrap();
+        let names: Vec<String> = authors.iter().map(ToString::to_string).collect();
+        assert_eq!(names, vec!["Ann Lee <ann@example.com>", "Bob <bob@example.com>"]);
+        assert_eq!(recent_authors(repo, 1).unwrap().len(), 1);
+
+        assert_eq!(match_authors(&authors, "bob").unwrap()[0].name, "Bob");
+        assert_eq!(match_authors(&authors, "New <new@example.com>").unwrap()[0].email, "new@example.com");
+        assert!(match_authors(&authors, "nobody").is_err());
+    }
+}
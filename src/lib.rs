@@ -2,4 +2,6 @@ pub mod ai;
 pub mod commands;
 pub mod config;
 pub mod git;
+#[cfg(test)]
+pub(crate) mod test_support;
 // test
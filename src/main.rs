@@ -1,12 +1,35 @@
 #![warn(clippy::style, clippy::complexity, clippy::perf, clippy::correctness)]
 
-use ai_commit::commands::{amend, commit, config, install, uninstall};
+use ai_commit::ai::cache::CachePolicy;
+use ai_commit::ai::language;
+use ai_commit::commands::output::{ExitStatus, OutputFormat};
+use ai_commit::commands::{
+    amend, changelog, commit, config, history, install, merge_message, pr, review, reword, serve, split,
+    squash_message, stage, stats, tui, uninstall, version_bump,
+};
+use ai_commit::config::Severity;
+use ai_commit::git::trailers::Trailer;
 use anyhow::Result;
 use clap::{Arg, Command};
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     env_logger::init();
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let status = ExitStatus::of(&e);
+            // No changes and cancelled commits were already reported by the command
+            if !status.is_quiet() {
+                eprintln!("Error: {e:?}");
+            }
+            ExitCode::from(status.code())
+        }
+    }
+}
+
+async fn run() -> Result<()> {
     let matches = Command::new("ai-commit")
         .version(env!("CARGO_PKG_VERSION"))
         .about("AI-assisted Git commit message generator (defaults to 'commit' if no subcommand)")
@@ -18,8 +41,8 @@ async fn main() -> Result<()> {
                 .short('l')
                 .long("language")
                 .value_name("LANGUAGE")
-                .value_parser(["en", "zh"])
-                .help("Language for commit messages (en/zh, implies 'commit' command)"),
+                .value_parser(language::parse_language_code)
+                .help("Language code for commit messages, e.g. en, zh, ja, de (implies 'commit' command)"),
         )
         .arg(
             Arg::new("keywords")
@@ -29,7 +52,14 @@ async fn main() -> Result<()> {
                 .help("Keywords or context to guide AI commit message generation (implies 'commit' command)")
                 .global(false),
         )
-        .subcommand(Command::new("install").about("Install git hooks for AI commit assistance"))
+        .subcommand(
+            Command::new("install").about("Install git hooks for AI commit assistance").arg(
+                Arg::new("review")
+                    .long("review")
+                    .help("Also install a pre-commit hook that runs `ai-commit review --hook`")
+                    .action(clap::ArgAction::SetTrue),
+            ),
+        )
         .subcommand(Command::new("uninstall").about("Remove AI commit hooks"))
         .subcommand(
             Command::new("commit")
@@ -39,8 +69,8 @@ async fn main() -> Result<()> {
                         .short('l')
                         .long("language")
                         .value_name("LANGUAGE")
-                        .value_parser(["en", "zh"])
-                        .help("Language for commit messages (en/zh)"),
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for commit messages, e.g. en, zh, ja, de"),
                 )
                 .arg(
                     Arg::new("context-limit")
@@ -55,6 +85,122 @@ async fn main() -> Result<()> {
                         .help("Show generated message without committing")
                         .action(clap::ArgAction::SetTrue),
                 )
+                .arg(
+                    Arg::new("commit")
+                        .long("commit")
+                        .help("Commit even when `dry_run_by_default` is set")
+                        .conflicts_with("dry-run")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .visible_alias("no-confirm")
+                        .help("Commit without asking for confirmation, overriding `auto_confirm`")
+                        .conflicts_with("confirm")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("confirm")
+                        .long("confirm")
+                        .help("Ask for confirmation even when `auto_confirm` is set")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("gpg-sign")
+                        .short('S')
+                        .long("gpg-sign")
+                        .help("Sign the commit, overriding `gpg_sign` and `commit.gpgsign`")
+                        .conflicts_with("no-gpg-sign")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("no-gpg-sign")
+                        .long("no-gpg-sign")
+                        .help("Do not sign the commit, overriding `gpg_sign` and `commit.gpgsign`")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("all")
+                        .short('a')
+                        .long("all")
+                        .help("Commit all modified and deleted tracked files, and describe those changes")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("signoff")
+                        .short('s')
+                        .long("signoff")
+                        .help("Add a Signed-off-by trailer for the committer")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("no-verify")
+                        .short('n')
+                        .long("no-verify")
+                        .help("Skip the pre-commit and commit-msg hooks")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("trailer")
+                        .long("trailer")
+                        .value_name("KEY: VALUE")
+                        .help("Add a trailer such as 'Reviewed-by: Name <email>' (repeatable)")
+                        .value_parser(|s: &str| s.parse::<Trailer>())
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("co-author")
+                        .long("co-author")
+                        .value_name("NAME")
+                        .help("Add Co-authored-by for a recent author matching NAME, or choose from a list")
+                        .num_args(0..=1)
+                        .default_missing_value("")
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("git-args")
+                        .value_name("GIT_ARGS")
+                        .help("Options passed through to `git commit` after `--`, e.g. -- --author='A <a@example.com>'")
+                        .num_args(1..)
+                        .last(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("no-cache")
+                        .long("no-cache")
+                        .help("Neither reuse nor store a cached message for these changes")
+                        .conflicts_with("regenerate")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("regenerate")
+                        .long("regenerate")
+                        .help("Generate a new message even if one is cached for these changes")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("reuse")
+                        .long("reuse")
+                        .value_name("last|ID")
+                        .help("Commit with a message from `ai-commit history` instead of generating one")
+                        .conflicts_with_all(["keywords", "body", "no-cache", "regenerate"]),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .value_parser(clap::value_parser!(OutputFormat))
+                        .default_value("text")
+                        .help("Output format: text, or json for a machine-readable preview (with --dry-run)"),
+                )
+                .arg(
+                    Arg::new("body")
+                        .long("body")
+                        .help("Generate a subject plus a wrapped body explaining what changed and why")
+                        .action(clap::ArgAction::SetTrue),
+                )
                 .arg(
                     Arg::new("keywords")
                         .short('k')
@@ -71,8 +217,8 @@ async fn main() -> Result<()> {
                         .short('l')
                         .long("language")
                         .value_name("LANGUAGE")
-                        .value_parser(["en", "zh"])
-                        .help("Language for commit messages (en/zh)"),
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for commit messages, e.g. en, zh, ja, de"),
                 )
                 .arg(
                     Arg::new("context-limit")
@@ -87,6 +233,48 @@ async fn main() -> Result<()> {
                         .help("Show generated message without amending")
                         .action(clap::ArgAction::SetTrue),
                 )
+                .arg(
+                    Arg::new("commit")
+                        .long("commit")
+                        .help("Commit even when `dry_run_by_default` is set")
+                        .conflicts_with("dry-run")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .visible_alias("no-confirm")
+                        .help("Commit without asking for confirmation, overriding `auto_confirm`")
+                        .conflicts_with("confirm")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("confirm")
+                        .long("confirm")
+                        .help("Ask for confirmation even when `auto_confirm` is set")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("gpg-sign")
+                        .short('S')
+                        .long("gpg-sign")
+                        .help("Sign the commit, overriding `gpg_sign` and `commit.gpgsign`")
+                        .conflicts_with("no-gpg-sign")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("no-gpg-sign")
+                        .long("no-gpg-sign")
+                        .help("Do not sign the commit, overriding `gpg_sign` and `commit.gpgsign`")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("body")
+                        .long("body")
+                        .help("Generate a subject plus a wrapped body explaining what changed and why")
+                        .action(clap::ArgAction::SetTrue),
+                )
                 .arg(
                     Arg::new("keywords")
                         .short('k')
@@ -95,6 +283,387 @@ async fn main() -> Result<()> {
                         .help("Keywords or context to guide AI commit message generation"),
                 ),
         )
+        .subcommand(
+            Command::new("split")
+                .about("Split staged changes into several logical commits using AI")
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for commit messages, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show the proposed commits without creating them")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("stage")
+                .about("Stage only the unstaged hunks that match a description, using AI")
+                .arg(
+                    Arg::new("description")
+                        .value_name("DESCRIPTION")
+                        .required(true)
+                        .help("Description of the change to stage, e.g. \"the retry fix\""),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show which hunks match without staging them")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("pr")
+                .about("Generate a pull request title and description for the current branch")
+                .arg(
+                    Arg::new("base")
+                        .short('b')
+                        .long("base")
+                        .value_name("BRANCH")
+                        .help("Branch to compare with (default: [pr] base_branch, then origin/HEAD, main, master)"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .help("Write the description to FILE (for gh pr create --body-file)"),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the description, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("merge-message")
+                .about("Generate a message for the merge in progress, keeping git's merge header")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .help("Write the message to FILE, keeping its `#` comment lines (for prepare-commit-msg)"),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the message, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("squash-message")
+                .about("Generate a single message for squashing a branch or a `git merge --squash`")
+                .arg(Arg::new("base").value_name("BASE").help(
+                    "Squash the commits since BASE (default: the staged `git merge --squash` and its SQUASH_MSG)",
+                ))
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .help("Write the message to FILE, keeping its `#` comment lines (for prepare-commit-msg)"),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the message, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("reword")
+                .about("Regenerate the messages of a range of commits up to HEAD")
+                .arg(
+                    Arg::new("range")
+                        .value_name("RANGE")
+                        .required(true)
+                        .help("Commits to reword, e.g. HEAD~3..HEAD or HEAD~3"),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the messages, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show the proposed messages without rewriting history")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("gpg-sign")
+                        .short('S')
+                        .long("gpg-sign")
+                        .help("Sign the new commits, overriding `gpg_sign` and `commit.gpgsign`")
+                        .conflicts_with("no-gpg-sign")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("no-gpg-sign")
+                        .long("no-gpg-sign")
+                        .help("Do not sign the new commits, overriding `gpg_sign` and `commit.gpgsign`")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("review")
+                .about("Review staged changes with the AI service before committing")
+                .arg(
+                    Arg::new("fail-on")
+                        .long("fail-on")
+                        .value_name("SEVERITY")
+                        .value_parser(clap::value_parser!(Severity))
+                        .help("Exit with an error when a finding is at least this severe (low, medium, high)"),
+                )
+                .arg(
+                    Arg::new("hook")
+                        .long("hook")
+                        .help("Pre-commit hook mode: block on [review] fail_on, never fail on AI errors")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the findings, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("changelog")
+                .about("Generate Keep a Changelog release notes from a commit range")
+                .arg(
+                    Arg::new("range")
+                        .value_name("RANGE")
+                        .help("Commit range such as v0.1.5..HEAD (default: latest tag..HEAD)"),
+                )
+                .arg(
+                    Arg::new("release")
+                        .long("release")
+                        .value_name("VERSION")
+                        .help("Version for the section heading (default: [Unreleased])"),
+                )
+                .arg(
+                    Arg::new("ai")
+                        .long("ai")
+                        .help("Rewrite the entries as user-facing release notes using the AI service")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("write")
+                        .short('w')
+                        .long("write")
+                        .help("Update the changelog file in place")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("FILE")
+                        .default_value("CHANGELOG.md")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .help("Changelog file to update with --write"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Include docs, test, chore, ci, build and style commits")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for --ai release notes, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("version-bump")
+                .about("Suggest the next semantic version from commits since the last tag")
+                .arg(
+                    Arg::new("write")
+                        .short('w')
+                        .long("write")
+                        .help("Update the version in Cargo.toml and package.json")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .help("Create an annotated tag (with --write, commit the version change first)")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("no-ai")
+                        .long("no-ai")
+                        .help("Treat non-conventional commits as patches instead of asking the AI service")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve JSON-RPC requests for editor integrations")
+                .arg(
+                    Arg::new("stdio")
+                        .long("stdio")
+                        .help("Talk JSON-RPC over stdin and stdout, one message per line")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Listen for JSON-RPC connections on 127.0.0.1:PORT")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .group(clap::ArgGroup::new("transport").args(["stdio", "port"]).required(true)),
+        )
+        .subcommand(
+            Command::new("tui")
+                .about("Review changed files, diffs and the generated message in a terminal UI")
+                .arg(
+                    Arg::new("language")
+                        .short('l')
+                        .long("language")
+                        .value_name("LANGUAGE")
+                        .value_parser(language::parse_language_code)
+                        .help("Language code for the commit message, e.g. en, zh, ja, de"),
+                )
+                .arg(
+                    Arg::new("context-limit")
+                        .long("context-limit")
+                        .value_name("CHARS")
+                        .help("Maximum characters to send to AI (default: from config)")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List generated messages, committed or not, or show one in full")
+                .arg(
+                    Arg::new("id")
+                        .value_name("ID")
+                        .help("Show this message in full")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Include messages generated in other repositories")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .short('n')
+                        .long("limit")
+                        .value_name("N")
+                        .default_value("20")
+                        .help("How many of the newest messages to list")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Show token usage and cost per day or month, repository and model")
+                .arg(
+                    Arg::new("monthly")
+                        .long("monthly")
+                        .help("Group by month instead of by day")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("repo")
+                        .long("repo")
+                        .help("Only count usage in the current repository")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
         .subcommand(
             Command::new("config")
                 .about("Manage configuration")
@@ -105,21 +674,126 @@ async fn main() -> Result<()> {
         .get_matches();
 
     match matches.subcommand() {
-        Some(("install", _)) => install::install_hook(),
+        Some(("install", sub_matches)) => install::install_hook(sub_matches.get_flag("review")),
         Some(("uninstall", _)) => uninstall::uninstall_hook(),
         Some(("amend", sub_matches)) => {
             let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
             let keywords = sub_matches.get_one::<String>("keywords").map(|s| s.as_str());
-            let dry_run = sub_matches.get_flag("dry-run");
+            let mode = run_mode(sub_matches);
             let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
-            amend::handle_amend(language, keywords, dry_run, context_limit).await
+            let body = sub_matches.get_flag("body");
+            let sign = sign_override(sub_matches);
+            amend::handle_amend(language, keywords, mode, context_limit, body, sign).await
         }
         Some(("commit", sub_matches)) => {
+            let git = commit::GitCommitArgs {
+                all: sub_matches.get_flag("all"),
+                signoff: sub_matches.get_flag("signoff"),
+                no_verify: sub_matches.get_flag("no-verify"),
+                sign: sign_override(sub_matches),
+                trailers: sub_matches.get_many::<Trailer>("trailer").into_iter().flatten().cloned().collect(),
+                co_authors: sub_matches.get_many::<String>("co-author").into_iter().flatten().cloned().collect(),
+                passthrough: sub_matches.get_many::<String>("git-args").into_iter().flatten().cloned().collect(),
+            };
+            commit::handle_commit(&commit::CommitArgs {
+                language: sub_matches.get_one::<String>("language").cloned(),
+                keywords: sub_matches.get_one::<String>("keywords").cloned(),
+                model: None,
+                mode: run_mode(sub_matches),
+                context_limit: sub_matches.get_one::<usize>("context-limit").copied(),
+                body: sub_matches.get_flag("body"),
+                git,
+                format: sub_matches.get_one::<OutputFormat>("format").copied().unwrap_or_default(),
+                cache: cache_policy(sub_matches),
+                reuse: sub_matches.get_one::<String>("reuse").cloned(),
+            })
+            .await
+        }
+        Some(("split", sub_matches)) => {
             let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
-            let keywords = sub_matches.get_one::<String>("keywords").map(|s| s.as_str());
             let dry_run = sub_matches.get_flag("dry-run");
             let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
-            commit::handle_commit(language, keywords, dry_run, context_limit).await
+            split::handle_split(language, dry_run, context_limit).await
+        }
+        Some(("stage", sub_matches)) => {
+            let description = sub_matches.get_one::<String>("description").map(|s| s.as_str()).unwrap_or_default();
+            let dry_run = sub_matches.get_flag("dry-run");
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            stage::handle_stage(description, dry_run, context_limit).await
+        }
+        Some(("pr", sub_matches)) => {
+            let base = sub_matches.get_one::<String>("base").map(|s| s.as_str());
+            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
+            let output = sub_matches.get_one::<std::path::PathBuf>("output").map(|p| p.as_path());
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            pr::handle_pr(base, language, output, context_limit).await
+        }
+        Some(("merge-message", sub_matches)) => {
+            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
+            let output = sub_matches.get_one::<std::path::PathBuf>("output").map(|p| p.as_path());
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            merge_message::handle_merge_message(language, output, context_limit).await
+        }
+        Some(("squash-message", sub_matches)) => {
+            let base = sub_matches.get_one::<String>("base").map(|s| s.as_str());
+            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
+            let output = sub_matches.get_one::<std::path::PathBuf>("output").map(|p| p.as_path());
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            squash_message::handle_squash_message(base, language, output, context_limit).await
+        }
+        Some(("reword", sub_matches)) => {
+            let range = sub_matches.get_one::<String>("range").map(|s| s.as_str()).unwrap_or_default();
+            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
+            let dry_run = sub_matches.get_flag("dry-run");
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            reword::handle_reword(range, language, dry_run, sign_override(sub_matches), context_limit).await
+        }
+        Some(("review", sub_matches)) => {
+            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
+            let fail_on = sub_matches.get_one::<Severity>("fail-on").copied();
+            let hook = sub_matches.get_flag("hook");
+            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
+            review::handle_review(language, fail_on, hook, context_limit).await
+        }
+        Some(("changelog", sub_matches)) => {
+            changelog::handle_changelog(changelog::ChangelogOptions {
+                range: sub_matches.get_one::<String>("range").map(|s| s.as_str()),
+                version: sub_matches.get_one::<String>("release").map(|s| s.as_str()),
+                ai: sub_matches.get_flag("ai"),
+                write: sub_matches.get_flag("write"),
+                file: sub_matches.get_one::<std::path::PathBuf>("file").cloned().unwrap_or_default(),
+                all: sub_matches.get_flag("all"),
+                language: sub_matches.get_one::<String>("language").map(|s| s.as_str()),
+                context_limit: sub_matches.get_one::<usize>("context-limit").copied(),
+            })
+            .await
+        }
+        Some(("version-bump", sub_matches)) => {
+            version_bump::handle_version_bump(version_bump::VersionBumpOptions {
+                write: sub_matches.get_flag("write"),
+                tag: sub_matches.get_flag("tag"),
+                no_ai: sub_matches.get_flag("no-ai"),
+                context_limit: sub_matches.get_one::<usize>("context-limit").copied(),
+            })
+            .await
+        }
+        Some(("serve", sub_matches)) => {
+            serve::handle_serve(sub_matches.get_flag("stdio"), sub_matches.get_one::<u16>("port").copied()).await
+        }
+        Some(("tui", sub_matches)) => {
+            tui::handle_tui(
+                sub_matches.get_one::<String>("language").map(|s| s.as_str()),
+                sub_matches.get_one::<usize>("context-limit").copied(),
+            )
+            .await
+        }
+        Some(("history", sub_matches)) => history::handle_history(
+            sub_matches.get_one::<u64>("id").copied(),
+            sub_matches.get_flag("all"),
+            sub_matches.get_one::<usize>("limit").copied().unwrap_or(20),
+        ),
+        Some(("stats", sub_matches)) => {
+            stats::handle_stats(sub_matches.get_flag("monthly"), sub_matches.get_flag("repo"))
         }
         Some(("config", sub_matches)) => match sub_matches.subcommand() {
             Some(("show", _)) => config::show_config(),
@@ -128,16 +802,59 @@ async fn main() -> Result<()> {
             _ => config::show_config(),
         },
         Some((_, sub_matches)) => {
-            let language = sub_matches.get_one::<String>("language").map(|s| s.as_str());
-            let keywords = sub_matches.get_one::<String>("keywords").map(|s| s.as_str());
-            let dry_run = sub_matches.get_flag("dry-run");
-            let context_limit = sub_matches.get_one::<usize>("context-limit").copied();
-            commit::handle_commit(language, keywords, dry_run, context_limit).await
+            commit::handle_commit(&commit::CommitArgs {
+                language: sub_matches.get_one::<String>("language").cloned(),
+                keywords: sub_matches.get_one::<String>("keywords").cloned(),
+                mode: commit::RunMode { dry_run: sub_matches.get_flag("dry-run").then_some(true), confirm: None },
+                context_limit: sub_matches.get_one::<usize>("context-limit").copied(),
+                body: sub_matches.get_flag("body"),
+                ..Default::default()
+            })
+            .await
         }
         _ => {
-            let language = matches.get_one::<String>("language").map(|s| s.as_str());
-            let keywords = matches.get_one::<String>("keywords").map(|s| s.as_str());
-            commit::handle_commit(language, keywords, false, None).await
+            commit::handle_commit(&commit::CommitArgs {
+                language: matches.get_one::<String>("language").cloned(),
+                keywords: matches.get_one::<String>("keywords").cloned(),
+                ..Default::default()
+            })
+            .await
         }
     }
 }
+
+/// `--no-cache` / `--regenerate`; the cache is used otherwise.
+fn cache_policy(matches: &clap::ArgMatches) -> CachePolicy {
+    if matches.get_flag("no-cache") {
+        CachePolicy::Off
+    } else if matches.get_flag("regenerate") {
+        CachePolicy::Refresh
+    } else {
+        CachePolicy::Use
+    }
+}
+
+/// `-S` / `--no-gpg-sign`; `None` leaves signing to the config.
+fn sign_override(matches: &clap::ArgMatches) -> Option<bool> {
+    if matches.get_flag("gpg-sign") {
+        Some(true)
+    } else if matches.get_flag("no-gpg-sign") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// `--dry-run` / `--commit` and `--yes` / `--confirm`; unset flags leave it to the config.
+fn run_mode(matches: &clap::ArgMatches) -> commit::RunMode {
+    let flag = |on: &str, off: &str| {
+        if matches.get_flag(on) {
+            Some(true)
+        } else if matches.get_flag(off) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    commit::RunMode { dry_run: flag("dry-run", "commit"), confirm: flag("confirm", "yes") }
+}
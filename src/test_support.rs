@@ -0,0 +1,103 @@
+//! Scratch directories and repositories for unit tests. Both are removed when dropped, so a
+//! failing test does not leave them behind.
+
+use git2::{Oid, Repository, Signature};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// An empty directory under the system temp dir, unique to this process and call.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("ai-commit-{name}-{}-{n}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub(crate) fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A fresh repository whose identity is `Test <test@example.com>`, with signing off.
+pub(crate) struct TestRepo {
+    // Declared first so the repository is closed before its directory is removed
+    pub repo: Repository,
+    pub dir: TempDir,
+}
+
+impl TestRepo {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = TempDir::new(name);
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_bool("commit.gpgsign", false).unwrap();
+        Self { repo, dir }
+    }
+
+    /// Write `file` in the working tree without staging it.
+    pub(crate) fn write(&self, file: &str, content: &str) {
+        let path = self.dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Write `file` and add it to the index.
+    pub(crate) fn stage(&self, file: &str, content: &str) {
+        self.write(file, content);
+        self.add(file);
+    }
+
+    /// Add `file` as it is in the working tree to the index.
+    pub(crate) fn add(&self, file: &str) {
+        let mut index = self.repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Commit the index on top of HEAD as `Test`, without hooks.
+    pub(crate) fn commit(&self, message: &str) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        self.commit_as(&signature, message)
+    }
+
+    /// Commit the index on top of HEAD with `signature` as author and committer.
+    pub(crate) fn commit_as(&self, signature: &Signature, message: &str) -> Oid {
+        let tree = self.repo.find_tree(self.repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let parent = self.repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo.commit(Some("HEAD"), signature, signature, message, &tree, &parents).unwrap()
+    }
+
+    /// Stage `file` with `content` and commit it.
+    pub(crate) fn commit_file(&self, file: &str, content: &str, message: &str) -> Oid {
+        self.stage(file, content);
+        self.commit(message)
+    }
+}
+
+/// Write an executable shell script, for hooks and stand-in signing programs.
+#[cfg(unix)]
+pub(crate) fn write_script(path: &Path, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
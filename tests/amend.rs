@@ -0,0 +1,40 @@
+//! `ai-commit amend` end to end, against the mock AI service.
+
+mod support;
+
+use support::{Fixture, MockServer, Reply, describe};
+
+#[test]
+fn test_amend_rewrites_head_message() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting and farewell"));
+    let fixture = Fixture::new("amend", &server);
+    fixture.stage("hello.txt", "hello\n");
+    fixture.commit("feat: add greeting");
+    fixture.stage("bye.txt", "bye\n");
+
+    let output = fixture.run(&["amend", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting and farewell");
+    assert_eq!(fixture.commit_count(), 2);
+    assert!(!fixture.has_staged_changes());
+
+    // The model sees the whole amended commit and the message it replaces
+    let prompt = server.last_prompt();
+    assert!(prompt.contains("+hello") && prompt.contains("+bye"), "{prompt}");
+    assert!(prompt.contains("feat: add greeting"), "{prompt}");
+}
+
+#[test]
+fn test_cancelled_amend_leaves_head() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: something else"));
+    let fixture = Fixture::new("amend-cancel", &server);
+    fixture.stage("hello.txt", "hello\n");
+    fixture.commit("feat: add greeting");
+
+    let output = fixture.run_with_input(&["amend"], "n\n");
+    assert_eq!(output.status.code(), Some(6), "{}", describe(&output));
+    assert_eq!(fixture.head_message(), "feat: add greeting");
+    assert_eq!(fixture.history()[0]["accepted"], false);
+}
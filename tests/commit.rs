@@ -0,0 +1,148 @@
+//! `ai-commit commit` end to end, against the mock AI service.
+
+mod support;
+
+use support::{Fixture, MockServer, Reply, describe};
+
+#[test]
+fn test_commit_with_generated_message() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting\n\nSay hello to users."));
+    let fixture = Fixture::new("commit", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting\n\nSay hello to users.");
+    assert_eq!(fixture.commit_count(), 2);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["model"], "mock-model");
+    assert!(server.last_prompt().contains("+hello"), "{}", server.last_prompt());
+
+    let history = fixture.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["accepted"], true);
+}
+
+#[test]
+fn test_dry_run_json_preview() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("json", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit", "--dry-run", "--format", "json"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "ok");
+    assert_eq!(report["message"], "feat: add greeting");
+    assert_eq!(report["model"], "mock-model");
+    assert_eq!(report["diff_source"], "staged");
+    assert_eq!(report["diff_stats"]["files_changed"], 1);
+    assert_eq!(report["usage"]["prompt_tokens"], 50);
+    assert_eq!(fixture.commit_count(), 1);
+}
+
+#[test]
+fn test_api_errors_exit_with_ai_error() {
+    let server = MockServer::start();
+    server.reply(Reply::error(503, "model overloaded"));
+    server.reply(Reply::message("feat: add gree").finish_reason("length"));
+    let fixture = Fixture::new("api-error", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert_eq!(output.status.code(), Some(5), "{}", describe(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("model overloaded"), "{}", describe(&output));
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert_eq!(output.status.code(), Some(5), "{}", describe(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("truncated"), "{}", describe(&output));
+
+    assert_eq!(fixture.commit_count(), 1);
+    assert!(fixture.has_staged_changes());
+}
+
+#[test]
+fn test_auto_confirm_from_config() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("auto-confirm", &server);
+    fixture.config("[commit]\nauto_confirm = true\n");
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting");
+}
+
+#[test]
+fn test_json_preview_never_asks_for_co_authors() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("co-author-json", &server);
+    fixture.stage("one.txt", "one\n");
+    fixture.commit_as("feat: one", "Ann Lee", "ann@example.com");
+    fixture.stage("two.txt", "two\n");
+    fixture.commit_as("feat: two", "Anna Park", "anna@example.com");
+    fixture.stage("hello.txt", "hello\n");
+
+    for query in ["ann", ""] {
+        let output = fixture.run(&["commit", "--dry-run", "--format", "json", "--co-author", query]);
+        assert_eq!(output.status.code(), Some(2), "{}", describe(&output));
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(report["error"].as_str().unwrap().contains("co-author"), "{report}");
+    }
+    assert!(server.requests().is_empty());
+
+    let output = fixture.run(&["commit", "--dry-run", "--format", "json", "--co-author", "anna"]);
+    assert!(output.status.success(), "{}", describe(&output));
+}
+
+#[test]
+fn test_nothing_to_commit() {
+    let server = MockServer::start();
+    let fixture = Fixture::new("no-changes", &server);
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert_eq!(output.status.code(), Some(3), "{}", describe(&output));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn test_preview_then_commit_asks_once() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("cache", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let preview = fixture.run(&["commit", "--dry-run"]);
+    assert!(preview.status.success(), "{}", describe(&preview));
+    assert_eq!(fixture.commit_count(), 1);
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting");
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_cancelled_message_can_be_reused() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("reuse", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run_with_input(&["commit"], "n\n");
+    assert_eq!(output.status.code(), Some(6), "{}", describe(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--reuse last"), "{}", describe(&output));
+    assert_eq!(fixture.commit_count(), 1);
+
+    let output = fixture.run(&["commit", "--reuse", "last", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting");
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(fixture.history()[0]["accepted"], true);
+}
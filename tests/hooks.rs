@@ -0,0 +1,89 @@
+//! Commit hooks around `ai-commit commit`, and the hooks ai-commit installs run by `git`.
+#![cfg(unix)]
+
+mod support;
+
+use support::{Fixture, MockServer, Reply, describe};
+
+#[test]
+fn test_commit_msg_hook_edits_the_message() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("hook-commit-msg", &server);
+    fixture.hook("commit-msg", "printf '\\nReviewed-by: hook\\n' >> \"$1\"");
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting\n\nReviewed-by: hook");
+}
+
+#[test]
+fn test_failing_hook_keeps_the_message() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: add greeting"));
+    let fixture = Fixture::new("hook-fail", &server);
+    fixture.hook("pre-commit", "echo 'lint failed' >&2; exit 1");
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.run(&["commit", "--yes"]);
+    assert!(!output.status.success(), "{}", describe(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pre-commit hook failed"), "{}", describe(&output));
+    assert_eq!(fixture.commit_count(), 1);
+    assert_eq!(fixture.history()[0]["accepted"], false);
+
+    // Once the hook is fixed, the kept message is used without another request
+    fixture.hook("pre-commit", "exit 0");
+    let output = fixture.run(&["commit", "--reuse", "last", "--yes"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.head_message().trim_end(), "feat: add greeting");
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_review_hook_blocks_high_findings() {
+    let server = MockServer::start();
+    server.reply(Reply::message(
+        r#"{"findings": [{"file": "hello.txt", "line": 1, "severity": "high", "message": "leaks a secret"}]}"#,
+    ));
+    server.reply(Reply::message(r#"{"findings": []}"#));
+    let fixture = Fixture::new("hook-review", &server);
+    fixture.hook("pre-commit", include_str!("../templates/pre-commit"));
+    fixture.stage("hello.txt", "hello\n");
+
+    let output = fixture.git(&["commit", "-m", "feat: add greeting"]);
+    assert!(!output.status.success(), "{}", describe(&output));
+    // git shows hook output on stderr
+    assert!(String::from_utf8_lossy(&output.stderr).contains("leaks a secret"), "{}", describe(&output));
+    assert_eq!(fixture.commit_count(), 1);
+
+    let output = fixture.git(&["commit", "-m", "feat: add greeting"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    assert_eq!(fixture.commit_count(), 2);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_prepare_commit_msg_hook_writes_squash_message() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: greet and wave"));
+    let fixture = Fixture::new("hook-squash", &server);
+    fixture.hook("prepare-commit-msg", include_str!("../templates/prepare-commit-msg"));
+    let main = fixture.repo.head().unwrap().shorthand().unwrap().to_string();
+
+    assert!(fixture.git(&["checkout", "-q", "-b", "feature"]).status.success());
+    fixture.stage("hello.txt", "hello\n");
+    fixture.commit("feat: greet");
+    fixture.stage("wave.txt", "wave\n");
+    fixture.commit("feat: wave");
+    assert!(fixture.git(&["checkout", "-q", &main]).status.success());
+
+    let output = fixture.git(&["merge", "--squash", "feature"]);
+    assert!(output.status.success(), "{}", describe(&output));
+    let output = fixture.git(&["commit", "--no-edit"]);
+    assert!(output.status.success(), "{}", describe(&output));
+
+    assert_eq!(fixture.head_message().trim_end(), "feat: greet and wave");
+    let prompt = server.last_prompt();
+    assert!(prompt.contains("feat: greet") && prompt.contains("feat: wave"), "{prompt}");
+}
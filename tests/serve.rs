@@ -0,0 +1,102 @@
+//! `ai-commit serve --stdio` end to end: streamed deltas and cancelling a slow request.
+
+mod support;
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use support::{Fixture, MockServer, Reply};
+
+/// A running server, its input and the messages it has written.
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+}
+
+impl Session {
+    fn start(fixture: &Fixture) -> Self {
+        let mut child =
+            fixture.command(&["serve", "--stdio"]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (tx, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                let _ = tx.send(serde_json::from_str(&line).unwrap());
+            }
+        });
+        Self { child, stdin, messages }
+    }
+
+    fn send(&mut self, message: Value) {
+        writeln!(self.stdin, "{message}").unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// Notifications received before the response to `id`, and that response.
+    fn until_response(&self, id: u64) -> (Vec<Value>, Value) {
+        let mut notifications = Vec::new();
+        loop {
+            let message = self.messages.recv_timeout(Duration::from_secs(20)).expect("no response from the server");
+            if message["id"] == id {
+                return (notifications, message);
+            }
+            notifications.push(message);
+        }
+    }
+
+    fn shutdown(mut self) {
+        self.send(json!({ "jsonrpc": "2.0", "id": 99, "method": "shutdown" }));
+        self.until_response(99);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[test]
+fn test_generate_message_streams_deltas() {
+    let server = MockServer::start();
+    server.reply(Reply::chunks(&["feat: add", " greeting"]).chunk_delay(Duration::from_millis(20)));
+    let fixture = Fixture::new("serve-stream", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let mut session = Session::start(&fixture);
+    session.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "generateMessage" }));
+    let (notifications, response) = session.until_response(1);
+    assert_eq!(response["result"]["message"], "feat: add greeting", "{response}");
+
+    let deltas: Vec<&str> = notifications
+        .iter()
+        .filter(|n| n["method"] == "$/delta")
+        .map(|n| n["params"]["text"].as_str().unwrap())
+        .collect();
+    assert_eq!(deltas, ["feat: add", " greeting"]);
+    assert_eq!(server.requests()[0]["stream"], true);
+    session.shutdown();
+}
+
+#[test]
+fn test_cancel_slow_request() {
+    let server = MockServer::start();
+    server.reply(Reply::message("feat: too late").delay(Duration::from_secs(30)));
+    let fixture = Fixture::new("serve-cancel", &server);
+    fixture.stage("hello.txt", "hello\n");
+
+    let mut session = Session::start(&fixture);
+    let started = Instant::now();
+    session.send(json!({ "jsonrpc": "2.0", "id": 1, "method": "generateMessage" }));
+    while server.requests().is_empty() {
+        assert!(started.elapsed() < Duration::from_secs(20), "the request never reached the server");
+        thread::sleep(Duration::from_millis(10));
+    }
+    session.send(json!({ "jsonrpc": "2.0", "method": "$/cancelRequest", "params": { "id": 1 } }));
+
+    let (_, response) = session.until_response(1);
+    assert_eq!(response["error"]["code"], -32800, "{response}");
+    assert!(started.elapsed() < Duration::from_secs(20));
+    session.shutdown();
+}
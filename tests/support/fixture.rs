@@ -0,0 +1,182 @@
+//! Throwaway repositories built with git2, and the `ai-commit` binary run inside them with
+//! its config, cache and data directories kept under the fixture.
+
+use git2::{Repository, Signature};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use super::MockServer;
+
+/// A repository with one commit, `chore: initial commit` adding `README.md`.
+pub struct Fixture {
+    root: PathBuf,
+    pub dir: PathBuf,
+    pub repo: Repository,
+    endpoint: String,
+}
+
+impl Fixture {
+    pub fn new(name: &str, server: &MockServer) -> Self {
+        let root = std::env::temp_dir().join(format!("ai-commit-e2e-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("repo");
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_bool("commit.gpgsign", false).unwrap();
+
+        let fixture = Self { root, dir, repo, endpoint: server.url() };
+        fixture.stage("README.md", "# Fixture\n");
+        fixture.commit("chore: initial commit");
+        fixture
+    }
+
+    /// Write a file in the working tree without staging it.
+    pub fn write(&self, path: &str, content: &str) {
+        let path = self.dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn stage(&self, path: &str, content: &str) {
+        self.write(path, content);
+        let mut index = self.repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Commit the index directly, without ai-commit.
+    pub fn commit(&self, message: &str) {
+        self.commit_as(message, "Test", "test@example.com");
+    }
+
+    /// Commit the index as another author.
+    pub fn commit_as(&self, message: &str, name: &str, email: &str) {
+        let signature = Signature::now(name, email).unwrap();
+        let tree = self.repo.find_tree(self.repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let parent = self.repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap();
+    }
+
+    pub fn head_message(&self) -> String {
+        self.repo.head().unwrap().peel_to_commit().unwrap().message().unwrap().to_string()
+    }
+
+    pub fn commit_count(&self) -> usize {
+        let mut walk = self.repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        walk.count()
+    }
+
+    /// Whether the index differs from HEAD.
+    pub fn has_staged_changes(&self) -> bool {
+        let head = self.repo.head().unwrap().peel_to_tree().unwrap();
+        let diff = self.repo.diff_tree_to_index(Some(&head), None, None).unwrap();
+        diff.deltas().len() > 0
+    }
+
+    /// Replace the config file; without one ai-commit writes the defaults.
+    pub fn config(&self, toml: &str) {
+        let path = self.root.join("config").join("ai-commit").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, toml).unwrap();
+    }
+
+    /// Install an executable hook script with `body` as its shell commands.
+    #[cfg(unix)]
+    pub fn hook(&self, name: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let path = self.repo.path().join("hooks").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// `ai-commit` run in the repository against the mock server.
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_ai-commit"));
+        command.args(args);
+        self.isolate(&mut command);
+        command
+    }
+
+    /// `git` run in the repository, finding this build of `ai-commit` first on `PATH` for
+    /// the hooks that call it.
+    pub fn git(&self, args: &[&str]) -> Output {
+        let mut command = Command::new("git");
+        command.args(args);
+        self.isolate(&mut command);
+        let bin_dir = Path::new(env!("CARGO_BIN_EXE_ai-commit")).parent().unwrap();
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let mut paths = vec![bin_dir.to_path_buf()];
+        paths.extend(std::env::split_paths(&path));
+        command.env("PATH", std::env::join_paths(paths).unwrap());
+        command.output().unwrap()
+    }
+
+    fn isolate(&self, command: &mut Command) {
+        command
+            .current_dir(&self.dir)
+            .env("HOME", self.root.join("home"))
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("XDG_CACHE_HOME", self.root.join("cache"))
+            .env("XDG_DATA_HOME", self.root.join("data"))
+            .env("AI_COMMIT_ENDPOINT", &self.endpoint)
+            .env("AI_COMMIT_API_KEY", "test-key")
+            .env("AI_COMMIT_MODEL", "mock-model")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("NO_COLOR", "1")
+            .env_remove("RUST_BACKTRACE")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_INDEX_FILE");
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.run_with_input(args, "")
+    }
+
+    /// Run with `input` on stdin, e.g. the answer to a confirmation prompt.
+    pub fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+        let mut child =
+            self.command(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    /// The messages kept in `history.jsonl` by the runs so far, with `{"accepted": ID}`
+    /// lines folded into them.
+    pub fn history(&self) -> Vec<serde_json::Value> {
+        let path = self.root.join("data").join("ai-commit").join("history.jsonl");
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let mut messages: Vec<serde_json::Value> = Vec::new();
+        for line in content.lines() {
+            let line: serde_json::Value = serde_json::from_str(line).unwrap();
+            match line["accepted"].as_u64() {
+                Some(id) => messages.iter_mut().filter(|m| m["id"] == id).for_each(|m| m["accepted"] = true.into()),
+                None => messages.push(line),
+            }
+        }
+        messages
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// stdout and stderr of a run, for assertion messages.
+pub fn describe(output: &Output) -> String {
+    format!(
+        "status {:?}\nstdout:\n{}\nstderr:\n{}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
@@ -0,0 +1,209 @@
+//! A scriptable OpenAI-compatible chat completions server on the loopback interface.
+//!
+//! Replies are queued with [`MockServer::reply`] and served in order, one per request; a
+//! request with nothing queued gets the [`MockServer::always`] reply, or a 500 error. Every
+//! request body is kept for assertions. Replies to requests with `"stream": true` are sent
+//! as server-sent events, one per chunk.
+
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// One scripted response.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    /// The message text in pieces; a streamed reply sends one event per piece.
+    chunks: Vec<String>,
+    /// Replaces the completion document for error replies.
+    error_body: Option<String>,
+    finish_reason: String,
+    delay: Duration,
+    chunk_delay: Duration,
+}
+
+impl Reply {
+    /// A completion with `content`, streamed word by word when the request asks for it.
+    pub fn message(content: &str) -> Self {
+        Self::chunks(&content.split_inclusive(' ').collect::<Vec<_>>())
+    }
+
+    /// A completion whose text is `chunks` joined, streamed one chunk per event.
+    pub fn chunks(chunks: &[&str]) -> Self {
+        Self {
+            status: 200,
+            chunks: chunks.iter().map(|c| c.to_string()).collect(),
+            error_body: None,
+            finish_reason: "stop".to_string(),
+            delay: Duration::ZERO,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// An HTTP error with `body` as the response text.
+    pub fn error(status: u16, body: &str) -> Self {
+        Self { status, error_body: Some(body.to_string()), ..Self::chunks(&[]) }
+    }
+
+    pub fn finish_reason(mut self, reason: &str) -> Self {
+        self.finish_reason = reason.to_string();
+        self
+    }
+
+    /// Wait this long before answering.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Wait this long between streamed events.
+    pub fn chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = delay;
+        self
+    }
+
+    fn content(&self) -> String {
+        self.chunks.concat()
+    }
+
+    fn usage(&self) -> Value {
+        let completion = self.chunks.len();
+        json!({ "prompt_tokens": 50, "completion_tokens": completion, "total_tokens": 50 + completion })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    replies: VecDeque<Reply>,
+    fallback: Option<Reply>,
+    requests: Vec<Value>,
+}
+
+/// The server; it runs until the test process exits.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind the mock server");
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let accept_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&accept_state);
+                thread::spawn(move || {
+                    let _ = serve(stream, &state);
+                });
+            }
+        });
+        Self { addr, state }
+    }
+
+    /// The chat completions endpoint, for `AI_COMMIT_ENDPOINT`.
+    pub fn url(&self) -> String {
+        format!("http://{}/v1/chat/completions", self.addr)
+    }
+
+    /// Queue `reply` for the next request without one.
+    pub fn reply(&self, reply: Reply) -> &Self {
+        self.state.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    /// Answer every request with `reply` once the queue is empty.
+    pub fn always(&self, reply: Reply) -> &Self {
+        self.state.lock().unwrap().fallback = Some(reply);
+        self
+    }
+
+    /// The JSON bodies of the requests received so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The user message of the last request.
+    pub fn last_prompt(&self) -> String {
+        let requests = self.requests();
+        let request = requests.last().expect("no request was made");
+        let messages = request["messages"].as_array().unwrap();
+        messages.iter().rev().find(|m| m["role"] == "user").unwrap()["content"].as_str().unwrap().to_string()
+    }
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let streamed = request["stream"] == true;
+
+    let reply = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request);
+        state.replies.pop_front().or_else(|| state.fallback.clone())
+    };
+    let reply = reply.unwrap_or_else(|| Reply::error(500, "mock server: no reply scripted for this request"));
+    thread::sleep(reply.delay);
+    respond(stream, &reply, streamed)
+}
+
+fn respond(mut stream: TcpStream, reply: &Reply, streamed: bool) -> std::io::Result<()> {
+    if let Some(body) = &reply.error_body {
+        return write_response(&mut stream, reply.status, "text/plain", body);
+    }
+    if !streamed {
+        let body = json!({
+            "choices": [{ "message": { "role": "assistant", "content": reply.content() }, "finish_reason": reply.finish_reason }],
+            "usage": reply.usage(),
+        });
+        return write_response(&mut stream, reply.status, "application/json", &body.to_string());
+    }
+
+    // No Content-Length: the body ends when the connection closes
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n")?;
+    for chunk in &reply.chunks {
+        send_event(&mut stream, &json!({ "choices": [{ "delta": { "content": chunk } }] }))?;
+        thread::sleep(reply.chunk_delay);
+    }
+    send_event(&mut stream, &json!({ "choices": [{ "delta": {}, "finish_reason": reply.finish_reason }] }))?;
+    send_event(&mut stream, &json!({ "choices": [], "usage": reply.usage() }))?;
+    write!(stream, "data: [DONE]\n\n")?;
+    stream.flush()
+}
+
+fn send_event(stream: &mut TcpStream, event: &Value) -> std::io::Result<()> {
+    write!(stream, "data: {event}\n\n")?;
+    stream.flush()
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} Mock\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,11 @@
+//! Shared by the end-to-end tests: a mock AI service and fixture repositories that run the
+//! `ai-commit` binary against it, offline.
+
+// Each test crate uses its own part of the harness
+#![allow(dead_code, unused_imports)]
+
+pub mod fixture;
+pub mod mock_server;
+
+pub use fixture::{Fixture, describe};
+pub use mock_server::{MockServer, Reply};